@@ -0,0 +1,104 @@
+//! Expansion of the `bench` attribute.
+
+use proc_macro::{Delimiter, Literal, Span, TokenStream, TokenTree};
+
+use crate::entry::item_span;
+use crate::{code, error, group, is_punct};
+
+/// The number of iterations for benchmarks which don't set one.
+const DEFAULT_ITERATIONS: u32 = 1000;
+
+/// Turns an `async fn` without arguments into a regular function, which runs
+/// the body of the original function repeatedly and reports how long that
+/// took.
+pub(crate) fn expand(args: TokenStream, item: TokenStream) -> TokenStream {
+    let iterations = match parse_args(args) {
+        Ok(iterations) => iterations,
+        Err((span, message)) => return error(span, &format!("`#[bench]` {}", message)),
+    };
+
+    let tokens: Vec<TokenTree> = item.into_iter().collect();
+    let is_ident = |token: &TokenTree, name: &str| match token {
+        TokenTree::Ident(ident) => ident.to_string() == name,
+        _ => false,
+    };
+    let asyncness = tokens.iter().position(|token| is_ident(token, "async"));
+    let fn_token = tokens.iter().position(|token| is_ident(token, "fn"));
+    let not_async_fn = || {
+        error(
+            item_span(&tokens),
+            "`#[bench]` can only be used on an `async fn`",
+        )
+    };
+    let (asyncness, fn_token) = match (asyncness, fn_token) {
+        (Some(asyncness), Some(fn_token)) if asyncness < fn_token => (asyncness, fn_token),
+        _ => return not_async_fn(),
+    };
+    let name = match tokens.get(fn_token + 1) {
+        Some(TokenTree::Ident(name)) => name.clone(),
+        _ => return not_async_fn(),
+    };
+    match tokens.get(fn_token + 2) {
+        Some(TokenTree::Group(params))
+            if params.delimiter() == Delimiter::Parenthesis && params.stream().is_empty() => {}
+        Some(TokenTree::Group(params)) if params.delimiter() == Delimiter::Parenthesis => {
+            let message = format!("`#[bench]` function `{}` can't take arguments", name);
+            return error(params.span(), &message);
+        }
+        _ => return not_async_fn(),
+    }
+    let body = match tokens.last() {
+        Some(TokenTree::Group(body)) if body.delimiter() == Delimiter::Brace => body.stream(),
+        _ => return not_async_fn(),
+    };
+
+    let mut future = code("move || async move");
+    future.extend(Some(group(Delimiter::Brace, body)));
+    let mut args: TokenStream = TokenTree::Literal(Literal::string(&name.to_string())).into();
+    args.extend(code(","));
+    args.extend(Some(TokenTree::Literal(Literal::u32_suffixed(iterations))));
+    args.extend(code(","));
+    args.extend(future);
+
+    let mut run = code("let bench = ::async_macros::executor::bench");
+    run.extend(Some(group(Delimiter::Parenthesis, args)));
+    run.extend(code("; ::std::println!(\"{}\", bench); bench"));
+
+    // Everything up to and including the parameters is kept, apart from the
+    // `async` keyword.
+    let mut output = TokenStream::new();
+    output.extend(tokens[..asyncness].iter().cloned());
+    output.extend(tokens[asyncness + 1..fn_token + 3].iter().cloned());
+    output.extend(code("-> ::async_macros::executor::Bench"));
+    output.extend(Some(group(Delimiter::Brace, run)));
+    output
+}
+
+/// Parses the number of iterations, given as `iterations = n`.
+///
+/// Errors are reported at the offending argument.
+fn parse_args(args: TokenStream) -> Result<u32, (Span, String)> {
+    let args: Vec<TokenTree> = args.into_iter().collect();
+    match args.as_slice() {
+        [] => Ok(DEFAULT_ITERATIONS),
+        [TokenTree::Ident(key), eq, n] if key.to_string() == "iterations" && is_punct(eq, '=') => {
+            match n.to_string().replace('_', "").parse() {
+                Ok(0) | Err(_) => Err((
+                    n.span(),
+                    format!(
+                        "expects a positive integer after `iterations =`, found `{}`",
+                        n
+                    ),
+                )),
+                Ok(n) => Ok(n),
+            }
+        }
+        [arg, ..] => Err((
+            arg.span(),
+            format!(
+                "doesn't accept the argument `{}`, only `iterations = n`",
+                arg
+            ),
+        )),
+    }
+}
@@ -0,0 +1,85 @@
+//! Expansion of the attributes which run an `async fn` on an executor.
+
+use proc_macro::{Delimiter, Span, TokenStream, TokenTree};
+
+use crate::{code, error, group, is_punct};
+
+/// Turns an `async fn` into a regular function, which runs the body of the
+/// original function to completion with `block_on`.
+///
+/// `args` are the arguments of the attribute, either nothing or
+/// `block_on = path`, and `attrs` is prepended to the generated function.
+pub(crate) fn expand(name: &str, args: TokenStream, item: TokenStream, attrs: &str) -> TokenStream {
+    let block_on = match parse_args(args) {
+        Ok(block_on) => block_on,
+        Err((span, message)) => return error(span, &format!("`#[{}]` {}", name, message)),
+    };
+
+    let mut tokens: Vec<TokenTree> = item.into_iter().collect();
+    let asyncness = tokens.iter().position(|token| match token {
+        TokenTree::Ident(ident) => ident.to_string() == "async",
+        _ => false,
+    });
+    let body = match (asyncness, tokens.last()) {
+        (Some(_), Some(TokenTree::Group(body))) if body.delimiter() == Delimiter::Brace => {
+            body.stream()
+        }
+        _ => {
+            let message = format!("`#[{}]` can only be used on an `async fn`", name);
+            return error(item_span(&tokens), &message);
+        }
+    };
+    tokens.remove(asyncness.unwrap());
+    tokens.pop();
+
+    let mut future = code("async");
+    future.extend(Some(group(Delimiter::Brace, body)));
+    let mut call = block_on;
+    call.extend(Some(group(Delimiter::Parenthesis, future)));
+
+    let mut output = code(attrs);
+    output.extend(tokens);
+    output.extend(Some(group(Delimiter::Brace, call)));
+    output
+}
+
+/// Parses the path of the `block_on` function to use, which defaults to the
+/// one of the `executor` feature.
+///
+/// Errors are reported at the offending argument.
+fn parse_args(args: TokenStream) -> Result<TokenStream, (Span, String)> {
+    let mut args = args.into_iter();
+    match (args.next(), args.next()) {
+        (None, _) => Ok(code("::async_macros::executor::block_on")),
+        (Some(TokenTree::Ident(key)), Some(eq))
+            if key.to_string() == "block_on" && is_punct(&eq, '=') =>
+        {
+            let path: TokenStream = args.collect();
+            if path.is_empty() {
+                Err((eq.span(), "expects a path after `block_on =`".into()))
+            } else {
+                Ok(path)
+            }
+        }
+        (Some(arg), _) => Err((
+            arg.span(),
+            format!(
+                "doesn't accept the argument `{}`, only `block_on = path`",
+                arg
+            ),
+        )),
+    }
+}
+
+/// Returns the span to report errors about an item at: its `fn` token if it
+/// has one, or else its first token.
+pub(crate) fn item_span(tokens: &[TokenTree]) -> Span {
+    let fn_token = tokens.iter().find(|token| match token {
+        TokenTree::Ident(ident) => ident.to_string() == "fn",
+        _ => false,
+    });
+    match fn_token.or(tokens.first()) {
+        Some(token) => token.span(),
+        None => Span::call_site(),
+    }
+}
@@ -0,0 +1,113 @@
+//! Procedural macros for `async-macros`.
+//!
+//! This crate is an implementation detail of
+//! [`async-macros`](https://docs.rs/async-macros), which re-exports and
+//! documents everything in here behind its `macros` feature. Depend on that
+//! crate instead.
+
+#![forbid(future_incompatible, rust_2018_idioms)]
+#![deny(missing_debug_implementations, nonstandard_style)]
+#![warn(missing_docs, unreachable_pub)]
+
+use proc_macro::{Delimiter, Group, Ident, Literal, Spacing, Span, TokenStream, TokenTree};
+
+mod bench;
+mod entry;
+mod stream;
+
+/// Implementation of `stream!`.
+///
+/// The input is the path to `async-macros`, followed by a `;` and the body of
+/// the stream.
+#[doc(hidden)]
+#[proc_macro]
+pub fn stream_impl(input: TokenStream) -> TokenStream {
+    stream::expand(input, false)
+}
+
+/// Implementation of `try_stream!`.
+///
+/// The input has the same form as for `stream_impl!`.
+#[doc(hidden)]
+#[proc_macro]
+pub fn try_stream_impl(input: TokenStream) -> TokenStream {
+    stream::expand(input, true)
+}
+
+// Documented where `async-macros` re-exports it.
+#[allow(missing_docs)]
+#[proc_macro_attribute]
+pub fn main(args: TokenStream, item: TokenStream) -> TokenStream {
+    entry::expand("main", args, item, "")
+}
+
+// Documented where `async-macros` re-exports it.
+#[allow(missing_docs)]
+#[proc_macro_attribute]
+pub fn test(args: TokenStream, item: TokenStream) -> TokenStream {
+    entry::expand("test", args, item, "#[::core::prelude::v1::test]")
+}
+
+// Documented where `async-macros` re-exports it.
+#[allow(missing_docs)]
+#[proc_macro_attribute]
+pub fn bench(args: TokenStream, item: TokenStream) -> TokenStream {
+    bench::expand(args, item)
+}
+
+/// Parses generated code, giving it spans which keep its local variables
+/// hidden from the code of the user.
+fn code(source: &str) -> TokenStream {
+    let tokens: TokenStream = source.parse().unwrap();
+    respan(tokens, Span::mixed_site())
+}
+
+fn respan(tokens: TokenStream, span: Span) -> TokenStream {
+    tokens
+        .into_iter()
+        .map(|mut token| {
+            if let TokenTree::Group(group) = &token {
+                let mut respanned = Group::new(group.delimiter(), respan(group.stream(), span));
+                respanned.set_span(span);
+                token = TokenTree::Group(respanned);
+            } else {
+                token.set_span(span);
+            }
+            token
+        })
+        .collect()
+}
+
+/// Wraps tokens in a group created by the macro.
+fn group(delimiter: Delimiter, tokens: TokenStream) -> TokenTree {
+    let mut group = Group::new(delimiter, tokens);
+    group.set_span(Span::mixed_site());
+    TokenTree::Group(group)
+}
+
+/// Splits off the path to `async-macros` at the start of the input.
+fn split_crate_path(input: TokenStream) -> (TokenStream, TokenStream) {
+    let mut tokens = input.into_iter();
+    let path = tokens
+        .by_ref()
+        .take_while(|token| !is_punct(token, ';'))
+        .collect();
+    (path, tokens.collect())
+}
+
+fn is_punct(token: &TokenTree, ch: char) -> bool {
+    match token {
+        TokenTree::Punct(punct) => punct.as_char() == ch && punct.spacing() == Spacing::Alone,
+        _ => false,
+    }
+}
+
+/// Reports an error at `span`, such as the span of the offending argument.
+fn error(span: Span, message: &str) -> TokenStream {
+    let mut output: TokenStream = TokenTree::Ident(Ident::new("compile_error", span)).into();
+    output.extend(code("!"));
+    let message = TokenTree::Literal(Literal::string(message));
+    output.extend(Some(group(Delimiter::Parenthesis, message.into())));
+    output.extend(code(";"));
+    respan(output, span)
+}
@@ -0,0 +1,128 @@
+//! Expansion of the `stream!` and `try_stream!` macros.
+
+use proc_macro::{Delimiter, TokenStream, TokenTree};
+
+use crate::{code, group, split_crate_path};
+
+/// Expands the body of a stream into a call to `stream_fn`, with every
+/// `yield` turned into a send to the stream.
+///
+/// The body of a `fallible` stream yields `Ok` items, and may use `?`: an
+/// error ends the stream after yielding it as an `Err` item.
+pub(crate) fn expand(input: TokenStream, fallible: bool) -> TokenStream {
+    let (path, body) = split_crate_path(input);
+    let mut body = rewrite_yields(body, fallible);
+    if fallible {
+        let mut inner = TokenStream::new();
+        inner.extend(Some(group(Delimiter::Brace, body)));
+        inner.extend(code("::core::result::Result::Ok(())"));
+
+        body = code("let __result: ::core::result::Result<(), _> = async");
+        body.extend(Some(group(Delimiter::Brace, inner)));
+        body.extend(code(
+            ".await;
+            if let ::core::result::Result::Err(__error) = __result {
+                __yielder.send(::core::result::Result::Err(__error)).await;
+            }",
+        ));
+    }
+
+    let mut closure = code("move |__yielder| async move");
+    closure.extend(Some(group(Delimiter::Brace, body)));
+
+    let mut output = path;
+    output.extend(code("::stream_fn"));
+    output.extend(Some(group(Delimiter::Parenthesis, closure)));
+    output
+}
+
+/// Replaces every `yield value` with `__yielder.send(value).await`, wrapping
+/// the value in `Ok` if the stream is `fallible`.
+fn rewrite_yields(input: TokenStream, fallible: bool) -> TokenStream {
+    let mut output: Vec<TokenTree> = Vec::new();
+    let mut tokens = input.into_iter();
+    while let Some(token) = tokens.next() {
+        match token {
+            TokenTree::Ident(ident) if ident.to_string() == "yield" => {
+                let (value, end) = take_value(&mut tokens);
+                let mut value = rewrite_yields(value, fallible);
+                if fallible {
+                    let mut ok = code("::core::result::Result::Ok");
+                    ok.extend(Some(group(Delimiter::Parenthesis, value)));
+                    value = ok;
+                }
+                output.extend(code("__yielder.send"));
+                output.push(group(Delimiter::Parenthesis, value));
+                output.extend(code(".await"));
+                output.extend(end);
+            }
+            // Nested invocations yield to their own stream.
+            TokenTree::Group(group) if !follows_stream_macro(&output) => {
+                let mut rewritten = proc_macro::Group::new(
+                    group.delimiter(),
+                    rewrite_yields(group.stream(), fallible),
+                );
+                rewritten.set_span(group.span());
+                output.push(TokenTree::Group(rewritten));
+            }
+            token => output.push(token),
+        }
+    }
+    output.into_iter().collect()
+}
+
+/// Takes the value of a `yield` from `tokens`, returning it along with the
+/// `;` or `,` which ended it, if any.
+///
+/// A `,` ends the value when it ends a match arm, as in `A => yield 1, B =>
+/// ...`, but not when it separates the generic arguments of a turbofish or the
+/// parameters of a closure.
+fn take_value(tokens: &mut impl Iterator<Item = TokenTree>) -> (TokenStream, Option<TokenTree>) {
+    let mut value: Vec<TokenTree> = Vec::new();
+    // How deep the value is in the angle brackets of turbofishes.
+    let mut generics = 0usize;
+    // Whether the value is in the parameters of a closure.
+    let mut params = false;
+    for token in tokens {
+        if let TokenTree::Punct(punct) = &token {
+            let previous = match value.last() {
+                Some(TokenTree::Punct(previous)) => Some(previous.as_char()),
+                _ => None,
+            };
+            match punct.as_char() {
+                ';' | ',' if generics == 0 && !params => {
+                    return (value.into_iter().collect(), Some(token));
+                }
+                '<' if generics > 0 || previous == Some(':') => generics += 1,
+                // The `>` of `->` doesn't close anything.
+                '>' if generics > 0 && previous != Some('-') => generics -= 1,
+                '|' if params => params = false,
+                '|' if is_closure_start(&value) => params = true,
+                _ => {}
+            }
+        }
+        value.push(token);
+    }
+    (value.into_iter().collect(), None)
+}
+
+/// Returns whether a `|` following `tokens` starts the parameters of a
+/// closure, rather than being a binary or.
+fn is_closure_start(tokens: &[TokenTree]) -> bool {
+    match tokens {
+        [] => true,
+        [.., TokenTree::Ident(ident)] => ident.to_string() == "move",
+        _ => false,
+    }
+}
+
+/// Returns whether the tokens end with `stream!` or `try_stream!`.
+fn follows_stream_macro(tokens: &[TokenTree]) -> bool {
+    match tokens {
+        [.., TokenTree::Ident(ident), TokenTree::Punct(bang)] if bang.as_char() == '!' => {
+            let name = ident.to_string();
+            name == "stream" || name == "try_stream"
+        }
+        _ => false,
+    }
+}
@@ -0,0 +1,67 @@
+/// Polls a future once and asserts that it is ready, returning its output.
+///
+/// The future must be `Unpin`; pass `&mut fut` or `fut.as_mut()` to keep
+/// using it afterwards. Streams can be checked by passing
+/// `stream.next()`. The second argument is the `&mut Context` to poll with.
+///
+/// # Panics
+///
+/// Panics if the future is pending.
+///
+/// # Examples
+///
+/// ```
+/// use async_macros::task::noop_context;
+/// use async_macros::{assert_pending, assert_ready, future};
+///
+/// let mut cx = noop_context();
+///
+/// let mut a = future::ready(1u8);
+/// assert_eq!(assert_ready!(&mut a, &mut cx), 1);
+///
+/// let mut b = future::pending::<u8>();
+/// assert_pending!(&mut b, &mut cx);
+/// ```
+#[macro_export]
+macro_rules! assert_ready {
+    ($fut:expr, $cx:expr $(,)?) => {{
+        let mut fut = $fut;
+        let cx: &mut $crate::utils::task::Context<'_> = $cx;
+        match $crate::utils::future::Future::poll($crate::utils::pin::Pin::new(&mut fut), cx) {
+            $crate::utils::task::Poll::Ready(output) => output,
+            $crate::utils::task::Poll::Pending => {
+                panic!(
+                    "expected `{}` to be ready, but it was pending",
+                    stringify!($fut)
+                )
+            }
+        }
+    }};
+}
+
+/// Polls a future once and asserts that it is pending.
+///
+/// The future must be `Unpin`; pass `&mut fut` or `fut.as_mut()` to keep
+/// using it afterwards. Streams can be checked by passing
+/// `stream.next()`. The second argument is the `&mut Context` to poll with.
+///
+/// See [`assert_ready!`] for an example.
+///
+/// # Panics
+///
+/// Panics if the future is ready.
+#[macro_export]
+macro_rules! assert_pending {
+    ($fut:expr, $cx:expr $(,)?) => {{
+        let mut fut = $fut;
+        let cx: &mut $crate::utils::task::Context<'_> = $cx;
+        if let $crate::utils::task::Poll::Ready(_) =
+            $crate::utils::future::Future::poll($crate::utils::pin::Pin::new(&mut fut), cx)
+        {
+            panic!(
+                "expected `{}` to be pending, but it was ready",
+                stringify!($fut)
+            );
+        }
+    }};
+}
@@ -0,0 +1,158 @@
+//! Definition of the `AtomicWaker` slot
+
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::hint;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use core::task::Waker;
+
+/// A slot holding the waker of a single task, which can be shared between
+/// the task and whoever needs to wake it.
+///
+/// This is the building block for futures which are woken from elsewhere: the
+/// future registers its waker every time it returns `Pending`, and the other
+/// side calls [`wake`](AtomicWaker::wake) once there is progress to be made.
+/// It doesn't lock: registering and waking only ever take a few atomic
+/// operations, and never wait on each other, so it can be woken from other
+/// threads as well as from interrupt handlers.
+///
+/// # Examples
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::task::AtomicWaker;
+/// use async_macros::{join, poll_fn};
+/// use std::sync::atomic::{AtomicBool, Ordering};
+/// use std::task::Poll;
+///
+/// let waker = AtomicWaker::new();
+/// let ready = AtomicBool::new(false);
+///
+/// let wait = poll_fn(|cx| {
+///     waker.register(cx.waker());
+///     if ready.load(Ordering::SeqCst) {
+///         Poll::Ready(())
+///     } else {
+///         Poll::Pending
+///     }
+/// });
+/// let signal = async {
+///     ready.store(true, Ordering::SeqCst);
+///     waker.wake();
+/// };
+///
+/// join!(wait, signal).await;
+/// # });
+/// ```
+pub struct AtomicWaker {
+    state: AtomicUsize,
+    waker: UnsafeCell<Option<Waker>>,
+}
+
+// The waker is only accessed by whoever moved `state` away from `WAITING`:
+// `register` sets `REGISTERING`, and `take` sets `WAKING`. Whoever finds the
+// other bit set leaves the waker to them instead of waiting.
+const WAITING: usize = 0;
+const REGISTERING: usize = 0b01;
+const WAKING: usize = 0b10;
+
+// SAFETY: access to `waker` is serialized through `state`, and wakers are
+// `Send` and `Sync`.
+unsafe impl Send for AtomicWaker {}
+unsafe impl Sync for AtomicWaker {}
+
+impl AtomicWaker {
+    /// Create a new, empty `AtomicWaker`.
+    pub const fn new() -> Self {
+        Self {
+            state: AtomicUsize::new(WAITING),
+            waker: UnsafeCell::new(None),
+        }
+    }
+
+    /// Registers `waker` to be woken by the next call to
+    /// [`wake`](AtomicWaker::wake), replacing the previous one.
+    ///
+    /// The waker isn't cloned if the registered one already wakes the same
+    /// task. If the `AtomicWaker` is woken while the waker is registered,
+    /// `waker` is woken right away instead.
+    pub fn register(&self, waker: &Waker) {
+        match self
+            .state
+            .compare_exchange(WAITING, REGISTERING, Ordering::Acquire, Ordering::Acquire)
+            .unwrap_or_else(|state| state)
+        {
+            WAITING => {
+                // SAFETY: setting `REGISTERING` gave us access to the waker.
+                let slot = unsafe { &mut *self.waker.get() };
+                match slot {
+                    Some(current) if current.will_wake(waker) => {}
+                    _ => *slot = Some(waker.clone()),
+                }
+
+                let registered = self.state.compare_exchange(
+                    REGISTERING,
+                    WAITING,
+                    Ordering::AcqRel,
+                    Ordering::Acquire,
+                );
+                if registered.is_err() {
+                    // `take` was called meanwhile, and left the waker to us.
+                    // We still have access to it, as `WAKING` is only cleared
+                    // by whoever set `REGISTERING`.
+                    let waker = slot.take();
+                    self.state.swap(WAITING, Ordering::AcqRel);
+                    if let Some(waker) = waker {
+                        waker.wake();
+                    }
+                }
+            }
+            WAKING => {
+                // The previous waker is being taken out to be woken, so wake
+                // this one right away as well.
+                waker.wake_by_ref();
+                hint::spin_loop();
+            }
+            // Another thread is registering a waker at the same time, which is
+            // a race between callers: one of the wakers wins.
+            _ => {}
+        }
+    }
+
+    /// Wakes the registered waker, if any, and removes it.
+    pub fn wake(&self) {
+        if let Some(waker) = self.take() {
+            waker.wake();
+        }
+    }
+
+    /// Removes the registered waker, if any, without waking it.
+    ///
+    /// If a waker is being registered at the same time, `None` is returned, and
+    /// [`register`](AtomicWaker::register) wakes that waker once it's done.
+    pub fn take(&self) -> Option<Waker> {
+        match self.state.fetch_or(WAKING, Ordering::AcqRel) {
+            WAITING => {
+                // SAFETY: setting `WAKING` gave us access to the waker.
+                let waker = unsafe { (*self.waker.get()).take() };
+                self.state.fetch_and(!WAKING, Ordering::Release);
+                waker
+            }
+            // Either `register` is running and takes care of the waker once
+            // it sees `WAKING`, or another `take` already is.
+            _ => None,
+        }
+    }
+}
+
+impl Default for AtomicWaker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for AtomicWaker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AtomicWaker").finish()
+    }
+}
@@ -0,0 +1,41 @@
+/// Binds the expressions passed to a macro to local variables, and passes
+/// those on to the macro.
+///
+/// Every expression is bound to a variable named `fut`, which is a different
+/// variable each time as they are introduced by different expansions of this
+/// macro. The variables are passed on as `fut as "<expression>"`, so that the
+/// macro can name the futures after the expressions rather than after the
+/// variables, see [`future_name!`](crate::utils::future_name). `prefix` is
+/// passed on to the macro in front of the variables.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __bind_futures {
+    ($macro:ident [$($prefix:tt)*] [$($bound:tt)*] $fut:expr $(, $($rest:tt)*)?) => {
+        $crate::utils::bind_futures!(
+            $macro [$($prefix)*] [$($bound)* (fut, $fut, stringify!($fut))] $($($rest)*)?
+        )
+    };
+    ($macro:ident [$($prefix:tt)*] [$(($name:ident, $fut:expr, $desc:expr))*]) => {
+        // The expressions are evaluated outside of the expansion of the macro,
+        // so that they can't refer to its local variables.
+        match ($($fut,)*) {
+            ($($name,)*) => $crate::$macro!($($prefix)* $($name as $desc),*),
+        }
+    };
+}
+
+/// Returns the name of a future passed to a macro.
+///
+/// This is the expression the future was bound from by
+/// [`bind_futures!`](crate::utils::bind_futures) if it is followed by `as
+/// "<expression>"`, or the name of its variable otherwise.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __future_name {
+    ($fut:ident as $name:expr) => {
+        $name
+    };
+    ($fut:ident) => {
+        stringify!($fut)
+    };
+}
@@ -0,0 +1,130 @@
+//! A growable merge of streams of different types.
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::fmt;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+use futures_core::stream::{FusedStream, Stream};
+
+/// A stream merging streams of different types which yield the same items.
+///
+/// Every stream pushed into it is boxed, so that channel receivers, intervals
+/// and mapped streams can be merged without an enum wrapping all of them, and
+/// without being `Unpin`. The streams are polled in turn like those of
+/// [`join_stream!`], starting after the stream which yielded last, and are
+/// dropped as soon as they end. This is what `join_stream!(boxed; ...)`
+/// returns.
+///
+/// The stream yields `None` whenever it holds no streams, but it can be
+/// reused by pushing more streams into it. It reports itself as terminated
+/// through [`FusedStream`] from the moment it yields `None` until a stream is
+/// pushed into it again.
+///
+/// # Examples
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::BoxedJoinStream;
+/// use futures::stream::{self, StreamExt};
+///
+/// let mut streams = BoxedJoinStream::new();
+/// streams.push(stream::iter(vec![1u8, 2]));
+/// streams.push(stream::once(async { 3u8 }));
+/// streams.push(stream::repeat(4u8).take(1).map(|n| n * 2));
+///
+/// assert_eq!(streams.collect::<Vec<_>>().await, vec![1, 3, 8, 2]);
+/// # });
+/// ```
+#[must_use = "streams do nothing unless polled"]
+pub struct BoxedJoinStream<'a, T> {
+    streams: Vec<Pin<Box<dyn Stream<Item = T> + Send + 'a>>>,
+    /// The index of the stream to poll first.
+    start: usize,
+    terminated: bool,
+}
+
+impl<'a, T> BoxedJoinStream<'a, T> {
+    /// Create a new, empty merge.
+    pub fn new() -> Self {
+        Self {
+            streams: Vec::new(),
+            start: 0,
+            terminated: false,
+        }
+    }
+
+    /// Returns the number of streams which haven't ended yet.
+    pub fn len(&self) -> usize {
+        self.streams.len()
+    }
+
+    /// Returns `true` if the merge holds no streams.
+    pub fn is_empty(&self) -> bool {
+        self.streams.is_empty()
+    }
+
+    /// Push a stream into the merge.
+    ///
+    /// The stream is polled the next time the merge is polled.
+    pub fn push<S>(&mut self, stream: S)
+    where
+        S: Stream<Item = T> + Send + 'a,
+    {
+        self.streams.push(Box::pin(stream));
+        self.terminated = false;
+    }
+}
+
+impl<T> Default for BoxedJoinStream<'_, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> fmt::Debug for BoxedJoinStream<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BoxedJoinStream")
+            .field("len", &self.streams.len())
+            .finish()
+    }
+}
+
+impl<T> Stream for BoxedJoinStream<'_, T> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let this = &mut *self;
+        let len = this.streams.len();
+        let mut index = this.start;
+        for _ in 0..len {
+            if index >= this.streams.len() {
+                index = 0;
+            }
+            match this.streams[index].as_mut().poll_next(cx) {
+                Poll::Ready(Some(item)) => {
+                    this.start = index + 1;
+                    return Poll::Ready(Some(item));
+                }
+                // The next stream takes the place of the one which ended.
+                Poll::Ready(None) => drop(this.streams.remove(index)),
+                Poll::Pending => index += 1,
+            }
+        }
+
+        this.start = index;
+        if this.streams.is_empty() {
+            this.terminated = true;
+            Poll::Ready(None)
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+impl<T> FusedStream for BoxedJoinStream<'_, T> {
+    fn is_terminated(&self) -> bool {
+        self.terminated
+    }
+}
@@ -0,0 +1,131 @@
+//! Definition of the `WithBudget` cooperative polling budget
+
+use core::cell::Cell;
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+use pin_project_lite::pin_project;
+
+std::thread_local! {
+    /// The polls left in the budget of the task being polled, if any.
+    static BUDGET: Cell<Option<usize>> = const { Cell::new(None) };
+}
+
+/// Runs a future with a budget of `polls` child polls per wakeup.
+///
+/// Every time a future passed to `join!`, `try_join!`, `select!`,
+/// `try_select!` or `join_limited!`, or a stream passed to `join_stream!`,
+/// `try_join_stream!` or `interleave_stream!`, is polled, it consumes one poll from the budget of its task. Once the
+/// budget is used up, children are no longer polled: they wake the task and
+/// return `Poll::Pending` instead, so that the task yields to the executor
+/// and other tasks get to run. The budget is refilled every time the
+/// returned future is polled.
+///
+/// If a budget is already active, the smaller of the two applies.
+///
+/// # Examples
+///
+/// ```
+/// use async_macros::{future, join, task};
+/// use std::future::Future;
+/// use std::pin::pin;
+///
+/// let busy = async {
+///     for _ in 0..10 {
+///         let a = future::ready(1u8);
+///         let b = future::ready(2u8);
+///         join!(a, b).await;
+///     }
+/// };
+///
+/// // Without a budget this would complete in a single poll.
+/// let mut busy = pin!(task::with_budget(4, busy));
+/// let mut cx = task::noop_context();
+/// let mut polls = 1;
+/// while busy.as_mut().poll(&mut cx).is_pending() {
+///     polls += 1;
+/// }
+/// assert_eq!(polls, 5);
+/// ```
+pub fn with_budget<F: Future>(polls: usize, future: F) -> WithBudget<F> {
+    WithBudget { polls, future }
+}
+
+pin_project! {
+    /// Future for the [`with_budget`] function.
+    #[must_use = "futures do nothing unless you `.await` or poll them"]
+    pub struct WithBudget<F> {
+        polls: usize,
+        #[pin]
+        future: F,
+    }
+}
+
+impl<F: Future> Future for WithBudget<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        let this = self.project();
+
+        /// Takes the polls used by this future from the outer budget on drop,
+        /// even if polling panics.
+        struct Restore {
+            outer: Option<usize>,
+            budget: usize,
+        }
+
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                let left = BUDGET.with(Cell::get).unwrap_or(0);
+                let used = self.budget - left;
+                BUDGET.with(|budget| budget.set(self.outer.map(|outer| outer - used)));
+            }
+        }
+
+        let outer = BUDGET.with(Cell::get);
+        let polls = *this.polls;
+        let budget = outer.map_or(polls, |outer| outer.min(polls));
+        BUDGET.with(|cell| cell.set(Some(budget)));
+        let _restore = Restore { outer, budget };
+        this.future.poll(cx)
+    }
+}
+
+impl<F> fmt::Debug for WithBudget<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WithBudget")
+            .field("polls", &self.polls)
+            .finish()
+    }
+}
+
+/// Consumes one poll from the budget of the current task.
+///
+/// This returns `Poll::Ready` if there was a poll left, or if the task has no
+/// budget. Otherwise it wakes the task and returns `Poll::Pending`, and the
+/// caller should return `Poll::Pending` as well. Combinators polling futures
+/// of their own can use this to take part in [`with_budget`].
+pub fn poll_budget(cx: &mut Context<'_>) -> Poll<()> {
+    let left = BUDGET.with(|budget| match budget.get() {
+        Some(0) => false,
+        Some(left) => {
+            budget.set(Some(left - 1));
+            true
+        }
+        None => true,
+    });
+    if left {
+        Poll::Ready(())
+    } else {
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+/// Returns the polls left in the budget of the current task, or `None` if it
+/// has no budget.
+pub fn remaining_budget() -> Option<usize> {
+    BUDGET.with(Cell::get)
+}
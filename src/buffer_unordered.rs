@@ -0,0 +1,130 @@
+//! Definition of the `BufferUnordered` stream adapter
+
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+use futures_core::stream::{FusedStream, Stream};
+use pin_project_lite::pin_project;
+
+use crate::FuturesUnordered;
+
+/// Maps the items of a stream through an async function, with at most
+/// `limit` of the resulting futures running at once.
+///
+/// The returned stream yields the outputs of the futures as they complete,
+/// which may be in a different order than the items they were created from.
+/// A new item is only pulled from the underlying stream while fewer than
+/// `limit` futures are running.
+///
+/// # Panics
+///
+/// Panics if `limit` is zero.
+///
+/// # Examples
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::buffer_unordered;
+/// use futures::stream::{self, StreamExt};
+///
+/// let urls = stream::iter(vec!["a", "bb", "ccc"]);
+/// let fetched = buffer_unordered(urls, 2, |url| async move { url.len() });
+///
+/// let mut lengths = fetched.collect::<Vec<_>>().await;
+/// lengths.sort();
+/// assert_eq!(lengths, vec![1, 2, 3]);
+/// # });
+/// ```
+pub fn buffer_unordered<S, F, Fut>(stream: S, limit: usize, f: F) -> BufferUnordered<S, F, Fut>
+where
+    S: Stream,
+    F: FnMut(S::Item) -> Fut,
+    Fut: Future,
+{
+    assert!(
+        limit > 0,
+        "`buffer_unordered` needs a limit of at least one"
+    );
+    BufferUnordered {
+        stream,
+        stream_done: false,
+        f,
+        running: FuturesUnordered::new(),
+        limit,
+    }
+}
+
+pin_project! {
+    /// Stream for the [`buffer_unordered`] function.
+    #[must_use = "streams do nothing unless polled"]
+    pub struct BufferUnordered<S, F, Fut> {
+        #[pin]
+        stream: S,
+        stream_done: bool,
+        f: F,
+        running: FuturesUnordered<Fut>,
+        limit: usize,
+    }
+}
+
+impl<S, F, Fut> Stream for BufferUnordered<S, F, Fut>
+where
+    S: Stream,
+    F: FnMut(S::Item) -> Fut,
+    Fut: Future,
+{
+    type Item = Fut::Output;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Fut::Output>> {
+        let mut this = self.project();
+
+        while !*this.stream_done && this.running.len() < *this.limit {
+            match this.stream.as_mut().poll_next(cx) {
+                Poll::Ready(Some(item)) => this.running.push((this.f)(item)),
+                Poll::Ready(None) => *this.stream_done = true,
+                Poll::Pending => break,
+            }
+        }
+
+        match Pin::new(this.running).poll_next(cx) {
+            Poll::Ready(Some(output)) => Poll::Ready(Some(output)),
+            // The stream may still produce more items.
+            Poll::Ready(None) if !*this.stream_done => Poll::Pending,
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let running = self.running.len();
+        if self.stream_done {
+            return (running, Some(running));
+        }
+        let (lower, upper) = self.stream.size_hint();
+        let lower = lower.saturating_add(running);
+        let upper = upper.and_then(|upper| upper.checked_add(running));
+        (lower, upper)
+    }
+}
+
+impl<S, F, Fut> FusedStream for BufferUnordered<S, F, Fut>
+where
+    S: Stream,
+    F: FnMut(S::Item) -> Fut,
+    Fut: Future,
+{
+    fn is_terminated(&self) -> bool {
+        self.stream_done && self.running.is_empty()
+    }
+}
+
+impl<S, F, Fut> fmt::Debug for BufferUnordered<S, F, Fut> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BufferUnordered")
+            .field("running", &self.running.len())
+            .field("limit", &self.limit)
+            .finish()
+    }
+}
@@ -0,0 +1,29 @@
+//! Definition of the `Cancelled` child information
+
+/// A future which was cancelled by a macro before it completed.
+///
+/// This is passed to the `on_cancel` hooks of [`try_join!`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cancelled {
+    index: usize,
+    name: &'static str,
+}
+
+impl Cancelled {
+    /// Create a new `Cancelled` for the future at `index` among the futures
+    /// passed to a macro, named `name`.
+    pub fn new(index: usize, name: &'static str) -> Self {
+        Self { index, name }
+    }
+
+    /// Returns the index of the future among the futures passed to the macro.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Returns the name of the future: the name of its variable, or the
+    /// expression it was passed to the macro as.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+}
@@ -0,0 +1,124 @@
+//! Definition of the `catch_unwind` function
+
+use core::any::Any;
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use std::panic::{self, AssertUnwindSafe};
+
+use pin_project_lite::pin_project;
+
+/// Catches panics while polling a future.
+///
+/// The returned future resolves to `Ok` with the output of the future, or to
+/// `Err` with the [`Panicked`] payload if polling it panicked. The future is
+/// not polled again after a panic.
+///
+/// The future doesn't need to be [`UnwindSafe`](std::panic::UnwindSafe):
+/// it is dropped without being polled again once it panicked, but values it
+/// shares with other futures may be observed in an inconsistent state.
+///
+/// # Examples
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::catch_unwind;
+///
+/// let output = catch_unwind(async { panic!("oh no") }).await;
+/// assert_eq!(output.unwrap_err().message(), Some("oh no"));
+/// # });
+/// ```
+pub fn catch_unwind<F: Future>(future: F) -> CatchUnwind<F> {
+    CatchUnwind {
+        future: Some(future),
+    }
+}
+
+pin_project! {
+    /// Future for the [`catch_unwind`] function.
+    #[must_use = "futures do nothing unless you `.await` or poll them"]
+    pub struct CatchUnwind<F> {
+        #[pin]
+        future: Option<F>,
+    }
+}
+
+impl<F: Future> Future for CatchUnwind<F> {
+    type Output = Result<F::Output, Panicked>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut this = self.project();
+        let future = match this.future.as_mut().as_pin_mut() {
+            Some(future) => future,
+            None => panic!("`CatchUnwind` polled after completion"),
+        };
+        let output = panic::catch_unwind(AssertUnwindSafe(|| future.poll(cx)));
+        match output {
+            Ok(Poll::Pending) => Poll::Pending,
+            Ok(Poll::Ready(output)) => {
+                this.future.set(None);
+                Poll::Ready(Ok(output))
+            }
+            Err(payload) => {
+                this.future.set(None);
+                Poll::Ready(Err(Panicked { payload }))
+            }
+        }
+    }
+}
+
+impl<F> fmt::Debug for CatchUnwind<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CatchUnwind").finish()
+    }
+}
+
+/// The error returned when a future panicked.
+pub struct Panicked {
+    payload: Box<dyn Any + Send>,
+}
+
+impl Panicked {
+    /// Returns the message the future panicked with, if it was a string.
+    pub fn message(&self) -> Option<&str> {
+        match self.payload.downcast_ref::<&'static str>() {
+            Some(message) => Some(message),
+            None => self.payload.downcast_ref::<String>().map(String::as_str),
+        }
+    }
+
+    /// Returns the payload the future panicked with.
+    pub fn payload(&self) -> &(dyn Any + Send) {
+        &*self.payload
+    }
+
+    /// Consumes the error, returning the payload the future panicked with.
+    pub fn into_payload(self) -> Box<dyn Any + Send> {
+        self.payload
+    }
+
+    /// Resumes the panic, with the same payload.
+    pub fn resume_unwind(self) -> ! {
+        panic::resume_unwind(self.payload)
+    }
+}
+
+impl fmt::Debug for Panicked {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Panicked")
+            .field("message", &self.message())
+            .finish()
+    }
+}
+
+impl fmt::Display for Panicked {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.message() {
+            Some(message) => write!(f, "the future panicked: {}", message),
+            None => f.write_str("the future panicked"),
+        }
+    }
+}
+
+impl std::error::Error for Panicked {}
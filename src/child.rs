@@ -0,0 +1,218 @@
+//! Definition of the `ChildFuture` and `ChildStream` wrappers around the
+//! futures and streams of a macro.
+
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+use futures_core::stream::{FusedStream, Stream};
+use pin_project_lite::pin_project;
+
+/// Wraps a child future of a macro, to make it observable.
+///
+/// With the `tracing` feature every child gets a `child` span at the `TRACE`
+/// level, recording the `combinator` it belongs to, its `index` among the
+/// children and its `name`. The span is a child of the span which is current
+/// when the future is wrapped, and is entered every time the future is
+/// polled, so that spans follow the logical task rather than whichever thread
+/// polls it.
+///
+/// With the `poll-observer` feature every poll of the child is reported to
+/// the [`PollObserver`](crate::observer::PollObserver), if one is set, and
+/// with the `metrics` feature its metrics are passed to the
+/// [`Recorder`](crate::metrics::Recorder), if one is set.
+///
+/// With the `budget` feature every poll of the child consumes one poll from
+/// the budget of its task, see [`with_budget`](crate::task::with_budget).
+///
+/// Without any of these features the future is polled as is.
+pub fn child<F: Future>(
+    combinator: &'static str,
+    index: usize,
+    name: &'static str,
+    future: F,
+) -> ChildFuture<F> {
+    ChildFuture {
+        inner: Inner::new(combinator, index, name),
+        future,
+    }
+}
+
+/// Wraps a child stream of a macro, to make it observable.
+///
+/// This is the same as [`child`], for streams. A stream completes when it
+/// ends.
+pub fn child_stream<S: Stream>(
+    combinator: &'static str,
+    index: usize,
+    name: &'static str,
+    stream: S,
+) -> ChildStream<S> {
+    ChildStream {
+        inner: Inner::new(combinator, index, name),
+        stream,
+    }
+}
+
+pin_project! {
+    /// Future for the [`child`] function.
+    #[must_use = "futures do nothing unless you `.await` or poll them"]
+    pub struct ChildFuture<F> {
+        inner: Inner,
+        #[pin]
+        future: F,
+    }
+}
+
+pin_project! {
+    /// Stream for the [`child_stream`] function.
+    #[must_use = "streams do nothing unless polled"]
+    pub struct ChildStream<S> {
+        inner: Inner,
+        #[pin]
+        stream: S,
+    }
+}
+
+/// The state needed to observe a child, depending on the features.
+struct Inner {
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+    #[cfg(feature = "poll-observer")]
+    child: crate::observer::Child,
+    #[cfg(feature = "metrics")]
+    metrics: crate::metrics::State,
+}
+
+impl Inner {
+    fn new(combinator: &'static str, index: usize, name: &'static str) -> Self {
+        let _ = (combinator, index, name);
+        Self {
+            #[cfg(feature = "tracing")]
+            span: tracing::trace_span!("child", combinator, index, name),
+            #[cfg(feature = "poll-observer")]
+            child: crate::observer::Child {
+                combinator,
+                index,
+                name,
+            },
+            #[cfg(feature = "metrics")]
+            metrics: crate::metrics::State::new(),
+        }
+    }
+
+    /// Polls the child through `poll`, which returns whether the child
+    /// completed along with the result.
+    fn poll<T>(
+        &mut self,
+        cx: &mut Context<'_>,
+        poll: impl FnOnce(&mut Context<'_>) -> (bool, Poll<T>),
+    ) -> Poll<T> {
+        #[cfg(feature = "budget")]
+        {
+            if crate::task::poll_budget(cx).is_pending() {
+                return Poll::Pending;
+            }
+        }
+
+        #[cfg(feature = "tracing")]
+        let _enter = self.span.enter();
+
+        #[cfg(feature = "poll-observer")]
+        let observer = crate::observer::get();
+        #[cfg(feature = "poll-observer")]
+        {
+            if let Some(observer) = observer {
+                observer.on_poll_start(&self.child);
+            }
+        }
+
+        #[cfg(feature = "metrics")]
+        let (completed, output) = {
+            let mut completed = false;
+            let output = self.metrics.poll(cx, |cx| {
+                let (done, output) = poll(cx);
+                completed = done;
+                output
+            });
+            (completed, output)
+        };
+        #[cfg(not(feature = "metrics"))]
+        let (completed, output) = poll(cx);
+
+        #[cfg(feature = "poll-observer")]
+        {
+            if let Some(observer) = observer {
+                observer.on_poll_end(&self.child, output.is_ready());
+                if completed {
+                    observer.on_complete(&self.child);
+                }
+            }
+        }
+        #[cfg(feature = "metrics")]
+        {
+            if completed {
+                self.metrics.finish(&self.child, true);
+            }
+        }
+
+        let _ = completed;
+        output
+    }
+}
+
+#[cfg(feature = "metrics")]
+impl Drop for Inner {
+    fn drop(&mut self) {
+        self.metrics.finish(&self.child, false);
+    }
+}
+
+impl<F: Future> Future for ChildFuture<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        let this = self.project();
+        let future = this.future;
+        this.inner.poll(cx, |cx| {
+            let output = future.poll(cx);
+            (output.is_ready(), output)
+        })
+    }
+}
+
+impl<F> fmt::Debug for ChildFuture<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChildFuture").finish()
+    }
+}
+
+impl<S: Stream> Stream for ChildStream<S> {
+    type Item = S::Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<S::Item>> {
+        let this = self.project();
+        let stream = this.stream;
+        this.inner.poll(cx, |cx| {
+            let output = stream.poll_next(cx);
+            (matches!(output, Poll::Ready(None)), output)
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.stream.size_hint()
+    }
+}
+
+impl<S: FusedStream> FusedStream for ChildStream<S> {
+    fn is_terminated(&self) -> bool {
+        self.stream.is_terminated()
+    }
+}
+
+impl<S> fmt::Debug for ChildStream<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChildStream").finish()
+    }
+}
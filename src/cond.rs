@@ -0,0 +1,35 @@
+/// Awaits one of two futures, picked by a runtime condition.
+///
+/// `cond!(flag, a, b)` awaits `a` if `flag` is true, and `b` otherwise. The
+/// futures may be of different types, as long as they have the same output
+/// type. Only the picked future expression is evaluated, so the other one is
+/// never created.
+///
+/// To keep the picked future around without awaiting it right away, build an
+/// [`Either`](crate::Either) instead.
+///
+/// The condition is evaluated when the returned future is first polled.
+///
+/// # Examples
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::{cond, future};
+///
+/// let cached = true;
+/// let value = cond!(cached, future::ready(1u8), async { 2u8 }).await;
+/// assert_eq!(value, 1);
+/// # });
+/// ```
+#[macro_export]
+macro_rules! cond {
+    ($flag:expr, $a:expr, $b:expr $(,)?) => {
+        async {
+            if $flag {
+                $a.await
+            } else {
+                $b.await
+            }
+        }
+    };
+}
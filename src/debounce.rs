@@ -0,0 +1,126 @@
+//! Definition of the `Debounce` stream adapter
+
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use core::time::Duration;
+
+use futures_core::stream::{FusedStream, Stream};
+use pin_project_lite::pin_project;
+
+use crate::time::Timer;
+
+/// How many items a [`Debounce`] pulls from its stream per poll, so that a
+/// stream which is always ready can't starve the executor.
+const POLLS_PER_WAKEUP: usize = 32;
+
+/// Only yields an item once a stream has been quiet for `duration`,
+/// yielding the latest item it received.
+///
+/// Every item received restarts the wait, which is measured with `timer`,
+/// and replaces the item received before it. This is what search as you
+/// type and filesystem watchers need, where only the last of a burst of
+/// events matters. Once the stream ends, the item it received last is
+/// yielded right away.
+///
+/// # Examples
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::time::{self, ThreadTimer};
+/// use futures::stream::{self, StreamExt};
+/// use std::time::Duration;
+///
+/// let keystrokes = stream::iter(vec!["r", "ru", "rus", "rust"]).chain(stream::pending());
+/// let mut queries = time::debounce(ThreadTimer, keystrokes, Duration::from_millis(10));
+///
+/// assert_eq!(queries.next().await, Some("rust"));
+/// # });
+/// ```
+pub fn debounce<T, S>(timer: T, stream: S, duration: Duration) -> Debounce<T, S>
+where
+    T: Timer,
+    S: Stream,
+{
+    Debounce {
+        stream,
+        timer,
+        duration,
+        sleep: None,
+        latest: None,
+        stream_done: false,
+        terminated: false,
+    }
+}
+
+pin_project! {
+    /// Stream for the [`debounce`] function.
+    #[must_use = "streams do nothing unless polled"]
+    pub struct Debounce<T: Timer, S: Stream> {
+        #[pin]
+        stream: S,
+        timer: T,
+        duration: Duration,
+        // The wait since the latest item, which is set along with `latest`.
+        #[pin]
+        sleep: Option<T::Sleep>,
+        latest: Option<S::Item>,
+        stream_done: bool,
+        terminated: bool,
+    }
+}
+
+impl<T: Timer, S: Stream> Stream for Debounce<T, S> {
+    type Item = S::Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<S::Item>> {
+        let mut this = self.project();
+        let mut sleep = this.sleep;
+
+        let mut polls = 0;
+        while !*this.stream_done {
+            if polls == POLLS_PER_WAKEUP {
+                cx.waker().wake_by_ref();
+                return Poll::Pending;
+            }
+            polls += 1;
+            match this.stream.as_mut().poll_next(cx) {
+                Poll::Ready(Some(item)) => {
+                    *this.latest = Some(item);
+                    sleep.set(Some(this.timer.sleep(*this.duration)));
+                }
+                Poll::Ready(None) => *this.stream_done = true,
+                Poll::Pending => break,
+            }
+        }
+
+        if *this.stream_done {
+            sleep.set(None);
+            let item = this.latest.take();
+            *this.terminated = item.is_none();
+            return Poll::Ready(item);
+        }
+        match sleep.as_mut().as_pin_mut().map(|pending| pending.poll(cx)) {
+            Some(Poll::Ready(())) => {
+                sleep.set(None);
+                Poll::Ready(this.latest.take())
+            }
+            _ => Poll::Pending,
+        }
+    }
+}
+
+impl<T: Timer, S: Stream> FusedStream for Debounce<T, S> {
+    fn is_terminated(&self) -> bool {
+        self.terminated
+    }
+}
+
+impl<T: Timer, S: Stream> fmt::Debug for Debounce<T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Debounce")
+            .field("duration", &self.duration)
+            .finish()
+    }
+}
@@ -0,0 +1,101 @@
+//! Type assertions on the arguments of the macros.
+//!
+//! The macros pass every argument through these functions before using it,
+//! so that an argument of the wrong type is reported where it was passed to
+//! the macro rather than somewhere deep inside of the expansion. This is also
+//! where arguments implementing `IntoFuture` are turned into futures.
+
+use core::future::IntoFuture;
+
+use futures_core::future::TryFuture;
+use futures_core::stream::Stream;
+
+#[doc(hidden)]
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` is not a future",
+    label = "this argument is not a future",
+    note = "the macros take futures, such as `async` blocks or the calls of `async fn`s, and types implementing `IntoFuture`"
+)]
+pub trait IsFuture: IntoFuture {}
+
+impl<F: IntoFuture> IsFuture for F {}
+
+#[doc(hidden)]
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` is not a future resolving to a `Result`",
+    label = "this argument is not a fallible future",
+    note = "the `try_` macros take futures which resolve to a `Result`"
+)]
+pub trait IsTryFuture: IntoFuture<IntoFuture: TryFuture> {}
+
+impl<F> IsTryFuture for F
+where
+    F: IntoFuture,
+    F::IntoFuture: TryFuture,
+{
+}
+
+#[doc(hidden)]
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` is not a stream",
+    label = "this argument is not a stream"
+)]
+pub trait IsStream: Stream {}
+
+impl<S: Stream> IsStream for S {}
+
+#[doc(hidden)]
+#[diagnostic::on_unimplemented(
+    message = "the futures passed to the macro don't have the same output type",
+    label = "this future resolves to `{Self}`, but the first one resolves to `{T}`",
+    note = "all of the futures must resolve to the same type, as only one of them is returned"
+)]
+pub trait SameAs<T> {}
+
+impl<T> SameAs<T> for T {}
+
+/// Checks that an argument of a macro is a future, and turns it into one.
+#[doc(hidden)]
+#[inline(always)]
+pub fn assert_future<F: IsFuture>(future: F) -> F::IntoFuture {
+    future.into_future()
+}
+
+/// Checks that an argument of a macro is a fallible future, and turns it
+/// into one.
+#[doc(hidden)]
+#[inline(always)]
+pub fn assert_try_future<F: IsTryFuture>(future: F) -> F::IntoFuture {
+    future.into_future()
+}
+
+/// Checks that an argument of a macro is a stream.
+#[doc(hidden)]
+#[inline(always)]
+pub fn assert_stream<S: IsStream>(stream: S) -> S {
+    stream
+}
+
+/// Checks that two arguments of a macro have the same output type.
+#[doc(hidden)]
+#[inline(always)]
+pub fn assert_same_output<A, B>(_first: &A, _other: &B)
+where
+    A: IntoFuture,
+    B: IntoFuture,
+    B::Output: SameAs<A::Output>,
+{
+}
+
+/// Checks that all arguments of a macro have the same output type as the
+/// first one.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __assert_same_output {
+    () => {};
+    ($first:ident $(, $rest:ident)*) => {
+        $(
+            $crate::utils::assert_same_output(&$first, &$rest);
+        )*
+    };
+}
@@ -0,0 +1,113 @@
+//! Definition of the `Either` combinator
+
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use futures_core::future::FusedFuture;
+use futures_core::stream::{FusedStream, Stream};
+
+/// One of two futures or streams of different types.
+///
+/// `Either` is a future if both variants are futures with the same output,
+/// and a stream if both variants are streams with the same item type. This
+/// unifies conditional code paths without boxing.
+///
+/// # Examples
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::{future, Either};
+///
+/// let fast = true;
+/// let fut = if fast {
+///     Either::Left(future::ready(1u8))
+/// } else {
+///     Either::Right(async { 2u8 })
+/// };
+/// assert_eq!(fut.await, 1);
+/// # });
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Either<L, R> {
+    /// The first variant.
+    Left(L),
+    /// The second variant.
+    Right(R),
+}
+
+impl<L, R> Either<L, R> {
+    /// Projects a pinned `Either` to a pinned reference to its variant.
+    pub fn as_pin_mut(self: Pin<&mut Self>) -> Either<Pin<&mut L>, Pin<&mut R>> {
+        // SAFETY: both variants are structurally pinned, and `Either` never
+        // moves out of them.
+        unsafe {
+            match self.get_unchecked_mut() {
+                Either::Left(left) => Either::Left(Pin::new_unchecked(left)),
+                Either::Right(right) => Either::Right(Pin::new_unchecked(right)),
+            }
+        }
+    }
+}
+
+impl<L, R> Future for Either<L, R>
+where
+    L: Future,
+    R: Future<Output = L::Output>,
+{
+    type Output = L::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<L::Output> {
+        match self.as_pin_mut() {
+            Either::Left(left) => left.poll(cx),
+            Either::Right(right) => right.poll(cx),
+        }
+    }
+}
+
+impl<L, R> FusedFuture for Either<L, R>
+where
+    L: FusedFuture,
+    R: FusedFuture<Output = L::Output>,
+{
+    fn is_terminated(&self) -> bool {
+        match self {
+            Either::Left(left) => left.is_terminated(),
+            Either::Right(right) => right.is_terminated(),
+        }
+    }
+}
+
+impl<L, R> Stream for Either<L, R>
+where
+    L: Stream,
+    R: Stream<Item = L::Item>,
+{
+    type Item = L::Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<L::Item>> {
+        match self.as_pin_mut() {
+            Either::Left(left) => left.poll_next(cx),
+            Either::Right(right) => right.poll_next(cx),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self {
+            Either::Left(left) => left.size_hint(),
+            Either::Right(right) => right.size_hint(),
+        }
+    }
+}
+
+impl<L, R> FusedStream for Either<L, R>
+where
+    L: FusedStream,
+    R: FusedStream<Item = L::Item>,
+{
+    fn is_terminated(&self) -> bool {
+        match self {
+            Either::Left(left) => left.is_terminated(),
+            Either::Right(right) => right.is_terminated(),
+        }
+    }
+}
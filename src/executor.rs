@@ -0,0 +1,157 @@
+//! A minimal executor for running a single future.
+//!
+//! This is enough to drive the macros in this crate from examples, tests, and
+//! small command line tools, without depending on a full runtime.
+
+use std::fmt;
+use std::future::Future;
+use std::hint::black_box;
+use std::pin::pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread::{self, Thread};
+use std::time::{Duration, Instant};
+
+/// Wakes the thread running `block_on`.
+struct ThreadWaker {
+    thread: Thread,
+    woken: AtomicBool,
+}
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        if !self.woken.swap(true, Ordering::Release) {
+            self.thread.unpark();
+        }
+    }
+}
+
+/// Runs a future to completion on the current thread.
+///
+/// The thread is parked whenever the future is pending, until it is woken.
+///
+/// # Examples
+///
+/// ```
+/// use async_macros::executor::block_on;
+/// use async_macros::{future, join};
+///
+/// let output = block_on(async {
+///     let a = future::ready(1u8);
+///     let b = future::ready(2u8);
+///     join!(a, b).await
+/// });
+/// assert_eq!(output, (1, 2));
+/// ```
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = pin!(future);
+    let thread_waker = Arc::new(ThreadWaker {
+        thread: thread::current(),
+        woken: AtomicBool::new(false),
+    });
+    let waker = Waker::from(thread_waker.clone());
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+        // `park` may return spuriously, so only poll again once we've
+        // actually been woken.
+        while !thread_waker.woken.swap(false, Ordering::Acquire) {
+            thread::park();
+        }
+    }
+}
+
+/// Measures how long it takes to run the futures returned by `f`.
+///
+/// `f` is called `iterations` times, and every future it returns is awaited
+/// before the next one is created. All of them run inside of a single call to
+/// [`block_on`], so that the cost of the executor itself is kept out of the
+/// measurement as much as possible.
+///
+/// This is what the [`bench`](macro@crate::bench) attribute expands to.
+///
+/// # Panics
+///
+/// Panics if `iterations` is zero.
+///
+/// # Examples
+///
+/// ```
+/// use async_macros::executor::bench;
+/// use async_macros::{future, join};
+///
+/// let bench = bench("join", 100, || async {
+///     let a = future::ready(1u8);
+///     let b = future::ready(2u8);
+///     join!(a, b).await
+/// });
+/// assert_eq!(bench.iterations(), 100);
+/// assert!(bench.per_iteration() <= bench.elapsed());
+/// ```
+pub fn bench<F, Fut>(name: &'static str, iterations: u32, mut f: F) -> Bench
+where
+    F: FnMut() -> Fut,
+    Fut: Future,
+{
+    assert!(iterations > 0, "a benchmark needs at least one iteration");
+    let start = Instant::now();
+    block_on(async {
+        for _ in 0..iterations {
+            black_box(f().await);
+        }
+    });
+    Bench {
+        name,
+        iterations,
+        elapsed: start.elapsed(),
+    }
+}
+
+/// The result of a [`bench`] run.
+#[derive(Debug, Clone)]
+pub struct Bench {
+    name: &'static str,
+    iterations: u32,
+    elapsed: Duration,
+}
+
+impl Bench {
+    /// Returns the name of the benchmark.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Returns the number of times the benchmark was run.
+    pub fn iterations(&self) -> u32 {
+        self.iterations
+    }
+
+    /// Returns the time all iterations took together.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Returns the average time a single iteration took.
+    pub fn per_iteration(&self) -> Duration {
+        self.elapsed / self.iterations
+    }
+}
+
+impl fmt::Display for Bench {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {:?} per iteration ({} iterations)",
+            self.name,
+            self.per_iteration(),
+            self.iterations
+        )
+    }
+}
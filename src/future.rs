@@ -0,0 +1,130 @@
+//! Constructors for trivial futures.
+//!
+//! These make it possible to use the macros in this crate without depending
+//! on the `futures` crate just to create ready or pending futures.
+
+use core::fmt;
+use core::future::Future;
+use core::marker::PhantomData;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+/// Future for the [`ready`] function.
+#[derive(Debug, Clone)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct Ready<T>(Option<T>);
+
+impl<T> Unpin for Ready<T> {}
+
+/// Creates a future which is immediately ready with a value.
+///
+/// # Examples
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::future;
+/// use async_macros::join;
+///
+/// let a = future::ready(1u8);
+/// let b = future::ready(2u8);
+/// assert_eq!(join!(a, b).await, (1, 2));
+/// # });
+/// ```
+pub fn ready<T>(value: T) -> Ready<T> {
+    Ready(Some(value))
+}
+
+impl<T> Future for Ready<T> {
+    type Output = T;
+
+    fn poll(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<T> {
+        Poll::Ready(self.0.take().expect("Ready polled after completion"))
+    }
+}
+
+/// Future for the [`pending`] function.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct Pending<T> {
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Unpin for Pending<T> {}
+
+/// Creates a future which never resolves.
+///
+/// # Examples
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::future;
+/// use async_macros::select;
+///
+/// let a = future::pending::<u8>();
+/// let b = future::ready(2u8);
+/// assert_eq!(select!(a, b).await, 2);
+/// # });
+/// ```
+pub fn pending<T>() -> Pending<T> {
+    Pending {
+        _marker: PhantomData,
+    }
+}
+
+impl<T> Future for Pending<T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<T> {
+        Poll::Pending
+    }
+}
+
+impl<T> fmt::Debug for Pending<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pending").finish()
+    }
+}
+
+impl<T> Clone for Pending<T> {
+    fn clone(&self) -> Self {
+        pending()
+    }
+}
+
+/// Future for the [`yield_now`] function.
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct YieldNow {
+    yielded: bool,
+}
+
+/// Creates a future which yields to the executor once before resolving.
+///
+/// The first time it is polled the future wakes itself and returns
+/// `Pending`, which gives other futures a chance to run. This is also
+/// available as the [`yield_now!`](crate::yield_now!) macro.
+///
+/// # Examples
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::future;
+///
+/// future::yield_now().await;
+/// # });
+/// ```
+pub fn yield_now() -> YieldNow {
+    YieldNow { yielded: false }
+}
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.yielded {
+            return Poll::Ready(());
+        }
+        self.yielded = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
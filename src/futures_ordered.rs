@@ -0,0 +1,155 @@
+//! A growable queue of futures which yields their outputs in order.
+
+use alloc::collections::BTreeMap;
+use core::fmt;
+use core::future::Future;
+use core::iter::FromIterator;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+use futures_core::stream::{FusedStream, Stream};
+use pin_project_lite::pin_project;
+
+use crate::FuturesUnordered;
+
+/// A queue of futures which are driven concurrently, but whose outputs are
+/// yielded in the order the futures were pushed.
+///
+/// This is built on top of [`FuturesUnordered`]: outputs of futures which
+/// complete early are buffered until all futures pushed before them have
+/// completed too.
+///
+/// # Examples
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::FuturesOrdered;
+/// use futures::channel::oneshot;
+/// use futures::stream::StreamExt;
+///
+/// let (tx1, rx1) = oneshot::channel::<u8>();
+/// let (tx2, rx2) = oneshot::channel::<u8>();
+///
+/// let mut queue = FuturesOrdered::new();
+/// queue.push(rx1);
+/// queue.push(rx2);
+///
+/// tx2.send(2).unwrap();
+/// tx1.send(1).unwrap();
+///
+/// assert_eq!(queue.next().await, Some(Ok(1)));
+/// assert_eq!(queue.next().await, Some(Ok(2)));
+/// assert_eq!(queue.next().await, None);
+/// # });
+/// ```
+#[must_use = "streams do nothing unless polled"]
+pub struct FuturesOrdered<Fut: Future> {
+    in_progress: FuturesUnordered<Indexed<Fut>>,
+    queued: BTreeMap<usize, Fut::Output>,
+    next_in: usize,
+    next_out: usize,
+}
+
+pin_project! {
+    /// A future tagged with the position it was pushed at.
+    struct Indexed<Fut> {
+        index: usize,
+        #[pin]
+        future: Fut,
+    }
+}
+
+impl<Fut: Future> Future for Indexed<Fut> {
+    type Output = (usize, Fut::Output);
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let index = *this.index;
+        this.future.poll(cx).map(|output| (index, output))
+    }
+}
+
+impl<Fut: Future> Unpin for FuturesOrdered<Fut> {}
+
+impl<Fut: Future> FuturesOrdered<Fut> {
+    /// Create a new, empty queue.
+    pub fn new() -> Self {
+        Self {
+            in_progress: FuturesUnordered::new(),
+            queued: BTreeMap::new(),
+            next_in: 0,
+            next_out: 0,
+        }
+    }
+
+    /// Returns the number of futures in the queue, including the ones that
+    /// have completed but whose outputs have not yet been yielded.
+    pub fn len(&self) -> usize {
+        self.in_progress.len() + self.queued.len()
+    }
+
+    /// Returns `true` if the queue contains no futures.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Push a future to the back of the queue.
+    pub fn push(&mut self, future: Fut) {
+        let index = self.next_in;
+        self.next_in += 1;
+        self.in_progress.push(Indexed { index, future });
+    }
+}
+
+impl<Fut: Future> Default for FuturesOrdered<Fut> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Fut: Future> fmt::Debug for FuturesOrdered<Fut> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FuturesOrdered")
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+impl<Fut: Future> FromIterator<Fut> for FuturesOrdered<Fut> {
+    fn from_iter<I: IntoIterator<Item = Fut>>(iter: I) -> Self {
+        let mut queue = Self::new();
+        for future in iter {
+            queue.push(future);
+        }
+        queue
+    }
+}
+
+impl<Fut: Future> Stream for FuturesOrdered<Fut> {
+    type Item = Fut::Output;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        loop {
+            let next_out = this.next_out;
+            if let Some(output) = this.queued.remove(&next_out) {
+                this.next_out += 1;
+                return Poll::Ready(Some(output));
+            }
+
+            match Pin::new(&mut this.in_progress).poll_next(cx) {
+                Poll::Ready(Some((index, output))) => {
+                    this.queued.insert(index, output);
+                }
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+impl<Fut: Future> FusedStream for FuturesOrdered<Fut> {
+    fn is_terminated(&self) -> bool {
+        self.in_progress.is_terminated() && self.queued.is_empty()
+    }
+}
@@ -0,0 +1,236 @@
+//! A growable set of futures which yields their outputs as they complete.
+
+use alloc::boxed::Box;
+use alloc::collections::VecDeque;
+use alloc::sync::{Arc, Weak};
+use alloc::task::Wake;
+use alloc::vec::Vec;
+use core::fmt;
+use core::future::Future;
+use core::iter::FromIterator;
+use core::pin::Pin;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::task::{Context, Poll, Waker};
+
+use futures_core::stream::{FusedStream, Stream};
+
+use crate::sync::Mutex;
+
+/// A set of futures which may complete in any order.
+///
+/// Futures can be pushed into the set at any time. Polling the set as a
+/// [`Stream`] drives all of its futures concurrently and yields their outputs
+/// as they complete. Every future is given its own waker, so only the futures
+/// that have been woken are polled again.
+///
+/// The stream yields `None` whenever the set is empty, but it can be reused
+/// by pushing more futures into it. The set reports itself as terminated
+/// through [`FusedStream`] from the moment it yields `None` until a future is
+/// pushed into it again.
+///
+/// # Examples
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::FuturesUnordered;
+/// use futures::future;
+/// use futures::stream::StreamExt;
+///
+/// let mut set = FuturesUnordered::new();
+/// set.push(future::ready(1u8));
+/// set.push(future::ready(2u8));
+///
+/// let mut outputs = set.collect::<Vec<_>>().await;
+/// outputs.sort();
+/// assert_eq!(outputs, vec![1, 2]);
+/// # });
+/// ```
+#[must_use = "streams do nothing unless polled"]
+pub struct FuturesUnordered<Fut> {
+    slots: Vec<Option<Slot<Fut>>>,
+    free: Vec<usize>,
+    len: usize,
+    terminated: bool,
+    shared: Arc<Shared>,
+}
+
+/// A future in the set, together with the waker state it is polled with.
+struct Slot<Fut> {
+    future: Pin<Box<Fut>>,
+    task: Arc<Task>,
+}
+
+/// State shared between the set and the wakers of its futures.
+struct Shared {
+    /// The futures which have been woken since they were last polled.
+    ready: Mutex<VecDeque<Arc<Task>>>,
+    /// The waker of the task polling the set.
+    waker: Mutex<Option<Waker>>,
+}
+
+/// The waker of a single future in the set.
+struct Task {
+    index: usize,
+    queued: AtomicBool,
+    shared: Weak<Shared>,
+}
+
+impl Wake for Task {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        if self.queued.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        if let Some(shared) = self.shared.upgrade() {
+            shared.enqueue(self.clone());
+        }
+    }
+}
+
+impl Shared {
+    fn enqueue(&self, task: Arc<Task>) {
+        self.ready.lock().push_back(task);
+        // Wake outside of the lock, as waking may run arbitrary code.
+        let waker = self.waker.lock().clone();
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+impl<Fut> Unpin for FuturesUnordered<Fut> {}
+
+impl<Fut> FuturesUnordered<Fut> {
+    /// Create a new, empty set.
+    pub fn new() -> Self {
+        Self {
+            slots: Vec::new(),
+            free: Vec::new(),
+            len: 0,
+            terminated: false,
+            shared: Arc::new(Shared {
+                ready: Mutex::new(VecDeque::new()),
+                waker: Mutex::new(None),
+            }),
+        }
+    }
+
+    /// Returns the number of futures in the set.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the set contains no futures.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Push a future into the set.
+    ///
+    /// The future is polled the next time the set is polled.
+    pub fn push(&mut self, future: Fut) {
+        let index = self.free.pop().unwrap_or(self.slots.len());
+        let task = Arc::new(Task {
+            index,
+            queued: AtomicBool::new(true),
+            shared: Arc::downgrade(&self.shared),
+        });
+        let slot = Slot {
+            future: Box::pin(future),
+            task: task.clone(),
+        };
+        if index == self.slots.len() {
+            self.slots.push(Some(slot));
+        } else {
+            self.slots[index] = Some(slot);
+        }
+        self.len += 1;
+        self.terminated = false;
+        self.shared.enqueue(task);
+    }
+}
+
+impl<Fut> Default for FuturesUnordered<Fut> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Fut> fmt::Debug for FuturesUnordered<Fut> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FuturesUnordered")
+            .field("len", &self.len)
+            .finish()
+    }
+}
+
+impl<Fut> FromIterator<Fut> for FuturesUnordered<Fut> {
+    fn from_iter<I: IntoIterator<Item = Fut>>(iter: I) -> Self {
+        let mut set = Self::new();
+        for future in iter {
+            set.push(future);
+        }
+        set
+    }
+}
+
+impl<Fut: Future> Stream for FuturesUnordered<Fut> {
+    type Item = Fut::Output;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if self.len == 0 {
+            self.terminated = true;
+            return Poll::Ready(None);
+        }
+
+        {
+            let mut waker = self.shared.waker.lock();
+            match &*waker {
+                Some(waker) if waker.will_wake(cx.waker()) => {}
+                _ => *waker = Some(cx.waker().clone()),
+            }
+        }
+
+        // Only poll the futures which were ready when we started, so that a
+        // future which keeps waking itself can't starve the executor.
+        let budget = self.shared.ready.lock().len();
+        for _ in 0..budget {
+            let task = match self.shared.ready.lock().pop_front() {
+                Some(task) => task,
+                None => break,
+            };
+            task.queued.store(false, Ordering::Release);
+
+            let this = &mut *self;
+            let slot = match &mut this.slots[task.index] {
+                // The future this waker belonged to may have completed, and
+                // its slot been reused since.
+                Some(slot) if Arc::ptr_eq(&slot.task, &task) => slot,
+                _ => continue,
+            };
+
+            let waker = Waker::from(task.clone());
+            let mut task_cx = Context::from_waker(&waker);
+            if let Poll::Ready(output) = slot.future.as_mut().poll(&mut task_cx) {
+                this.slots[task.index] = None;
+                this.free.push(task.index);
+                this.len -= 1;
+                return Poll::Ready(Some(output));
+            }
+        }
+
+        if !self.shared.ready.lock().is_empty() {
+            cx.waker().wake_by_ref();
+        }
+        Poll::Pending
+    }
+}
+
+impl<Fut: Future> FusedStream for FuturesUnordered<Fut> {
+    fn is_terminated(&self) -> bool {
+        self.terminated
+    }
+}
@@ -0,0 +1,52 @@
+//! A global which can be set once, such as the poll observer.
+
+use core::cell::UnsafeCell;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+const UNSET: usize = 0;
+const SETTING: usize = 1;
+const SET: usize = 2;
+
+pub(crate) struct Global<T: ?Sized + 'static> {
+    state: AtomicUsize,
+    value: UnsafeCell<Option<&'static T>>,
+}
+
+// SAFETY: `value` is only written once, before `state` is set to `SET`, and
+// only read after that.
+unsafe impl<T: ?Sized + Sync> Sync for Global<T> {}
+
+impl<T: ?Sized> Global<T> {
+    pub(crate) const fn new() -> Self {
+        Self {
+            state: AtomicUsize::new(UNSET),
+            value: UnsafeCell::new(None),
+        }
+    }
+
+    /// Sets the value, failing if it was already set.
+    pub(crate) fn set(&self, value: &'static T) -> Result<(), ()> {
+        match self
+            .state
+            .compare_exchange(UNSET, SETTING, Ordering::Acquire, Ordering::Relaxed)
+        {
+            Ok(_) => {
+                // SAFETY: only the call which moved `state` out of `UNSET`
+                // gets here, and nobody reads the value until it is `SET`.
+                unsafe { *self.value.get() = Some(value) };
+                self.state.store(SET, Ordering::Release);
+                Ok(())
+            }
+            Err(_) => Err(()),
+        }
+    }
+
+    pub(crate) fn get(&self) -> Option<&'static T> {
+        if self.state.load(Ordering::Acquire) == SET {
+            // SAFETY: the value is never written again once `state` is `SET`.
+            unsafe { *self.value.get() }
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,98 @@
+#![allow(non_snake_case)]
+
+/// Starts a future, then starts backups after delays, and waits for the first
+/// one to complete.
+///
+/// `hedge!(timer; primary, backup after delay, ...)` polls `primary` right
+/// away. Every backup is started, that is polled for the first time, once its
+/// `delay` has passed since the previous future was started, as measured by
+/// the [`Timer`](crate::time::Timer) `timer`. As soon as any of the started
+/// futures completes, `hedge!` resolves to its output and the other futures
+/// are dropped. This is how hedged requests and happy eyeballs connections
+/// work: a slow primary doesn't hold up the result, but backups are only
+/// started when they are likely to help.
+///
+/// Like `select!`, if several futures are ready at the same time the first
+/// one in the list wins. All futures must have the same output type.
+///
+/// This macro is only usable inside of async functions, closures, and blocks.
+///
+/// # Examples
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::time::ThreadTimer;
+/// use async_macros::{future, hedge};
+/// use std::time::Duration;
+///
+/// let primary = future::pending::<&str>();
+/// let backup = async { "backup" };
+///
+/// let output = hedge!(ThreadTimer; primary, backup after Duration::from_millis(5)).await;
+/// assert_eq!(output, "backup");
+/// # });
+/// ```
+#[macro_export]
+macro_rules! hedge {
+    ($timer:expr; $first:ident $(, $fut:ident after $delay:expr)* $(,)?) => { {
+        async {
+            use $crate::utils::future::Future;
+            use $crate::utils::option::Option;
+            use $crate::utils::task::Poll;
+            use $crate::utils::time::Duration;
+
+            let timer = $timer;
+            let delays: &[Duration] = &[$($delay),*];
+            $crate::utils::assert_same_output!($first $(, $fut)*);
+            let mut index = 0;
+            let $first = $crate::utils::assert_future($first);
+            let $first = $crate::utils::child("hedge", index, stringify!($first), $first);
+            index += 1;
+            // Pin the futures on the stack, so that they stay in one place and
+            // are no longer accessible by the end user.
+            let mut $first = $crate::utils::pin::pin!($crate::MaybeDone::new($first));
+            $(
+                let $fut = $crate::utils::assert_future($fut);
+                let $fut = $crate::utils::child("hedge", index, stringify!($fut), $fut);
+                index += 1;
+                let mut $fut = $crate::utils::pin::pin!($crate::MaybeDone::new($fut));
+            )*
+            let _ = index;
+
+            // The number of futures which have been started, and the sleep
+            // until the next one starts.
+            let mut started = 1;
+            let mut sleep = $crate::utils::pin::pin!(Option::None);
+            $crate::utils::poll_fn(move |cx| {
+                while started <= delays.len() {
+                    match sleep.as_mut().as_pin_mut() {
+                        Option::None => {
+                            let next = $crate::time::Timer::sleep(&timer, delays[started - 1]);
+                            sleep.set(Option::Some(next));
+                        }
+                        Option::Some(next) => {
+                            if next.poll(cx).is_pending() {
+                                break;
+                            }
+                            sleep.set(Option::None);
+                            started += 1;
+                        }
+                    }
+                }
+
+                if Future::poll($first.as_mut(), cx).is_ready() {
+                    return Poll::Ready($first.as_mut().take().unwrap());
+                }
+                let mut index = 1;
+                $(
+                    if index < started && Future::poll($fut.as_mut(), cx).is_ready() {
+                        return Poll::Ready($fut.as_mut().take().unwrap());
+                    }
+                    index += 1;
+                )*
+                let _ = index;
+                Poll::Pending
+            }).await
+        }
+    } };
+}
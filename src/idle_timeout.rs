@@ -0,0 +1,144 @@
+//! Definition of the `IdleTimeout` stream adapter
+
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use core::time::Duration;
+
+use futures_core::stream::{FusedStream, Stream};
+use pin_project_lite::pin_project;
+
+use crate::time::{Elapsed, Timer};
+
+/// Yields an error whenever a stream doesn't yield an item within `duration`.
+///
+/// The items of the stream are yielded as `Ok`. The wait for an item starts
+/// once the stream is polled for it, and is measured with `timer`. If it
+/// passes before the stream yields the item, an [`Elapsed`] error is yielded
+/// instead, and the wait starts over, so the returned stream keeps going.
+/// Ending the stream on the first error instead is a matter of
+/// `take_while(Result::is_ok)`. The error can be replaced by one of your own
+/// with [`IdleTimeout::with_error`].
+///
+/// This makes it possible to detect stalled connections from a `select!`
+/// loop.
+///
+/// # Examples
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::time::{self, Elapsed, ThreadTimer};
+/// use futures::stream::{self, StreamExt};
+/// use std::time::Duration;
+///
+/// let messages = stream::iter(vec![1u8, 2]).chain(stream::pending());
+/// let mut messages = time::idle_timeout(ThreadTimer, messages, Duration::from_millis(10));
+///
+/// assert_eq!(messages.next().await, Some(Ok(1)));
+/// assert_eq!(messages.next().await, Some(Ok(2)));
+/// assert_eq!(messages.next().await, Some(Err(Elapsed::new())));
+/// # });
+/// ```
+pub fn idle_timeout<T, S>(timer: T, stream: S, duration: Duration) -> IdleTimeout<T, S>
+where
+    T: Timer,
+    S: Stream,
+{
+    IdleTimeout {
+        stream,
+        timer,
+        duration,
+        sleep: None,
+        error: Elapsed::new,
+    }
+}
+
+pin_project! {
+    /// Stream for the [`idle_timeout`] function.
+    ///
+    /// `M` makes the errors yielded when no item came in time, which are
+    /// [`Elapsed`] errors unless it was changed with
+    /// [`with_error`](IdleTimeout::with_error).
+    #[must_use = "streams do nothing unless polled"]
+    pub struct IdleTimeout<T: Timer, S, M = fn() -> Elapsed> {
+        #[pin]
+        stream: S,
+        timer: T,
+        duration: Duration,
+        // The wait for the next item, once it started.
+        #[pin]
+        sleep: Option<T::Sleep>,
+        error: M,
+    }
+}
+
+impl<T: Timer, S, M> IdleTimeout<T, S, M> {
+    /// Makes the errors yielded when no item came in time with `error`,
+    /// rather than yielding [`Elapsed`] errors.
+    pub fn with_error<N, E>(self, error: N) -> IdleTimeout<T, S, N>
+    where
+        N: FnMut() -> E,
+    {
+        IdleTimeout {
+            stream: self.stream,
+            timer: self.timer,
+            duration: self.duration,
+            sleep: self.sleep,
+            error,
+        }
+    }
+}
+
+impl<T, S, M, E> Stream for IdleTimeout<T, S, M>
+where
+    T: Timer,
+    S: Stream,
+    M: FnMut() -> E,
+{
+    type Item = Result<S::Item, E>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.project();
+        let mut sleep = this.sleep;
+
+        match this.stream.poll_next(cx) {
+            Poll::Ready(item) => {
+                sleep.set(None);
+                return Poll::Ready(item.map(Ok));
+            }
+            Poll::Pending => {}
+        }
+
+        if sleep.is_none() {
+            sleep.set(Some(this.timer.sleep(*this.duration)));
+        }
+        // The wait was just set if it wasn't already.
+        match sleep.as_mut().as_pin_mut().unwrap().poll(cx) {
+            Poll::Ready(()) => {
+                sleep.set(None);
+                Poll::Ready(Some(Err((this.error)())))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl<T, S, M, E> FusedStream for IdleTimeout<T, S, M>
+where
+    T: Timer,
+    S: FusedStream,
+    M: FnMut() -> E,
+{
+    fn is_terminated(&self) -> bool {
+        self.stream.is_terminated()
+    }
+}
+
+impl<T: Timer, S, M> fmt::Debug for IdleTimeout<T, S, M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IdleTimeout")
+            .field("duration", &self.duration)
+            .finish()
+    }
+}
@@ -0,0 +1,72 @@
+/// Combines multiple streams into a single stream, taking one item from each
+/// stream in turn.
+///
+/// Unlike [`join_stream!`], which yields items in whatever order the streams
+/// become ready, `interleave_stream!` follows a strict round-robin: the
+/// combined stream waits for the stream whose turn it is, even if other
+/// streams already have items available. Streams that have ended are skipped,
+/// and the combined stream ends once all of them have ended.
+///
+/// All streams must be `Unpin` and yield the same item type.
+///
+/// # Examples
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::interleave_stream;
+/// use futures::stream::{self, StreamExt};
+///
+/// let a = stream::iter(vec![1u8, 4, 6]);
+/// let b = stream::iter(vec![2u8, 5]);
+/// let c = stream::iter(vec![3u8]);
+///
+/// let s = interleave_stream!(a, b, c);
+///
+/// assert_eq!(s.collect::<Vec<_>>().await, vec![1, 2, 3, 4, 5, 6]);
+/// # });
+/// ```
+#[macro_export]
+macro_rules! interleave_stream {
+    ($($stream:ident $(as $name:expr)?),* $(,)?) => {{
+        let mut index = 0;
+        $(
+            let $stream = $crate::utils::assert_stream($stream);
+            let name = $crate::utils::future_name!($stream $(as $name)?);
+            let $stream = $crate::utils::child_stream("interleave_stream", index, name, $stream);
+            index += 1;
+            let mut $stream = $crate::MaybeDoneStream::new($stream);
+        )*
+        let len = index;
+        let mut turn = 0;
+        $crate::utils::poll_next_fn(move |cx| {
+            use $crate::utils::pin::Pin;
+            use $crate::utils::stream::{FusedStream, Stream};
+            use $crate::utils::task::Poll;
+
+            for _ in 0..len {
+                let mut index = 0;
+                $(
+                    if index == turn && !$stream.is_terminated() {
+                        match Stream::poll_next(Pin::new(&mut $stream), cx) {
+                            Poll::Ready(Some(item)) => {
+                                turn = (turn + 1) % len;
+                                return Poll::Ready(Some(item));
+                            }
+                            Poll::Ready(None) => {}
+                            Poll::Pending => return Poll::Pending,
+                        }
+                    }
+                    index += 1;
+                )*
+                let _ = index;
+                turn = (turn + 1) % len;
+            }
+
+            // Every stream has ended.
+            Poll::Ready(None)
+        })
+    }};
+    ($($stream:expr),+ $(,)?) => {
+        $crate::utils::bind_futures!(interleave_stream [] [] $($stream),+)
+    };
+}
@@ -5,6 +5,50 @@
 /// While `join!(a, b)` is similar to `(a.await, b.await)`,
 /// `join!` polls both futures concurrently and therefore is more efficent.
 ///
+/// With the `waker-routing` feature, every future is polled with its own
+/// waker, so that after a wakeup only the futures which were woken are polled
+/// again.
+///
+/// Futures can also be given names, as in `join!(users = fetch_users(), posts
+/// = fetch_posts())`. The output is then a struct with one field per name
+/// instead of a tuple, which keeps call sites joining many futures readable.
+/// The struct implements `Debug` and `Clone` if all outputs do.
+///
+/// Besides variables, futures can be passed as expressions, such as `async`
+/// blocks or calls of `async fn`s. They are pinned inside of the join like
+/// any other future, so that ad hoc concurrent work doesn't need local
+/// variables. This goes for the other macros taking futures or streams as
+/// well, apart from `hedge!` and `select_priority!`.
+///
+/// Values of types implementing [`IntoFuture`](core::future::IntoFuture), such
+/// as request builders, are turned into futures with `into_future`, so they
+/// can be passed as is.
+///
+/// This includes `&mut fut` for an `Unpin` future, or `fut.as_mut()` for a
+/// future pinned with [`pin!`](core::pin::pin), which lets the future take
+/// part in a join without being consumed by it. If the join is dropped before
+/// it completes, such as when it loses a `select!` against a timeout, the
+/// future can be awaited further on its own.
+///
+/// By default a panic in one of the futures propagates out of the join right
+/// away, and the other futures are only dropped once the join itself is.
+/// Passing `drop_on_panic;` before the futures makes the join drop all of the
+/// other futures, running their destructors, before the panic propagates, so
+/// that the resources they hold are released deterministically. The futures
+/// are not polled anymore once one of them panicked. This requires the `std`
+/// feature, and doesn't use the `waker-routing` feature. To keep the other
+/// futures running instead, see [`join_catch!`].
+///
+/// Individual futures can be given a deadline of their own, by passing a
+/// [`Timer`](crate::time::Timer) with `timer = <expr>;` before the futures and
+/// following the names of those futures with `@ <duration>`, as in
+/// `join!(timer = ThreadTimer; a, b @ Duration::from_millis(500), c)`. The
+/// join still waits for all of the futures, and the output of a future with a
+/// deadline is a `Result`, which is an [`Elapsed`](crate::time::Elapsed) error
+/// if the future didn't complete in time. The deadlines start when the join is
+/// created. This is the same as passing the futures through
+/// [`time::timeout`](crate::time::timeout).
+///
 /// This macro is only usable inside of async functions, closures, and blocks.
 ///
 /// # Examples
@@ -20,33 +64,244 @@
 /// assert_eq!(join!(a, b).await, (1, 2));
 /// # });
 /// ```
+///
+/// Naming the futures:
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::{future, join};
+///
+/// let output = join!(
+///     users = future::ready(vec!["alice", "bob"]),
+///     posts = async { 12u32 },
+/// )
+/// .await;
+///
+/// assert_eq!(output.users, ["alice", "bob"]);
+/// assert_eq!(output.posts, 12);
+/// # });
+/// ```
+///
+/// Passing `async` blocks:
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::{future, join};
+///
+/// async fn fetch(id: u8) -> Result<u8, ()> {
+///     Ok(id * 2)
+/// }
+///
+/// let output = join!(async { fetch(1).await }, fetch(2), future::ready(Ok::<_, ()>(3))).await;
+/// assert_eq!(output, (Ok(2), Ok(4), Ok(3)));
+/// # });
+/// ```
+///
+/// Joining a future without consuming it:
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::{future, join, select};
+/// use std::pin::pin;
+///
+/// let mut download = pin!(async {
+///     future::yield_now().await;
+///     "downloaded"
+/// });
+/// let timeout = future::ready("timed out");
+///
+/// // The join loses against the timeout, but `download` keeps its progress.
+/// let join = async { join!(download.as_mut(), future::pending::<()>()).await.0 };
+/// assert_eq!(select!(join, timeout).await, "timed out");
+/// assert_eq!(download.await, "downloaded");
+/// # });
+/// ```
+///
+/// Giving some of the futures a deadline:
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::time::{Elapsed, ThreadTimer};
+/// use async_macros::{future, join};
+/// use std::time::Duration;
+///
+/// let a = future::ready(1u8);
+/// let b = future::pending::<u8>();
+/// let c = future::ready(3u8);
+///
+/// let output = join!(timer = ThreadTimer; a, b @ Duration::from_millis(10), c).await;
+/// assert_eq!(output, (1, Err(Elapsed::new()), 3));
+/// # });
+/// ```
+///
+/// Dropping the other futures on panic:
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::{future, join};
+/// use std::cell::Cell;
+/// use std::panic::AssertUnwindSafe;
+///
+/// struct Connection<'a>(&'a Cell<bool>);
+///
+/// impl Drop for Connection<'_> {
+///     fn drop(&mut self) {
+///         self.0.set(false);
+///     }
+/// }
+///
+/// let open = Cell::new(true);
+/// let conn = Connection(&open);
+/// let server = async move {
+///     let _conn = conn;
+///     future::pending::<()>().await;
+/// };
+/// let handler = async { panic!("oh no") };
+///
+/// let join = join!(drop_on_panic; server, handler);
+/// let output = async_macros::catch_unwind(AssertUnwindSafe(join)).await;
+/// assert!(output.is_err());
+/// assert!(!open.get());
+/// # });
+/// ```
 #[macro_export]
 macro_rules! join {
-    ($($fut:ident),* $(,)?) => { {
+    (drop_on_panic; $($fut:ident $(as $name:expr)?),+ $(,)?) => { {
         async {
+            let mut index = 0;
             $(
-                // Move future into a local so that it is pinned in one place and
+                let $fut = $crate::utils::assert_future($fut);
+                let name = $crate::utils::future_name!($fut $(as $name)?);
+                let $fut = $crate::utils::child("join", index, name, $fut);
+                index += 1;
+                let $fut = $crate::catch_unwind($fut);
+                // Pin the future on the stack, so that it stays in one place and
                 // is no longer accessible by the end user.
-                let mut $fut = $crate::MaybeDone::new($fut);
+                let mut $fut = $crate::utils::pin::pin!($crate::MaybeDone::new($fut));
             )*
+            let _ = index;
             $crate::utils::poll_fn(move |cx| {
                 use $crate::utils::future::Future;
+                use $crate::utils::result::Result;
                 use $crate::utils::task::Poll;
-                use $crate::utils::pin::Pin;
 
+                let mut panicked = None;
                 let mut all_done = true;
                 $(
-                    let fut = unsafe { Pin::new_unchecked(&mut $fut) };
-                    all_done &= Future::poll(fut, cx).is_ready();
+                    if panicked.is_none() {
+                        let _ = Future::poll($fut.as_mut(), cx);
+                        if let Some(Result::Err(_)) = $fut.as_ref().output() {
+                            panicked = $fut.as_mut().take().and_then(Result::err);
+                        }
+                        all_done &= $fut.is_done();
+                    }
                 )*
+                if let Some(panicked) = panicked {
+                    // Drop every future in place before resuming the panic.
+                    $(
+                        $fut.set($crate::MaybeDone::Gone);
+                    )*
+                    panicked.resume_unwind();
+                }
+                if all_done {
+                    Poll::Ready(($(
+                        match $fut.as_mut().take() {
+                            Some(Result::Ok(output)) => output,
+                            _ => unreachable!(),
+                        },
+                    )*))
+                } else {
+                    Poll::Pending
+                }
+            }).await
+        }
+    } };
+    (drop_on_panic; $($fut:expr),+ $(,)?) => {
+        $crate::utils::bind_futures!(join [drop_on_panic;] [] $($fut),+)
+    };
+    (timer = $timer:expr; $($args:tt)+) => {
+        match $timer {
+            timer => $crate::join!(@timeouts timer [] $($args)+),
+        }
+    };
+    // Wraps every future followed by `@ <duration>` in a `Timeout`. The
+    // futures are collected the way `bind_futures!` collects them, so that
+    // they keep their own names.
+    (@timeouts $timer:ident [$($bound:tt)*] $next:ident @ $duration:expr $(, $($rest:tt)*)?) => {
+        $crate::join!(
+            @timeouts $timer
+            [$($bound)* (fut, $crate::time::timeout(&$timer, $duration, $next), stringify!($next))]
+            $($($rest)*)?
+        )
+    };
+    (@timeouts $timer:ident [$($bound:tt)*] $next:expr $(, $($rest:tt)*)?) => {
+        $crate::join!(@timeouts $timer [$($bound)* (fut, $next, stringify!($next))] $($($rest)*)?)
+    };
+    (@timeouts $timer:ident [$($bound:tt)+]) => {
+        $crate::utils::bind_futures!(join [] [$($bound)+])
+    };
+    ($($name:ident = $fut:expr),+ $(,)?) => {
+        // The futures are created outside of the block declaring the output
+        // struct, so that its name can't shadow anything they refer to.
+        match ($($fut,)+) {
+            ($($name,)+) => {
+                #[derive(Debug, Clone)]
+                #[allow(non_camel_case_types)]
+                struct Joined<$($name),+> {
+                    $($name: $name,)+
+                }
+
+                async move {
+                    let ($($name,)+) = $crate::join!($($name),+).await;
+                    Joined { $($name),+ }
+                }
+            }
+        }
+    };
+    ($($fut:ident $(as $name:expr)?),* $(,)?) => { {
+        async {
+            let mut index = 0;
+            $(
+                let $fut = $crate::utils::assert_future($fut);
+                let name = $crate::utils::future_name!($fut $(as $name)?);
+                let $fut = $crate::utils::child("join", index, name, $fut);
+                index += 1;
+                // Pin the future on the stack, so that it stays in one place and
+                // is no longer accessible by the end user.
+                let mut $fut = $crate::utils::pin::pin!($crate::MaybeDone::new($fut));
+            )*
+            let router = $crate::utils::WakerRouter::new(index);
+            let mut done = [$({ let _ = stringify!($fut); false }),*];
+            $crate::utils::poll_fn(move |cx| {
+                use $crate::utils::future::Future;
+                use $crate::utils::pin::Pin;
+                use $crate::utils::task::{Context, Poll};
+
+                router.register(cx);
+                let mut all_done = true;
+                // The children are polled in a loop rather than one after the
+                // other, which keeps the expansion small for many futures.
+                let children: &mut [Pin<&mut dyn Future<Output = ()>>] = &mut [$($fut.as_mut()),*];
+                for (index, child) in children.iter_mut().enumerate() {
+                    // Only poll the children which have been woken since they
+                    // were last polled.
+                    if !done[index] && router.take_woken(index) {
+                        let mut cx = Context::from_waker(router.waker(index, cx));
+                        done[index] = child.as_mut().poll(&mut cx).is_ready();
+                    }
+                    all_done &= done[index];
+                }
                 if all_done {
                     Poll::Ready(($(
-                        unsafe { Pin::new_unchecked(&mut $fut) }.take().unwrap(),
+                        $fut.as_mut().take().unwrap(),
                     )*))
                 } else {
                     Poll::Pending
                 }
             }).await
         }
-    } }
+    } };
+    ($($fut:expr),+ $(,)?) => {
+        $crate::utils::bind_futures!(join [] [] $($fut),+)
+    };
 }
@@ -0,0 +1,139 @@
+//! Definition of the `JoinAllLimited` combinator
+
+use alloc::vec::Vec;
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+use futures_core::stream::Stream;
+use pin_project_lite::pin_project;
+
+use crate::FuturesUnordered;
+
+/// Awaits all futures of an iterator, with at most `limit` of them running at
+/// once, returning their outputs in order.
+///
+/// Futures are started in the order of the iterator, and the next one is
+/// started as soon as a running one completes. This is the counterpart of
+/// [`join_limited!`](crate::join_limited!) for a dynamic number of futures.
+///
+/// # Panics
+///
+/// Panics if `limit` is zero.
+///
+/// # Examples
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::join_all_limited;
+///
+/// let requests = (1..=10u32).map(|n| async move { n * 2 });
+/// let outputs = join_all_limited(requests, 4).await;
+/// assert_eq!(outputs, (1..=10).map(|n| n * 2).collect::<Vec<_>>());
+/// # });
+/// ```
+pub fn join_all_limited<I>(iter: I, limit: usize) -> JoinAllLimited<I::IntoIter>
+where
+    I: IntoIterator,
+    I::Item: Future,
+{
+    assert!(
+        limit > 0,
+        "`join_all_limited` needs a limit of at least one"
+    );
+    JoinAllLimited {
+        pending: iter.into_iter(),
+        running: FuturesUnordered::new(),
+        outputs: Vec::new(),
+        limit,
+    }
+}
+
+/// Future for the [`join_all_limited`] function.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct JoinAllLimited<I>
+where
+    I: Iterator,
+    I::Item: Future,
+{
+    pending: I,
+    running: FuturesUnordered<Indexed<I::Item>>,
+    outputs: Vec<Option<<I::Item as Future>::Output>>,
+    limit: usize,
+}
+
+impl<I> Unpin for JoinAllLimited<I>
+where
+    I: Iterator,
+    I::Item: Future,
+{
+}
+
+impl<I> Future for JoinAllLimited<I>
+where
+    I: Iterator,
+    I::Item: Future,
+{
+    type Output = Vec<<I::Item as Future>::Output>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        loop {
+            while this.running.len() < this.limit {
+                let future = match this.pending.next() {
+                    Some(future) => future,
+                    None => break,
+                };
+                this.running.push(Indexed {
+                    index: this.outputs.len(),
+                    future,
+                });
+                this.outputs.push(None);
+            }
+
+            match Pin::new(&mut this.running).poll_next(cx) {
+                Poll::Ready(Some((index, output))) => this.outputs[index] = Some(output),
+                Poll::Ready(None) => {
+                    let outputs = core::mem::take(&mut this.outputs);
+                    return Poll::Ready(outputs.into_iter().map(Option::unwrap).collect());
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+impl<I> fmt::Debug for JoinAllLimited<I>
+where
+    I: Iterator,
+    I::Item: Future,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JoinAllLimited")
+            .field("running", &self.running.len())
+            .field("limit", &self.limit)
+            .finish()
+    }
+}
+
+pin_project! {
+    /// A future which remembers its position in the iterator.
+    struct Indexed<F> {
+        index: usize,
+        #[pin]
+        future: F,
+    }
+}
+
+impl<F: Future> Future for Indexed<F> {
+    type Output = (usize, F::Output);
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        match this.future.poll(cx) {
+            Poll::Ready(output) => Poll::Ready((*this.index, output)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
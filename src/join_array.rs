@@ -0,0 +1,39 @@
+#![allow(non_snake_case)]
+
+/// Awaits multiple futures with the same output simultaneously, returning
+/// all results as an array once complete.
+///
+/// This is the same as [`join!`], except that the outputs are collected into
+/// an array `[T; N]` instead of a tuple, so that they can be indexed and
+/// iterated over.
+///
+/// This macro is only usable inside of async functions, closures, and blocks.
+///
+/// # Examples
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::{future, join_array};
+///
+/// let a = future::ready(1u8);
+/// let b = async { 2u8 };
+/// let c = future::ready(3u8);
+///
+/// let outputs = join_array!(a, b, c).await;
+/// assert_eq!(outputs, [1, 2, 3]);
+/// assert_eq!(outputs.iter().sum::<u8>(), 6);
+/// # });
+/// ```
+#[macro_export]
+macro_rules! join_array {
+    ($($fut:ident $(as $name:expr)?),* $(,)?) => {
+        async move {
+            $crate::utils::assert_same_output!($($fut),*);
+            let ($($fut,)*) = $crate::join!($($fut $(as $name)?),*).await;
+            [$($fut),*]
+        }
+    };
+    ($($fut:expr),+ $(,)?) => {
+        $crate::utils::bind_futures!(join_array [] [] $($fut),+)
+    };
+}
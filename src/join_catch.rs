@@ -0,0 +1,38 @@
+/// Awaits multiple futures simultaneously, catching their panics.
+///
+/// This is the same as [`join!`], except that every future is wrapped in
+/// [`catch_unwind`](crate::catch_unwind): a panicking future resolves to
+/// `Err(Panicked)` in its slot of the output tuple, while the other futures
+/// keep running until they complete. Futures which didn't panic resolve to
+/// `Ok` with their output.
+///
+/// This requires the `std` feature.
+///
+/// This macro is only usable inside of async functions, closures, and blocks.
+///
+/// # Examples
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::{future, join_catch};
+///
+/// let a = future::ready(1u8);
+/// let b = async { panic!("oh no") };
+///
+/// let (a, b) = join_catch!(a, b).await;
+/// assert_eq!(a.unwrap(), 1);
+/// assert_eq!(b.unwrap_err().message(), Some("oh no"));
+/// # });
+/// ```
+#[macro_export]
+macro_rules! join_catch {
+    ($($fut:ident $(as $name:expr)?),* $(,)?) => { {
+        $(
+            let $fut = $crate::catch_unwind($crate::utils::assert_future($fut));
+        )*
+        $crate::join!($($fut as $crate::utils::future_name!($fut $(as $name)?)),*)
+    } };
+    ($($fut:expr),+ $(,)?) => {
+        $crate::utils::bind_futures!(join_catch [] [] $($fut),+)
+    };
+}
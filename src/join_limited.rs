@@ -0,0 +1,100 @@
+#![allow(non_snake_case)]
+
+/// Awaits multiple futures, with at most a given number of them running at
+/// once, returning all results once complete.
+///
+/// `join_limited!(n; a, b, c)` is like [`join!`], except that only the first
+/// `n` futures are polled to begin with. Every time one of the running futures
+/// completes, the next one in order is started. This keeps a burst of
+/// requests from overloading the service they are sent to.
+///
+/// To join a dynamic number of futures, use
+/// [`join_all_limited`](crate::join_all_limited) instead.
+///
+/// # Panics
+///
+/// Polling the returned future panics if the limit is zero.
+///
+/// This macro is only usable inside of async functions, closures, and blocks.
+///
+/// # Examples
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::{future, join_limited};
+/// use std::cell::Cell;
+///
+/// let in_flight = Cell::new(0);
+/// let most_in_flight = Cell::new(0);
+/// let request = |n: u8| {
+///     let (in_flight, most_in_flight) = (&in_flight, &most_in_flight);
+///     async move {
+///         in_flight.set(in_flight.get() + 1);
+///         most_in_flight.set(most_in_flight.get().max(in_flight.get()));
+///         future::yield_now().await;
+///         in_flight.set(in_flight.get() - 1);
+///         n
+///     }
+/// };
+///
+/// let a = request(1);
+/// let b = request(2);
+/// let c = request(3);
+/// assert_eq!(join_limited!(2; a, b, c).await, (1, 2, 3));
+/// assert_eq!(most_in_flight.get(), 2);
+/// # });
+/// ```
+#[macro_export]
+macro_rules! join_limited {
+    ($limit:expr; $($fut:ident $(as $name:expr)?),* $(,)?) => { {
+        async {
+            let limit: usize = $limit;
+            assert!(limit > 0, "`join_limited!` needs a limit of at least one");
+            let mut index = 0;
+            $(
+                let $fut = $crate::utils::assert_future($fut);
+                let name = $crate::utils::future_name!($fut $(as $name)?);
+                let $fut = $crate::utils::child("join_limited", index, name, $fut);
+                index += 1;
+                // Pin the future on the stack, so that it stays in one place and
+                // is no longer accessible by the end user.
+                let mut $fut = $crate::utils::pin::pin!($crate::MaybeDone::new($fut));
+            )*
+            let _ = index;
+            let started: &mut [bool] = &mut [$({ let _ = &$fut; false }),*];
+            let mut running = 0;
+            $crate::utils::poll_fn(move |cx| {
+                use $crate::utils::future::Future;
+                use $crate::utils::task::Poll;
+
+                let mut all_done = true;
+                let mut index = 0;
+                $(
+                    // Futures are started in order, whenever a slot is free.
+                    if !started[index] && running < limit {
+                        started[index] = true;
+                        running += 1;
+                    }
+                    if started[index] && !$fut.is_done() {
+                        if Future::poll($fut.as_mut(), cx).is_ready() {
+                            running -= 1;
+                        }
+                    }
+                    all_done &= $fut.is_done();
+                    index += 1;
+                )*
+                let _ = (index, &mut running);
+                if all_done {
+                    Poll::Ready(($(
+                        $fut.as_mut().take().unwrap(),
+                    )*))
+                } else {
+                    Poll::Pending
+                }
+            }).await
+        }
+    } };
+    ($limit:expr; $($fut:expr),+ $(,)?) => {
+        $crate::utils::bind_futures!(join_limited [$limit;] [] $($fut),+)
+    };
+}
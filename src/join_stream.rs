@@ -1,19 +1,23 @@
-use std::pin::Pin;
-use std::task::{Context, Poll};
+use core::pin::Pin;
+use core::task::{Context, Poll};
 
 use futures_core::Stream;
 
-/// A stream joining two or more streams.
+/// A stream joining two streams.
 ///
-/// This stream is returned by `join!`.
+/// This stream used to be returned by `join_stream!`, which now merges all of
+/// its streams in a single flat stream instead.
+#[deprecated(note = "`join_stream!` no longer returns this type")]
 #[derive(Debug)]
 pub struct JoinStream<L, R> {
     left: L,
     right: R,
 }
 
+#[allow(deprecated)]
 impl<L, R> Unpin for JoinStream<L, R> {}
 
+#[allow(deprecated)]
 impl<L, R> JoinStream<L, R> {
     #[doc(hidden)]
     pub fn new(left: L, right: R) -> Self {
@@ -21,6 +25,7 @@ impl<L, R> JoinStream<L, R> {
     }
 }
 
+#[allow(deprecated)]
 impl<L, R, T> Stream for JoinStream<L, R>
 where
     L: Stream<Item = T> + Unpin,
@@ -42,6 +47,18 @@ where
 
 /// Combines multiple streams into a single stream of all their outputs.
 ///
+/// Items are yielded as soon as any of the streams has one available. To keep
+/// the streams from starving each other, polling starts after the stream that
+/// yielded last, so every stream gets the first chance in turn. The combined
+/// stream ends once all of the streams have ended.
+///
+/// All streams must be `Unpin` and yield the same item type.
+///
+/// Passing `boxed;` before the streams lifts the `Unpin` requirement by
+/// boxing every stream into a [`BoxedJoinStream`](crate::BoxedJoinStream),
+/// which more streams can be pushed into later on. The streams must then be
+/// `Send`. This requires the `alloc` feature.
+///
 /// This macro is only usable inside of async functions, closures, and blocks.
 ///
 /// # Examples
@@ -64,13 +81,78 @@ where
 /// assert_eq!(s.next().await, None);
 /// # });
 /// ```
+///
+/// Boxing the streams:
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::join_stream;
+/// use futures::stream::{self, StreamExt};
+///
+/// let a = stream::iter(vec![1u8, 2]);
+/// // Streams which aren't `Unpin`, such as this one, can be passed as well.
+/// let b = stream::once(async { 3u8 });
+///
+/// let mut s = join_stream!(boxed; a, b, stream::once(async { 4u8 }).map(|n| n * 2));
+/// assert_eq!(s.next().await, Some(1));
+/// s.push(stream::once(async { 5u8 }));
+/// assert_eq!(s.collect::<Vec<_>>().await, vec![3, 8, 5, 2]);
+/// # });
+/// ```
 #[macro_export]
 macro_rules! join_stream {
-    ($stream1:ident, $stream2:ident, $($stream:ident),* $(,)?) => {{
-        let joined = $crate::JoinStream::new($stream1, $stream2);
+    (boxed; $($stream:expr),+ $(,)?) => {{
+        let mut streams = $crate::BoxedJoinStream::new();
+        let mut index = 0;
         $(
-            let joined = $crate::JoinStream::new(joined, $stream);
+            let stream = $crate::utils::assert_stream($stream);
+            streams.push($crate::utils::child_stream("join_stream", index, stringify!($stream), stream));
+            index += 1;
+        )+
+        let _ = index;
+        streams
+    }};
+    ($($stream:ident $(as $name:expr)?),* $(,)?) => {{
+        let mut index = 0;
+        $(
+            let $stream = $crate::utils::assert_stream($stream);
+            let name = $crate::utils::future_name!($stream $(as $name)?);
+            let $stream = $crate::utils::child_stream("join_stream", index, name, $stream);
+            index += 1;
+            let mut $stream = $crate::MaybeDoneStream::new($stream);
         )*
-        joined
+        let len = index;
+        let mut start = 0;
+        $crate::utils::poll_next_fn(move |cx| {
+            use $crate::utils::marker::Unpin;
+            use $crate::utils::pin::Pin;
+            use $crate::utils::stream::{FusedStream, Stream};
+            use $crate::utils::task::Poll;
+
+            // The streams are polled in a loop rather than one after the
+            // other, which keeps the expansion small for many streams. Polling
+            // starts from `start`, and wraps around to the streams before it.
+            let streams: &mut [&mut (dyn FusedStream<Item = _> + Unpin)] = &mut [$(&mut $stream),*];
+            for offset in 0..len {
+                let index = (start + offset) % len;
+                let stream = &mut *streams[index];
+                if stream.is_terminated() {
+                    continue;
+                }
+                if let Poll::Ready(Some(item)) = Stream::poll_next(Pin::new(stream), cx) {
+                    start = (index + 1) % len;
+                    return Poll::Ready(Some(item));
+                }
+            }
+
+            if streams.iter().all(|stream| stream.is_terminated()) {
+                Poll::Ready(None)
+            } else {
+                Poll::Pending
+            }
+        })
     }};
+    ($($stream:expr),+ $(,)?) => {
+        $crate::utils::bind_futures!(join_stream [] [] $($stream),+)
+    };
 }
@@ -0,0 +1,155 @@
+//! Definition of the `Lazy` async cell
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::cell::{Cell, OnceCell, RefCell};
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+
+/// A value which is initialized by a future the first time it is awaited.
+///
+/// Every call to [`get`](Lazy::get) returns a future resolving to a reference
+/// to the value. The first of them to be polled starts running the
+/// initialization future, and all of them share its output. This makes it
+/// possible to await the same value from several branches of a `join!` or
+/// `select!`.
+///
+/// # Examples
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::{join, Lazy};
+///
+/// let config = Lazy::new(async { 40u32 });
+///
+/// let a = async { *config.get().await + 1 };
+/// let b = async { *config.get().await + 2 };
+///
+/// assert_eq!(join!(a, b).await, (41, 42));
+/// # });
+/// ```
+pub struct Lazy<F: Future> {
+    value: OnceCell<F::Output>,
+    init: RefCell<Option<Pin<Box<F>>>>,
+    /// The wakers of the getters waiting on the value, by their ID.
+    waiters: RefCell<Vec<(usize, Waker)>>,
+    /// The ID of the getter which polled the initialization future last.
+    driver: Cell<Option<usize>>,
+    next_id: Cell<usize>,
+}
+
+impl<F: Future> Lazy<F> {
+    /// Create a new `Lazy` which will be initialized by `init`.
+    pub fn new(init: F) -> Self {
+        Self {
+            value: OnceCell::new(),
+            init: RefCell::new(Some(Box::pin(init))),
+            waiters: RefCell::new(Vec::new()),
+            driver: Cell::new(None),
+            next_id: Cell::new(0),
+        }
+    }
+
+    /// Returns a future resolving to a reference to the value, running the
+    /// initialization future if needed.
+    pub fn get(&self) -> LazyGet<'_, F> {
+        let id = self.next_id.get();
+        self.next_id.set(id.wrapping_add(1));
+        LazyGet { lazy: self, id }
+    }
+
+    /// Returns a reference to the value if it has been initialized.
+    pub fn get_now(&self) -> Option<&F::Output> {
+        self.value.get()
+    }
+
+    /// Consumes the `Lazy`, returning the value if it has been initialized.
+    pub fn into_inner(self) -> Option<F::Output> {
+        self.value.into_inner()
+    }
+}
+
+impl<F: Future> fmt::Debug for Lazy<F>
+where
+    F::Output: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Lazy")
+            .field("value", &self.value.get())
+            .finish()
+    }
+}
+
+/// Future for the [`Lazy::get`] method.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct LazyGet<'a, F: Future> {
+    lazy: &'a Lazy<F>,
+    id: usize,
+}
+
+impl<'a, F: Future> Future for LazyGet<'a, F> {
+    type Output = &'a F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let lazy = self.lazy;
+        if let Some(value) = lazy.value.get() {
+            return Poll::Ready(value);
+        }
+
+        // Whoever polls the initialization future last holds on to its
+        // wakeup, and wakes everyone else once it completes. If it is dropped
+        // before that, it wakes another getter to take over.
+        let mut init = lazy
+            .init
+            .try_borrow_mut()
+            .expect("`Lazy` awaited during its own initialization");
+        // The future is only dropped once the value has been set.
+        let future = init.as_mut().unwrap();
+        match future.as_mut().poll(cx) {
+            Poll::Ready(value) => {
+                *init = None;
+                drop(init);
+                assert!(lazy.value.set(value).is_ok());
+                lazy.driver.set(None);
+                for (_, waker) in lazy.waiters.take() {
+                    waker.wake();
+                }
+                Poll::Ready(lazy.value.get().unwrap())
+            }
+            Poll::Pending => {
+                lazy.driver.set(Some(self.id));
+                let mut waiters = lazy.waiters.borrow_mut();
+                match waiters.iter_mut().find(|(id, _)| *id == self.id) {
+                    Some((_, waker)) => waker.clone_from(cx.waker()),
+                    None => waiters.push((self.id, cx.waker().clone())),
+                }
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl<F: Future> Drop for LazyGet<'_, F> {
+    fn drop(&mut self) {
+        let lazy = self.lazy;
+        let mut waiters = lazy.waiters.borrow_mut();
+        waiters.retain(|(id, _)| *id != self.id);
+        if lazy.driver.get() != Some(self.id) {
+            return;
+        }
+        lazy.driver.set(None);
+        let next = waiters.first().map(|(_, waker)| waker.clone());
+        drop(waiters);
+        if let Some(next) = next {
+            next.wake();
+        }
+    }
+}
+
+impl<F: Future> fmt::Debug for LazyGet<'_, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LazyGet").finish()
+    }
+}
@@ -1,5 +1,59 @@
 //! Macros for async-std.
 //!
+//! # Features
+//!
+//! - `std` (enabled by default): implementations relying on the standard
+//!   library, such as [`task_local!`] and [`task::MockWaker`]. Enables
+//!   `alloc`.
+//! - `alloc` (enabled by default through `std`): combinators which need to
+//!   allocate, such as [`FuturesUnordered`].
+//! - `waker-routing`: give every future in `join!` and `try_join!` its own
+//!   waker, so that only the futures which were woken are polled again. This
+//!   allocates the wakers when the join is first polled. Enables `alloc`.
+//! - `critical-section`: [`task_local!`] without `std`, using the
+//!   [`critical-section`](https://docs.rs/critical-section) crate to track the
+//!   task being polled. This is only sound on a single execution context, so
+//!   it has to be enabled with [`task::assume_single_context`]. Enables
+//!   `alloc`.
+//! - `tracing`: every future passed to `join!`, `try_join!`, `select!` and
+//!   `try_select!` is polled inside of a `child` span of its own, recording
+//!   its index and name. These spans are children of the span which was
+//!   current when the macro started polling them. Tasks created with
+//!   [`task::with_locals`] enter the span which was current when they were
+//!   created every time they are polled.
+//! - `poll-observer`: report every poll of the futures passed to those macros,
+//!   and to `join_limited!`, to a [`PollObserver`](observer::PollObserver).
+//!   With `std` this includes [`SlowPolls`](observer::SlowPolls), which
+//!   detects polls taking too long.
+//! - `metrics`: record how often the futures passed to those macros, and the
+//!   streams passed to `join_stream!`, `try_join_stream!` and
+//!   `interleave_stream!`, are polled and woken, and how long they take to
+//!   complete, with a [`Recorder`](metrics::Recorder). Enables `std` and
+//!   `poll-observer`.
+//! - `budget`: limit how many of those futures and streams a task polls per
+//!   wakeup with [`task::with_budget`], yielding to the executor once the
+//!   budget is used up. Enables `std`.
+//! - `macros`: macros which need the companion `async-macros-impl` crate:
+//!   [`stream!`], [`try_stream!`], and the [`main`](macro@main),
+//!   [`test`](macro@test) and `bench` attributes. Enables `alloc`.
+//! - `executor`: a minimal [`block_on`](executor::block_on) executor. Enables
+//!   `std`.
+//! - `futures-channel`: select over the `oneshot` receivers of
+//!   [`futures-channel`](https://docs.rs/futures-channel) with the `in`
+//!   branches of [`stream_select!`], like over streams. Enables `alloc`.
+//! - `wasm-timer`: a [`Timer`](time::Timer) built on the `setTimeout`
+//!   function of JavaScript, on `wasm32` targets. Enables `std`.
+//!
+//! Without any features the crate is `#![no_std]`, and the core macros such
+//! as `join!`, `select!`, `try_join!` and `ready!` are still available.
+//!
+//! # Allocations
+//!
+//! `join!`, `try_join!`, `select!` and `try_select!` never allocate on the
+//! heap: all of their state lives inside of the future they expand to. The
+//! only exceptions are `join!` and `try_join!` with the `waker-routing`
+//! feature enabled, and all of them while a metrics recorder is set.
+//!
 //! # Examples
 //!
 //! ```
@@ -14,25 +68,259 @@
 //! # });
 //! ```
 
-#![forbid(future_incompatible, rust_2018_idioms)]
-#![deny(missing_debug_implementations, nonstandard_style)]
+#![cfg_attr(not(feature = "std"), no_std)]
+#![forbid(future_incompatible)]
+#![deny(missing_debug_implementations, nonstandard_style, rust_2018_idioms)]
 #![warn(missing_docs, unreachable_pub)]
 #![cfg_attr(test, deny(warnings))]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "executor")]
+pub mod executor;
+pub mod future;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+#[cfg(feature = "poll-observer")]
+pub mod observer;
+#[cfg(feature = "alloc")]
+pub mod oneshot;
+pub mod rng;
+pub mod task;
+pub mod time;
+
+mod assert;
+mod atomic_waker;
+mod bind_futures;
+#[cfg(feature = "alloc")]
+mod boxed_join_stream;
+#[cfg(feature = "budget")]
+mod budget;
+#[cfg(feature = "alloc")]
+mod buffer_unordered;
+mod cancelled;
+#[cfg(feature = "std")]
+mod catch_unwind;
+mod child;
+mod cond;
+mod debounce;
+mod diagnostics;
+mod either;
+#[cfg(feature = "alloc")]
+mod futures_ordered;
+#[cfg(feature = "alloc")]
+mod futures_unordered;
+#[cfg(feature = "poll-observer")]
+mod global;
+mod hedge;
+mod idle_timeout;
+mod interleave_stream;
 mod join;
+#[cfg(feature = "alloc")]
+mod join_all_limited;
+mod join_array;
+#[cfg(feature = "std")]
+mod join_catch;
+mod join_limited;
 mod join_stream;
+#[cfg(feature = "alloc")]
+mod lazy;
+mod maybe;
 mod maybe_done;
+mod maybe_done_stream;
+#[cfg(feature = "std")]
+mod must_complete;
+mod next_or;
+#[cfg(feature = "alloc")]
+mod notify;
+mod now_or_never;
+mod pin;
 mod poll_fn;
+mod poll_next_fn;
+mod poll_once;
+mod quorum;
 mod ready;
+#[cfg(feature = "alloc")]
+mod remote;
 mod select;
+mod select_as;
+mod select_loop;
+mod select_priority;
+#[cfg(feature = "alloc")]
+mod semaphore;
+#[cfg(feature = "alloc")]
+mod shared;
+mod skip_until;
+#[cfg(feature = "macros")]
+mod stream;
+#[cfg(feature = "alloc")]
+mod stream_fn;
+mod stream_select;
+#[cfg(feature = "alloc")]
+mod sync;
+mod tagged_error;
+mod take_until;
+#[cfg(any(feature = "std", feature = "critical-section"))]
+mod task_context;
+#[cfg(any(feature = "std", feature = "critical-section"))]
+mod task_local;
+mod throttle;
+mod timeout;
 mod try_join;
+mod try_join_stream;
+mod try_maybe_done;
 mod try_select;
+#[cfg(feature = "macros")]
+mod try_stream;
+mod waker_router;
+#[cfg(feature = "alloc")]
+mod waker_set;
+mod yield_now;
 
+#[cfg(feature = "alloc")]
+pub use boxed_join_stream::BoxedJoinStream;
+#[cfg(feature = "alloc")]
+pub use buffer_unordered::{buffer_unordered, BufferUnordered};
+pub use cancelled::Cancelled;
+#[cfg(feature = "std")]
+pub use catch_unwind::{catch_unwind, CatchUnwind, Panicked};
+pub use either::Either;
+#[cfg(feature = "alloc")]
+pub use futures_ordered::FuturesOrdered;
+#[cfg(feature = "alloc")]
+pub use futures_unordered::FuturesUnordered;
+#[cfg(feature = "alloc")]
+pub use join_all_limited::{join_all_limited, JoinAllLimited};
+#[allow(deprecated)]
 pub use join_stream::JoinStream;
-pub use maybe_done::MaybeDone;
+#[cfg(feature = "alloc")]
+pub use lazy::{Lazy, LazyGet};
+pub use maybe_done::{maybe_done, MaybeDone, MaybeDoneProj, MaybeDoneStatus};
+pub use maybe_done_stream::MaybeDoneStream;
+#[cfg(feature = "std")]
+pub use must_complete::{must_complete, MustComplete};
+pub use next_or::{next_or, NextOr};
+#[cfg(feature = "alloc")]
+pub use notify::{Notified, Notify};
+pub use poll_fn::{poll_fn, poll_state_fn, PollFn, PollStateFn};
+pub use poll_next_fn::{poll_next_fn, PollNextFn};
+#[cfg(feature = "alloc")]
+pub use remote::{remote, Remote, RemoteHandle};
+#[cfg(feature = "alloc")]
+pub use semaphore::{Acquire, Semaphore, SemaphorePermit};
+#[cfg(feature = "alloc")]
+pub use shared::Shared;
+pub use skip_until::{skip_until, SkipUntil};
+#[cfg(feature = "alloc")]
+pub use stream_fn::{stream_fn, StreamFn, Yield, Yielder};
+pub use tagged_error::TaggedError;
+pub use take_until::{take_until, TakeUntil};
+pub use try_maybe_done::TryMaybeDone;
+
+/// Runs an `async fn main` on an executor.
+///
+/// The attribute turns `async fn main()` into a regular `fn main()`, which
+/// blocks on the body of the original function until it completes. The
+/// function may return a value, such as a `Result`, just like a regular
+/// `main`.
+///
+/// By default the [`block_on`](executor::block_on) function of the
+/// `executor` feature is used. Any other function taking a future and
+/// returning its output can be used instead, with `#[main(block_on =
+/// path::to::block_on)]`.
+///
+/// This requires the `macros` feature.
+///
+/// # Examples
+///
+/// ```
+/// use async_macros::{future, join};
+///
+/// #[async_macros::main(block_on = futures::executor::block_on)]
+/// async fn main() {
+///     let a = future::ready(1u8);
+///     let b = future::ready(2u8);
+///     assert_eq!(join!(a, b).await, (1, 2));
+/// }
+/// ```
+#[cfg(feature = "macros")]
+pub use async_macros_impl::main;
+
+/// Runs an `async fn` test on an executor.
+///
+/// This is the counterpart of [`main`](macro@main) for tests: the function is
+/// turned into a regular `#[test]` function, which blocks on the body of the
+/// original one. It accepts the same `block_on = path` argument, and also
+/// defaults to the `block_on` function of the `executor` feature.
+///
+/// This requires the `macros` feature.
+///
+/// # Examples
+///
+/// ```
+/// use async_macros::{future, select};
+///
+/// #[async_macros::test(block_on = futures::executor::block_on)]
+/// async fn select_picks_the_ready_future() {
+///     let a = future::pending::<u8>();
+///     let b = future::ready(1u8);
+///     assert_eq!(select!(a, b).await, 1);
+/// }
+/// ```
+#[cfg(feature = "macros")]
+pub use async_macros_impl::test;
+
+/// Benchmarks an `async fn`.
+///
+/// The attribute turns an `async fn` without arguments into a regular
+/// function, which runs the body of the original function a number of times
+/// with [`executor::bench`] and prints how long an iteration took on average.
+/// The function returns the [`Bench`](executor::Bench) result, and can be
+/// called from the `main` of a benchmark target with `harness = false`.
+///
+/// The number of iterations defaults to 1000, and can be set with
+/// `#[bench(iterations = n)]`. The output of the body is passed through
+/// [`black_box`](std::hint::black_box), so that it isn't optimized away.
+///
+/// This requires the `macros` and `executor` features.
+///
+/// # Examples
+///
+/// ```
+/// use async_macros::{future, join};
+///
+/// #[async_macros::bench(iterations = 100)]
+/// async fn join_two_futures() -> (u8, u8) {
+///     let a = future::ready(1u8);
+///     let b = future::ready(2u8);
+///     join!(a, b).await
+/// }
+///
+/// fn main() {
+///     let bench = join_two_futures();
+///     assert_eq!(bench.iterations(), 100);
+/// }
+/// ```
+#[cfg(all(feature = "macros", feature = "executor"))]
+pub use async_macros_impl::bench;
 
 /// Helper re-exports for use in macros.
 pub mod utils {
+    pub use super::child::{child, child_stream, ChildFuture, ChildStream};
+    pub use super::diagnostics::{
+        assert_future, assert_same_output, assert_stream, assert_try_future,
+    };
     pub use super::poll_fn::poll_fn;
-    pub use core::{future, pin, result, task};
+    pub use super::poll_next_fn::poll_next_fn;
+    pub use super::stream_select::{Branch, ReceiverBranch, StreamBranch};
+    pub use super::waker_router::WakerRouter;
+    pub use crate::__assert_same_output as assert_same_output;
+    pub use crate::__bind_futures as bind_futures;
+    pub use crate::__future_name as future_name;
+    pub use core::{array, clone, cmp, convert, future, marker, option, pin, result, task, time};
+    pub use futures_core::stream;
+
+    #[cfg(feature = "macros")]
+    pub use async_macros_impl::{stream_impl, try_stream_impl};
 }
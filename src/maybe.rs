@@ -0,0 +1,35 @@
+/// Awaits an optional future.
+///
+/// `maybe!(opt)` resolves to `None` right away if `opt` is `None`, and to
+/// `Some(output)` once the future completes otherwise. This makes it possible
+/// to include optional work in a `join!` without boxing the future or
+/// writing an enum by hand.
+///
+/// The option is evaluated when the returned future is first polled.
+///
+/// # Examples
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::{future, join, maybe};
+///
+/// let a = future::ready(1u8);
+/// let b = maybe!(Some(future::ready(2u8)));
+/// let c = maybe!(None::<future::Ready<u8>>);
+///
+/// assert_eq!(join!(a, b, c).await, (1, Some(2), None));
+/// # });
+/// ```
+#[macro_export]
+macro_rules! maybe {
+    ($fut:expr $(,)?) => {
+        async {
+            match $fut {
+                $crate::utils::option::Option::Some(fut) => {
+                    $crate::utils::option::Option::Some(fut.await)
+                }
+                $crate::utils::option::Option::None => $crate::utils::option::Option::None,
+            }
+        }
+    };
+}
@@ -7,6 +7,7 @@ use core::future::Future;
 use core::mem;
 use core::pin::Pin;
 
+use futures_core::future::FusedFuture;
 use futures_core::ready;
 use futures_core::task::{Context, Poll};
 
@@ -22,12 +23,57 @@ pub enum MaybeDone<Fut: Future> {
     Gone,
 }
 
+/// Wraps a future into a `MaybeDone`.
+///
+/// This is the same as [`MaybeDone::new`], and matches the constructor found in
+/// the `futures` crate.
+pub fn maybe_done<Fut: Future>(future: Fut) -> MaybeDone<Fut> {
+    MaybeDone::new(future)
+}
+
 impl<Fut: Future> MaybeDone<Fut> {
     /// Create a new instance of `MaybeDone`.
     pub fn new(future: Fut) -> MaybeDone<Fut> {
         Self::Future(future)
     }
 
+    /// Returns `true` if the inner future has not completed yet.
+    #[inline]
+    pub fn is_future(&self) -> bool {
+        matches!(self, MaybeDone::Future(_))
+    }
+
+    /// Returns `true` if the inner future has completed and its output has
+    /// not been taken yet.
+    #[inline]
+    pub fn is_done(&self) -> bool {
+        matches!(self, MaybeDone::Done(_))
+    }
+
+    /// Returns `true` if the output of the future has been taken.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # futures::executor::block_on(async {
+    /// use async_macros::MaybeDone;
+    /// use futures::future;
+    ///
+    /// let mut fut = MaybeDone::new(future::ready(1u8));
+    /// assert!(fut.is_future());
+    ///
+    /// (&mut fut).await;
+    /// assert!(fut.is_done());
+    ///
+    /// fut.take_unpin();
+    /// assert!(fut.is_gone());
+    /// # });
+    /// ```
+    #[inline]
+    pub fn is_gone(&self) -> bool {
+        matches!(self, MaybeDone::Gone)
+    }
+
     /// Returns an [`Option`] containing a reference to the output of the future.
     /// The output of this method will be [`Some`] if and only if the inner
     /// future has been completed and [`take`](MaybeDone::take)
@@ -47,12 +93,9 @@ impl<Fut: Future> MaybeDone<Fut> {
     /// has not yet been called.
     #[inline]
     pub fn output_mut(self: Pin<&mut Self>) -> Option<&mut Fut::Output> {
-        unsafe {
-            let this = self.get_unchecked_mut();
-            match this {
-                MaybeDone::Done(res) => Some(res),
-                _ => None,
-            }
+        match self.as_pin_mut() {
+            MaybeDoneProj::Done(res) => Some(res),
+            _ => None,
         }
     }
 
@@ -60,35 +103,211 @@ impl<Fut: Future> MaybeDone<Fut> {
     /// towards completion.
     #[inline]
     pub fn take(self: Pin<&mut Self>) -> Option<Fut::Output> {
+        if !self.is_done() {
+            return None;
+        }
+        self.project_replace(MaybeDone::Gone)
+    }
+
+    /// Attempt to take the output of a `MaybeDone` without driving it
+    /// towards completion.
+    ///
+    /// This is the same as [`take`](MaybeDone::take), and matches the method
+    /// found in the `futures` crate.
+    #[inline]
+    pub fn take_output(self: Pin<&mut Self>) -> Option<Fut::Output> {
+        self.take()
+    }
+
+    /// Returns an [`Option`] containing a mutable reference to the output of
+    /// the future, without requiring the `MaybeDone` to be pinned.
+    ///
+    /// This is the same as [`output_mut`](MaybeDone::output_mut), for futures
+    /// which are `Unpin`.
+    #[inline]
+    pub fn output_mut_unpin(&mut self) -> Option<&mut Fut::Output>
+    where
+        Fut: Unpin,
+    {
+        Pin::new(self).output_mut()
+    }
+
+    /// Attempt to take the output of a `MaybeDone` without driving it
+    /// towards completion, and without requiring it to be pinned.
+    ///
+    /// This is the same as [`take`](MaybeDone::take), for futures which are
+    /// `Unpin`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # futures::executor::block_on(async {
+    /// use async_macros::MaybeDone;
+    /// use futures::future;
+    ///
+    /// let mut fut = MaybeDone::new(future::ready(1u8));
+    /// assert_eq!(fut.take_unpin(), None);
+    ///
+    /// (&mut fut).await;
+    /// assert_eq!(fut.output_mut_unpin(), Some(&mut 1));
+    /// assert_eq!(fut.take_unpin(), Some(1));
+    /// # });
+    /// ```
+    #[inline]
+    pub fn take_unpin(&mut self) -> Option<Fut::Output>
+    where
+        Fut: Unpin,
+    {
+        Pin::new(self).take()
+    }
+
+    /// Poll the inner future, without panicking if the output has already
+    /// been taken.
+    ///
+    /// Unlike polling the `MaybeDone` as a future, this can be called
+    /// repeatedly from long-lived loops: once the future has completed it
+    /// resolves to [`MaybeDoneStatus::Done`] while the output is available,
+    /// and to [`MaybeDoneStatus::Gone`] after it has been taken.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # futures::executor::block_on(async {
+    /// use async_macros::{MaybeDone, MaybeDoneStatus};
+    /// use futures::future::{self, poll_fn};
+    /// use std::pin::Pin;
+    ///
+    /// let mut fut = MaybeDone::new(future::ready(1u8));
+    /// let status = poll_fn(|cx| Pin::new(&mut fut).poll_checked(cx)).await;
+    /// assert_eq!(status, MaybeDoneStatus::Done);
+    ///
+    /// fut.take_unpin();
+    /// let status = poll_fn(|cx| Pin::new(&mut fut).poll_checked(cx)).await;
+    /// assert_eq!(status, MaybeDoneStatus::Gone);
+    /// # });
+    /// ```
+    pub fn poll_checked(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<MaybeDoneStatus> {
+        if self.is_gone() {
+            return Poll::Ready(MaybeDoneStatus::Gone);
+        }
+        ready!(self.as_mut().poll(cx));
+        Poll::Ready(MaybeDoneStatus::Done)
+    }
+
+    /// Returns a pinned projection to the inner future or output.
+    ///
+    /// This allows polling or inspecting the inner future of a pinned
+    /// `MaybeDone` without any `unsafe`. All pin projections of `MaybeDone`
+    /// go through here.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # futures::executor::block_on(async {
+    /// use async_macros::{MaybeDone, MaybeDoneProj};
+    /// use futures::future;
+    /// use futures::pin_mut;
+    ///
+    /// let fut = MaybeDone::new(future::ready(1u8));
+    /// pin_mut!(fut);
+    ///
+    /// match fut.as_mut().as_pin_mut() {
+    ///     MaybeDoneProj::Future(inner) => assert_eq!(inner.await, 1),
+    ///     _ => unreachable!(),
+    /// }
+    ///
+    /// fut.set(MaybeDone::Done(2));
+    /// if let MaybeDoneProj::Done(output) = fut.as_mut().as_pin_mut() {
+    ///     *output += 1;
+    /// }
+    /// assert_eq!(fut.take(), Some(3));
+    /// # });
+    /// ```
+    #[inline]
+    pub fn as_pin_mut(self: Pin<&mut Self>) -> MaybeDoneProj<'_, Fut> {
+        // SAFETY: the inner future is structurally pinned: it is never moved
+        // out of a pinned `MaybeDone`, only dropped in place when the variant
+        // is replaced through `Pin::set`. The output is not pinned.
+        unsafe {
+            match self.get_unchecked_mut() {
+                MaybeDone::Future(fut) => MaybeDoneProj::Future(Pin::new_unchecked(fut)),
+                MaybeDone::Done(res) => MaybeDoneProj::Done(res),
+                MaybeDone::Gone => MaybeDoneProj::Gone,
+            }
+        }
+    }
+
+    /// Replaces `self` with `replacement`, returning the output if it was the
+    /// `Done` variant. A future in the `Future` variant is dropped in place.
+    #[inline]
+    fn project_replace(self: Pin<&mut Self>, replacement: Self) -> Option<Fut::Output> {
+        // SAFETY: the output is not pinned, so it may be moved out, and the
+        // future is only ever dropped in place through `Pin::set`.
+        //
+        // This can't be generated by `pin-project-lite`, which only projects
+        // enums with named fields, while the variants of `MaybeDone` are
+        // public tuple variants. Together with `as_pin_mut` this is the only
+        // `unsafe` code of `MaybeDone`.
         unsafe {
             let this = self.get_unchecked_mut();
-            match this {
-                MaybeDone::Done(_) => {}
-                MaybeDone::Future(_) | MaybeDone::Gone => return None,
-            };
-            if let MaybeDone::Done(output) = mem::replace(this, MaybeDone::Gone) {
-                Some(output)
-            } else {
-                unreachable!()
+            if let MaybeDone::Future(_) = this {
+                Pin::new_unchecked(this).set(replacement);
+                return None;
+            }
+            match mem::replace(this, replacement) {
+                MaybeDone::Done(output) => Some(output),
+                _ => None,
             }
         }
     }
+}
 
-    // fn ok(self) -> Option<Fut::Output> {}
+/// The state of a completed [`MaybeDone`].
+///
+/// This type is returned by [`MaybeDone::poll_checked`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MaybeDoneStatus {
+    /// The future has completed, and its output can be taken
+    Done,
+    /// The output of the future has already been taken
+    Gone,
+}
+
+/// A pinned projection of a [`MaybeDone`].
+///
+/// This type is returned by [`MaybeDone::as_pin_mut`].
+#[derive(Debug)]
+pub enum MaybeDoneProj<'a, Fut: Future> {
+    /// A pinned reference to the not-yet-completed future
+    Future(Pin<&'a mut Fut>),
+    /// A reference to the output of the completed future
+    Done(&'a mut Fut::Output),
+    /// The output has already been taken
+    Gone,
 }
 
+// The output is never pinned, so only the future needs to be `Unpin`.
+impl<Fut: Future + Unpin> Unpin for MaybeDone<Fut> {}
+
 impl<Fut: Future> Future for MaybeDone<Fut> {
     type Output = ();
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        let res = unsafe {
-            match Pin::as_mut(&mut self).get_unchecked_mut() {
-                MaybeDone::Future(a) => ready!(Pin::new_unchecked(a).poll(cx)),
-                MaybeDone::Done(_) => return Poll::Ready(()),
-                MaybeDone::Gone => panic!("MaybeDone polled after value taken"),
-            }
+        let res = match self.as_mut().as_pin_mut() {
+            MaybeDoneProj::Future(a) => ready!(a.poll(cx)),
+            MaybeDoneProj::Done(_) => return Poll::Ready(()),
+            MaybeDoneProj::Gone => panic!("MaybeDone polled after value taken"),
         };
         self.set(MaybeDone::Done(res));
         Poll::Ready(())
     }
 }
+
+impl<Fut: Future> FusedFuture for MaybeDone<Fut> {
+    fn is_terminated(&self) -> bool {
+        match self {
+            MaybeDone::Future(_) => false,
+            MaybeDone::Done(_) | MaybeDone::Gone => true,
+        }
+    }
+}
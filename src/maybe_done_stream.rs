@@ -0,0 +1,88 @@
+//! A type that wraps a stream to keep track of whether it has ended.
+
+use core::pin::Pin;
+
+use futures_core::stream::{FusedStream, Stream};
+use futures_core::task::{Context, Poll};
+
+/// A stream that may have ended.
+///
+/// Once the inner stream yields `None` it is dropped, and polling the
+/// `MaybeDoneStream` keeps yielding `None` without polling it again.
+///
+/// # Examples
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::MaybeDoneStream;
+/// use futures::stream::{self, FusedStream, StreamExt};
+///
+/// let mut s = MaybeDoneStream::new(stream::once(async { 1u8 }).boxed());
+/// assert!(!s.is_terminated());
+///
+/// assert_eq!(s.next().await, Some(1));
+/// assert_eq!(s.next().await, None);
+/// assert!(s.is_terminated());
+/// assert_eq!(s.next().await, None);
+/// # });
+/// ```
+#[derive(Debug)]
+pub enum MaybeDoneStream<S> {
+    /// A stream which has not ended yet
+    Stream(S),
+    /// The stream has ended
+    Done,
+}
+
+impl<S: Stream> MaybeDoneStream<S> {
+    /// Create a new instance of `MaybeDoneStream`.
+    pub fn new(stream: S) -> MaybeDoneStream<S> {
+        Self::Stream(stream)
+    }
+
+    /// Returns a pinned reference to the inner stream, if it hasn't ended.
+    ///
+    /// All pin projections of `MaybeDoneStream` go through here.
+    #[inline]
+    fn stream_pin_mut(self: Pin<&mut Self>) -> Option<Pin<&mut S>> {
+        // SAFETY: the inner stream is structurally pinned: it is never moved
+        // out of a pinned `MaybeDoneStream`, only dropped in place when the
+        // variant is replaced through `Pin::set`.
+        unsafe {
+            match self.get_unchecked_mut() {
+                MaybeDoneStream::Stream(s) => Some(Pin::new_unchecked(s)),
+                MaybeDoneStream::Done => None,
+            }
+        }
+    }
+}
+
+impl<S: Unpin> Unpin for MaybeDoneStream<S> {}
+
+impl<S: Stream> Stream for MaybeDoneStream<S> {
+    type Item = S::Item;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let res = match self.as_mut().stream_pin_mut() {
+            Some(s) => s.poll_next(cx),
+            None => return Poll::Ready(None),
+        };
+        if let Poll::Ready(None) = res {
+            self.set(MaybeDoneStream::Done);
+        }
+        res
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self {
+            MaybeDoneStream::Stream(s) => s.size_hint(),
+            MaybeDoneStream::Done => (0, Some(0)),
+        }
+    }
+}
+
+impl<S: Stream> FusedStream for MaybeDoneStream<S> {
+    fn is_terminated(&self) -> bool {
+        matches!(self, MaybeDoneStream::Done)
+    }
+}
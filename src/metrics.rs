@@ -0,0 +1,217 @@
+//! Metrics about the futures and streams passed to the macros.
+//!
+//! A [`Recorder`] set with [`set_recorder`] receives the [`ChildMetrics`] of
+//! every future passed to `join!`, `try_join!`, `select!`, `try_select!` or
+//! `join_limited!`, and of every stream passed to `join_stream!`,
+//! `try_join_stream!` or `interleave_stream!`: how often it was polled and
+//! woken, and how long it took to complete. Only one recorder can be set for
+//! the whole program, much like a logger.
+//!
+//! While a recorder is set, every future and stream allocates a waker of its
+//! own when it is first polled, to count its wakeups.
+
+use alloc::sync::Arc;
+use alloc::task::Wake;
+use core::fmt;
+use core::sync::atomic::{AtomicU64, Ordering};
+use core::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
+
+use crate::global::Global;
+use crate::observer::Child;
+use crate::sync::Mutex;
+
+/// Receives the metrics of the futures and streams passed to the macros.
+///
+/// # Examples
+///
+/// ```
+/// use async_macros::metrics::{self, ChildMetrics, Recorder};
+/// use async_macros::observer::Child;
+/// use async_macros::{future, select};
+/// use std::sync::Mutex;
+///
+/// struct Log(Mutex<Vec<(&'static str, bool)>>);
+///
+/// impl Recorder for Log {
+///     fn record(&self, child: &Child, metrics: &ChildMetrics) {
+///         self.0.lock().unwrap().push((child.name(), metrics.completed()));
+///     }
+/// }
+///
+/// static LOG: Log = Log(Mutex::new(Vec::new()));
+/// metrics::set_recorder(&LOG).unwrap();
+///
+/// futures::executor::block_on(async {
+///     let winner = future::ready(1u8);
+///     let loser = future::pending::<u8>();
+///     select!(loser, winner).await;
+/// });
+/// let mut log = LOG.0.lock().unwrap();
+/// log.sort();
+/// assert_eq!(*log, [("loser", false), ("winner", true)]);
+/// ```
+pub trait Recorder: Sync {
+    /// Called once `child` has completed, or when it is dropped before
+    /// completing.
+    ///
+    /// Futures and streams which were never polled aren't recorded.
+    fn record(&self, child: &Child, metrics: &ChildMetrics);
+}
+
+/// The metrics of a single future or stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChildMetrics {
+    polls: u64,
+    wakeups: u64,
+    elapsed: Duration,
+    completed: bool,
+}
+
+impl ChildMetrics {
+    /// Returns how often the future or stream was polled.
+    pub fn polls(&self) -> u64 {
+        self.polls
+    }
+
+    /// Returns how often the future or stream was woken.
+    pub fn wakeups(&self) -> u64 {
+        self.wakeups
+    }
+
+    /// Returns the time from the first poll until the future or stream
+    /// completed, or until it was dropped.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Returns whether the future or stream completed, rather than being
+    /// dropped before that.
+    pub fn completed(&self) -> bool {
+        self.completed
+    }
+}
+
+static RECORDER: Global<dyn Recorder> = Global::new();
+
+/// Sets the recorder for the whole program.
+///
+/// This can only be done once. Futures and streams which are already being
+/// polled are recorded from their next poll on.
+pub fn set_recorder(recorder: &'static dyn Recorder) -> Result<(), SetRecorderError> {
+    RECORDER.set(recorder).map_err(|()| SetRecorderError(()))
+}
+
+/// Returns the recorder, if one has been set.
+pub fn recorder() -> Option<&'static dyn Recorder> {
+    RECORDER.get()
+}
+
+/// The error returned by [`set_recorder`] when a recorder was already set.
+#[derive(Debug)]
+pub struct SetRecorderError(());
+
+impl fmt::Display for SetRecorderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a metrics recorder has already been set")
+    }
+}
+
+impl core::error::Error for SetRecorderError {}
+
+/// The metrics being collected for a child, from its first poll on.
+pub(crate) struct State {
+    polls: u64,
+    started: Option<Instant>,
+    counter: Option<(Arc<WakeCounter>, Waker)>,
+    recorded: bool,
+}
+
+impl State {
+    pub(crate) const fn new() -> Self {
+        Self {
+            polls: 0,
+            started: None,
+            counter: None,
+            recorded: false,
+        }
+    }
+
+    /// Calls `poll` with a context counting wakeups, if a recorder is set.
+    pub(crate) fn poll<T>(
+        &mut self,
+        cx: &mut Context<'_>,
+        poll: impl FnOnce(&mut Context<'_>) -> Poll<T>,
+    ) -> Poll<T> {
+        if recorder().is_none() || self.recorded {
+            return poll(cx);
+        }
+        self.polls += 1;
+        self.started.get_or_insert_with(Instant::now);
+        let (counter, waker) = self.counter.get_or_insert_with(|| {
+            let counter = Arc::new(WakeCounter {
+                wakeups: AtomicU64::new(0),
+                waker: Mutex::new(None),
+            });
+            (counter.clone(), Waker::from(counter))
+        });
+        counter.register(cx.waker());
+        poll(&mut Context::from_waker(waker))
+    }
+
+    /// Records the metrics, once.
+    pub(crate) fn finish(&mut self, child: &Child, completed: bool) {
+        let started = match self.started {
+            Some(started) if !self.recorded => started,
+            _ => return,
+        };
+        self.recorded = true;
+        if let Some(recorder) = recorder() {
+            let wakeups = match &self.counter {
+                Some((counter, _)) => counter.wakeups.load(Ordering::Relaxed),
+                None => 0,
+            };
+            recorder.record(
+                child,
+                &ChildMetrics {
+                    polls: self.polls,
+                    wakeups,
+                    elapsed: started.elapsed(),
+                    completed,
+                },
+            );
+        }
+        // Let go of the waker of the task.
+        self.counter = None;
+    }
+}
+
+/// Counts the wakeups of a child, passing them on to the task polling it.
+struct WakeCounter {
+    wakeups: AtomicU64,
+    waker: Mutex<Option<Waker>>,
+}
+
+impl WakeCounter {
+    fn register(&self, waker: &Waker) {
+        let mut current = self.waker.lock();
+        match &*current {
+            Some(current) if current.will_wake(waker) => {}
+            _ => *current = Some(waker.clone()),
+        }
+    }
+}
+
+impl Wake for WakeCounter {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.wakeups.fetch_add(1, Ordering::Relaxed);
+        let waker = self.waker.lock().clone();
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
@@ -0,0 +1,165 @@
+//! Definition of the `MustComplete` cancellation guard
+
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+use pin_project_lite::pin_project;
+
+/// Flags a future which is dropped before it completes.
+///
+/// This is the same as [`must_complete`](crate::must_complete()), using the
+/// expression as the name of the future.
+///
+/// # Examples
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::{future, must_complete, select};
+/// use std::sync::atomic::{AtomicBool, Ordering};
+///
+/// static CANCELLED: AtomicBool = AtomicBool::new(false);
+///
+/// let write = must_complete!(async { future::yield_now().await })
+///     .on_cancel(|_name| CANCELLED.store(true, Ordering::SeqCst));
+/// let timeout = future::ready(());
+/// // `timeout` wins, and `write` is cancelled halfway through.
+/// select!(write, timeout).await;
+/// assert!(CANCELLED.load(Ordering::SeqCst));
+/// # });
+/// ```
+#[macro_export]
+macro_rules! must_complete {
+    ($fut:expr $(,)?) => {
+        $crate::must_complete(stringify!($fut), $fut)
+    };
+}
+
+/// Flags a future which is dropped before it completes.
+///
+/// Some operations aren't cancellation safe: dropping them halfway through,
+/// such as when another branch of a `select!` wins, loses data. Wrapping them
+/// in `must_complete` catches such accidental cancellations. If the returned
+/// future is dropped after it was first polled but before the future
+/// completed, it panics when debug assertions are enabled, and logs a warning
+/// otherwise. The warning is a `tracing` event with the `tracing` feature,
+/// and is printed to stderr without it.
+///
+/// [`on_cancel`](MustComplete::on_cancel) replaces this with a callback of
+/// your own.
+///
+/// This requires the `std` feature.
+///
+/// # Examples
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::{future, must_complete};
+///
+/// let write = must_complete("write", future::ready(1u8));
+/// assert_eq!(write.await, 1);
+/// # });
+/// ```
+pub fn must_complete<F: Future>(name: &'static str, future: F) -> MustComplete<F> {
+    MustComplete {
+        name,
+        on_cancel: None,
+        state: State::Created,
+        future,
+    }
+}
+
+pin_project! {
+    /// Future for the [`must_complete`](crate::must_complete()) function.
+    #[must_use = "futures do nothing unless you `.await` or poll them"]
+    pub struct MustComplete<F> {
+        name: &'static str,
+        on_cancel: Option<fn(&'static str)>,
+        state: State,
+        #[pin]
+        future: F,
+    }
+
+    impl<F> PinnedDrop for MustComplete<F> {
+        fn drop(this: Pin<&mut Self>) {
+            let this = this.project();
+            if *this.state != State::Polled {
+                return;
+            }
+            match *this.on_cancel {
+                Some(on_cancel) => on_cancel(this.name),
+                // Panicking while unwinding would abort.
+                None if cfg!(debug_assertions) && !std::thread::panicking() => {
+                    panic!("`{}` was dropped before it completed", this.name)
+                }
+                None => warn(this.name),
+            }
+        }
+    }
+}
+
+#[derive(PartialEq)]
+enum State {
+    Created,
+    Polled,
+    Completed,
+}
+
+impl<F> MustComplete<F> {
+    /// Calls `on_cancel` with the name of the future instead of panicking or
+    /// logging a warning when it is cancelled.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use async_macros::{future, must_complete, task};
+    /// use std::future::Future;
+    /// use std::sync::atomic::{AtomicBool, Ordering};
+    ///
+    /// static CANCELLED: AtomicBool = AtomicBool::new(false);
+    ///
+    /// let mut write = Box::pin(
+    ///     must_complete("write", future::pending::<()>())
+    ///         .on_cancel(|_name| CANCELLED.store(true, Ordering::SeqCst)),
+    /// );
+    /// assert!(write.as_mut().poll(&mut task::noop_context()).is_pending());
+    /// drop(write);
+    /// assert!(CANCELLED.load(Ordering::SeqCst));
+    /// ```
+    pub fn on_cancel(mut self, on_cancel: fn(&'static str)) -> Self {
+        self.on_cancel = Some(on_cancel);
+        self
+    }
+}
+
+impl<F: Future> Future for MustComplete<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        let this = self.project();
+        if *this.state == State::Created {
+            *this.state = State::Polled;
+        }
+        let output = this.future.poll(cx);
+        if output.is_ready() {
+            *this.state = State::Completed;
+        }
+        output
+    }
+}
+
+fn warn(name: &'static str) {
+    #[cfg(feature = "tracing")]
+    tracing::warn!(name, "future dropped before it completed");
+    #[cfg(not(feature = "tracing"))]
+    std::eprintln!("future `{}` dropped before it completed", name);
+}
+
+impl<F> fmt::Debug for MustComplete<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MustComplete")
+            .field("name", &self.name)
+            .finish()
+    }
+}
@@ -0,0 +1,84 @@
+//! Definition of the `NextOr` future
+
+use core::fmt;
+use core::future::{Future, IntoFuture};
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+use futures_core::stream::Stream;
+use pin_project_lite::pin_project;
+
+use crate::Either;
+
+/// Waits for either the next item of a stream or the output of a future,
+/// whichever comes first.
+///
+/// The returned future resolves to `Either::Left` with the next item of the
+/// stream, or `None` if it ended, or to `Either::Right` with the output of
+/// `future`. The stream is only borrowed, so that it can be waited on again
+/// afterwards, and `future` can be a `&mut` reference to a future which is
+/// waited on again as well, such as a shutdown signal in a loop. If both are
+/// ready at once, the future wins.
+///
+/// # Examples
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::{future, next_or, Either};
+/// use futures::stream;
+///
+/// let mut messages = stream::iter(vec![1u8, 2]);
+/// let mut shutdown = future::pending::<()>();
+///
+/// let mut received = Vec::new();
+/// while let Either::Left(Some(message)) = next_or(&mut messages, &mut shutdown).await {
+///     received.push(message);
+/// }
+/// assert_eq!(received, vec![1, 2]);
+/// # });
+/// ```
+pub fn next_or<S, F>(stream: &mut S, future: F) -> NextOr<'_, S, F::IntoFuture>
+where
+    S: Stream + Unpin + ?Sized,
+    F: IntoFuture,
+{
+    NextOr {
+        stream,
+        future: future.into_future(),
+    }
+}
+
+pin_project! {
+    /// Future for the [`next_or`] function.
+    #[must_use = "futures do nothing unless you `.await` or poll them"]
+    pub struct NextOr<'a, S: ?Sized, F> {
+        stream: &'a mut S,
+        #[pin]
+        future: F,
+    }
+}
+
+impl<S, F> Future for NextOr<'_, S, F>
+where
+    S: Stream + Unpin + ?Sized,
+    F: Future,
+{
+    type Output = Either<Option<S::Item>, F::Output>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        if let Poll::Ready(output) = this.future.poll(cx) {
+            return Poll::Ready(Either::Right(output));
+        }
+        match Pin::new(&mut **this.stream).poll_next(cx) {
+            Poll::Ready(item) => Poll::Ready(Either::Left(item)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl<S: ?Sized, F> fmt::Debug for NextOr<'_, S, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NextOr").finish()
+    }
+}
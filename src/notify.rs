@@ -0,0 +1,217 @@
+//! Definition of the `Notify` signal
+
+use alloc::vec::Vec;
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+
+use crate::sync::Mutex;
+
+/// A signal which tasks can wait on.
+///
+/// Waiting is done through the [`notified`](Notify::notified) future, which
+/// makes it possible to wait for manual signals such as a configuration
+/// reload or a shutdown in a `select!` arm.
+///
+/// [`notify_one`](Notify::notify_one) wakes a single waiter, or stores a
+/// permit for the next one if nobody is waiting.
+/// [`notify_waiters`](Notify::notify_waiters) wakes every future created
+/// before it was called.
+///
+/// # Examples
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::{join, Notify};
+///
+/// let reload = Notify::new();
+///
+/// let waiter = async {
+///     reload.notified().await;
+///     "reloaded"
+/// };
+/// let trigger = async {
+///     reload.notify_one();
+/// };
+///
+/// assert_eq!(join!(waiter, trigger).await, ("reloaded", ()));
+/// # });
+/// ```
+#[derive(Default)]
+pub struct Notify {
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    permit: bool,
+    /// Incremented by every call to `notify_waiters`.
+    epoch: usize,
+    next_id: usize,
+    waiters: Vec<Waiter>,
+}
+
+struct Waiter {
+    id: usize,
+    waker: Waker,
+    notified: bool,
+}
+
+impl State {
+    /// Notifies the first waiter which hasn't been notified yet, or stores a
+    /// permit if there is none.
+    fn notify_one(&mut self) -> Option<Waker> {
+        match self.waiters.iter_mut().find(|waiter| !waiter.notified) {
+            Some(waiter) => {
+                waiter.notified = true;
+                Some(waiter.waker.clone())
+            }
+            None => {
+                self.permit = true;
+                None
+            }
+        }
+    }
+
+    /// Removes a waiter, returning whether it was notified.
+    fn remove(&mut self, id: usize) -> bool {
+        match self.waiters.iter().position(|waiter| waiter.id == id) {
+            Some(index) => self.waiters.remove(index).notified,
+            None => false,
+        }
+    }
+}
+
+impl Notify {
+    /// Create a new `Notify` without a stored permit.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a future which resolves once this `Notify` is notified.
+    pub fn notified(&self) -> Notified<'_> {
+        Notified {
+            notify: self,
+            epoch: self.state.lock().epoch,
+            id: None,
+            done: false,
+        }
+    }
+
+    /// Wakes a single waiting future.
+    ///
+    /// If no future is waiting, a permit is stored so that the next one
+    /// resolves immediately. At most one permit is stored.
+    pub fn notify_one(&self) {
+        let waker = self.state.lock().notify_one();
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+
+    /// Wakes every future created by [`notified`](Notify::notified) before
+    /// this call.
+    ///
+    /// This doesn't store a permit.
+    pub fn notify_waiters(&self) {
+        let waiters = {
+            let mut state = self.state.lock();
+            state.epoch = state.epoch.wrapping_add(1);
+            core::mem::take(&mut state.waiters)
+        };
+        for waiter in waiters {
+            waiter.waker.wake();
+        }
+    }
+}
+
+impl fmt::Debug for Notify {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Notify").finish()
+    }
+}
+
+/// Future for the [`Notify::notified`] method.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct Notified<'a> {
+    notify: &'a Notify,
+    epoch: usize,
+    id: Option<usize>,
+    done: bool,
+}
+
+impl Future for Notified<'_> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.done {
+            return Poll::Ready(());
+        }
+
+        let notify = self.notify;
+        let mut state = notify.state.lock();
+        if state.epoch != self.epoch {
+            // `notify_waiters` was called, which already removed the waiter.
+            self.id = None;
+            self.done = true;
+            return Poll::Ready(());
+        }
+
+        if let Some(id) = self.id {
+            let waiter = state.waiters.iter_mut().find(|waiter| waiter.id == id);
+            let waiter = waiter.unwrap();
+            if !waiter.notified {
+                if !waiter.waker.will_wake(cx.waker()) {
+                    waiter.waker = cx.waker().clone();
+                }
+                return Poll::Pending;
+            }
+            state.remove(id);
+            self.id = None;
+            self.done = true;
+            return Poll::Ready(());
+        }
+
+        if core::mem::take(&mut state.permit) {
+            self.done = true;
+            return Poll::Ready(());
+        }
+
+        let id = state.next_id;
+        state.next_id = state.next_id.wrapping_add(1);
+        state.waiters.push(Waiter {
+            id,
+            waker: cx.waker().clone(),
+            notified: false,
+        });
+        self.id = Some(id);
+        Poll::Pending
+    }
+}
+
+impl Drop for Notified<'_> {
+    fn drop(&mut self) {
+        let waker = match self.id {
+            Some(id) => {
+                let mut state = self.notify.state.lock();
+                // Pass on a notification this future received but didn't use.
+                if state.remove(id) {
+                    state.notify_one()
+                } else {
+                    None
+                }
+            }
+            None => None,
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+impl fmt::Debug for Notified<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Notified").finish()
+    }
+}
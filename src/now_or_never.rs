@@ -0,0 +1,32 @@
+/// Polls a future a single time, returning its output if it is ready.
+///
+/// The future is polled with a waker that does nothing, so this is meant for
+/// futures which are expected to be ready already. If the future is not
+/// ready, `None` is returned and the future is dropped. Pass `&mut fut` to keep
+/// using an `Unpin` future afterwards.
+///
+/// Unlike [`poll_once!`], this macro does not need an async context.
+///
+/// # Examples
+///
+/// ```
+/// use async_macros::{future, now_or_never};
+///
+/// assert_eq!(now_or_never!(future::ready(1u8)), Some(1));
+/// assert_eq!(now_or_never!(future::pending::<u8>()), None);
+/// assert_eq!(now_or_never!(async { 2u8 }), Some(2));
+/// ```
+#[macro_export]
+macro_rules! now_or_never {
+    ($fut:expr $(,)?) => {{
+        use $crate::utils::future::Future;
+        use $crate::utils::task::Poll;
+
+        let fut = $crate::utils::pin::pin!($fut);
+        let mut cx = $crate::task::noop_context();
+        match Future::poll(fut, &mut cx) {
+            Poll::Ready(output) => $crate::utils::option::Option::Some(output),
+            Poll::Pending => $crate::utils::option::Option::None,
+        }
+    }};
+}
@@ -0,0 +1,240 @@
+//! Hooks into the polling of the futures passed to the macros.
+//!
+//! A [`PollObserver`] set with [`set_observer`] is notified whenever `join!`,
+//! `try_join!`, `select!`, `try_select!` or `join_limited!` polls one of its
+//! futures, so that profilers can see which branch of a combinator is doing
+//! work. Only one observer can be set for the whole program, much like a
+//! logger.
+//!
+//! With `std`, [`SlowPolls`] is an observer which reports polls taking longer
+//! than a threshold, which helps finding blocking code inside of joined
+//! futures.
+
+use core::fmt;
+#[cfg(feature = "std")]
+use std::cell::RefCell;
+#[cfg(feature = "std")]
+use std::time::{Duration, Instant};
+
+use crate::global::Global;
+
+/// A future passed to one of the macros.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Child {
+    pub(crate) combinator: &'static str,
+    pub(crate) index: usize,
+    pub(crate) name: &'static str,
+}
+
+impl Child {
+    /// Returns the name of the macro the future was passed to, such as
+    /// `"join"`.
+    pub fn combinator(&self) -> &'static str {
+        self.combinator
+    }
+
+    /// Returns the position of the future among those passed to the macro.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Returns the name of the future, as written in the macro invocation.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+/// Observes the polling of the futures passed to the macros.
+///
+/// All methods do nothing by default. They are called from within `poll`, so
+/// they should return quickly.
+///
+/// # Examples
+///
+/// ```
+/// use async_macros::observer::{self, Child, PollObserver};
+/// use async_macros::{future, join};
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+///
+/// struct CountPolls(AtomicUsize);
+///
+/// impl PollObserver for CountPolls {
+///     fn on_poll_start(&self, _child: &Child) {
+///         self.0.fetch_add(1, Ordering::Relaxed);
+///     }
+/// }
+///
+/// static POLLS: CountPolls = CountPolls(AtomicUsize::new(0));
+/// observer::set_observer(&POLLS).unwrap();
+///
+/// futures::executor::block_on(async {
+///     let a = future::ready(1u8);
+///     let b = future::ready(2u8);
+///     join!(a, b).await;
+/// });
+/// assert_eq!(POLLS.0.load(Ordering::Relaxed), 2);
+/// ```
+pub trait PollObserver: Sync {
+    /// Called right before `child` is polled.
+    fn on_poll_start(&self, child: &Child) {
+        let _ = child;
+    }
+
+    /// Called right after `child` was polled, with whether it was ready.
+    fn on_poll_end(&self, child: &Child, ready: bool) {
+        let _ = (child, ready);
+    }
+
+    /// Called once `child` has completed, after [`on_poll_end`].
+    ///
+    /// [`on_poll_end`]: PollObserver::on_poll_end
+    fn on_complete(&self, child: &Child) {
+        let _ = child;
+    }
+}
+
+static OBSERVER: Global<dyn PollObserver> = Global::new();
+
+/// Sets the observer for the whole program.
+///
+/// This can only be done once. Futures which are already being polled are
+/// observed from their next poll on.
+pub fn set_observer(observer: &'static dyn PollObserver) -> Result<(), SetObserverError> {
+    OBSERVER.set(observer).map_err(|()| SetObserverError(()))
+}
+
+/// Returns the observer, if one has been set.
+pub fn observer() -> Option<&'static dyn PollObserver> {
+    OBSERVER.get()
+}
+
+pub(crate) use observer as get;
+
+/// The error returned by [`set_observer`] when an observer was already set.
+#[derive(Debug)]
+pub struct SetObserverError(());
+
+impl fmt::Display for SetObserverError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a poll observer has already been set")
+    }
+}
+
+impl core::error::Error for SetObserverError {}
+
+/// An observer which reports polls taking longer than a threshold.
+///
+/// Polls which block, for example on file IO or a lock, keep every other
+/// future of the task from making progress. `SlowPolls` measures how long each
+/// poll of the futures passed to the macros takes, and calls its hook with the
+/// future and the duration of every poll which takes longer than the
+/// threshold.
+///
+/// Since futures passed to a macro may contain macros of their own, the time
+/// of a slow poll is also counted towards the futures it is nested in.
+///
+/// # Examples
+///
+/// ```
+/// use async_macros::observer::{self, Child, SlowPolls};
+/// use async_macros::{future, join};
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+/// use std::time::Duration;
+///
+/// static SLOW_POLLS: AtomicUsize = AtomicUsize::new(0);
+///
+/// fn report(child: &Child, _duration: Duration) {
+///     assert_eq!(child.name(), "blocking");
+///     SLOW_POLLS.fetch_add(1, Ordering::Relaxed);
+/// }
+///
+/// static DETECTOR: SlowPolls<fn(&Child, Duration)> =
+///     SlowPolls::new(Duration::from_millis(5), report);
+/// observer::set_observer(&DETECTOR).unwrap();
+///
+/// futures::executor::block_on(async {
+///     let blocking = async { std::thread::sleep(Duration::from_millis(20)) };
+///     let quick = future::ready(());
+///     join!(blocking, quick).await;
+/// });
+/// assert_eq!(SLOW_POLLS.load(Ordering::Relaxed), 1);
+/// ```
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct SlowPolls<F> {
+    threshold: Duration,
+    on_slow_poll: F,
+}
+
+#[cfg(feature = "std")]
+impl<F> SlowPolls<F>
+where
+    F: Fn(&Child, Duration) + Sync,
+{
+    /// Create a new observer calling `on_slow_poll` for every poll which
+    /// takes longer than `threshold`.
+    pub const fn new(threshold: Duration, on_slow_poll: F) -> Self {
+        Self {
+            threshold,
+            on_slow_poll,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl SlowPolls<fn(&Child, Duration)> {
+    /// Create a new observer warning about every poll which takes longer
+    /// than `threshold`.
+    ///
+    /// The warning is emitted as a `tracing` event with the `tracing`
+    /// feature, and printed to stderr otherwise.
+    pub const fn warn(threshold: Duration) -> Self {
+        Self::new(threshold, warn_slow_poll)
+    }
+}
+
+#[cfg(feature = "std")]
+fn warn_slow_poll(child: &Child, duration: Duration) {
+    #[cfg(feature = "tracing")]
+    tracing::warn!(
+        combinator = child.combinator,
+        index = child.index,
+        name = child.name,
+        ?duration,
+        "slow poll"
+    );
+    #[cfg(not(feature = "tracing"))]
+    std::eprintln!(
+        "slow poll: future `{}` of `{}!` took {:?}",
+        child.name,
+        child.combinator,
+        duration
+    );
+}
+
+#[cfg(feature = "std")]
+std::thread_local! {
+    /// When the polls which are in progress on this thread started, innermost
+    /// last.
+    static POLL_STARTS: RefCell<Vec<Instant>> = const { RefCell::new(Vec::new()) };
+}
+
+#[cfg(feature = "std")]
+impl<F> PollObserver for SlowPolls<F>
+where
+    F: Fn(&Child, Duration) + Sync,
+{
+    fn on_poll_start(&self, _child: &Child) {
+        POLL_STARTS.with(|starts| starts.borrow_mut().push(Instant::now()));
+    }
+
+    fn on_poll_end(&self, child: &Child, _ready: bool) {
+        let start = POLL_STARTS.with(|starts| starts.borrow_mut().pop());
+        if let Some(start) = start {
+            let duration = start.elapsed();
+            if duration > self.threshold {
+                (self.on_slow_poll)(child, duration);
+            }
+        }
+    }
+}
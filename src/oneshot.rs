@@ -0,0 +1,202 @@
+//! A channel for sending a single value between tasks.
+//!
+//! This is the companion to `select!` for completion and cancellation
+//! signals: the receiver is a future which resolves once a value is sent, or
+//! once the sender is dropped.
+//!
+//! # Examples
+//!
+//! ```
+//! # futures::executor::block_on(async {
+//! use async_macros::{future, oneshot, select};
+//!
+//! let (tx, rx) = oneshot::channel::<()>();
+//! let work = async {
+//!     future::yield_now().await;
+//!     "finished"
+//! };
+//! let shutdown = async {
+//!     let _ = rx.await;
+//!     "shut down"
+//! };
+//!
+//! tx.send(()).unwrap();
+//! assert_eq!(select!(work, shutdown).await, "shut down");
+//! # });
+//! ```
+
+use alloc::sync::Arc;
+use core::error::Error;
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+
+use crate::sync::Mutex;
+
+/// Creates a new oneshot channel, returning the sender and receiver halves.
+pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
+    let inner = Arc::new(Mutex::new(State {
+        value: None,
+        rx_waker: None,
+        tx_waker: None,
+        tx_dropped: false,
+        rx_dropped: false,
+    }));
+    (
+        Sender {
+            inner: inner.clone(),
+        },
+        Receiver { inner },
+    )
+}
+
+struct State<T> {
+    value: Option<T>,
+    rx_waker: Option<Waker>,
+    tx_waker: Option<Waker>,
+    tx_dropped: bool,
+    rx_dropped: bool,
+}
+
+/// Stores `waker` in `slot`, unless it would wake the same task already.
+fn register(slot: &mut Option<Waker>, waker: &Waker) {
+    match slot {
+        Some(current) if current.will_wake(waker) => {}
+        _ => *slot = Some(waker.clone()),
+    }
+}
+
+/// The sending half of a oneshot channel.
+pub struct Sender<T> {
+    inner: Arc<Mutex<State<T>>>,
+}
+
+impl<T> Sender<T> {
+    /// Sends a value to the receiver, returning it back if the receiver was
+    /// dropped.
+    pub fn send(self, value: T) -> Result<(), T> {
+        let waker = {
+            let mut state = self.inner.lock();
+            if state.rx_dropped {
+                return Err(value);
+            }
+            state.value = Some(value);
+            state.rx_waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+        Ok(())
+    }
+
+    /// Returns whether the receiver was dropped.
+    pub fn is_canceled(&self) -> bool {
+        self.inner.lock().rx_dropped
+    }
+
+    /// Polls whether the receiver was dropped, registering the current task
+    /// to be woken when it is.
+    pub fn poll_canceled(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        let mut state = self.inner.lock();
+        if state.rx_dropped {
+            Poll::Ready(())
+        } else {
+            register(&mut state.tx_waker, cx.waker());
+            Poll::Pending
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let waker = {
+            let mut state = self.inner.lock();
+            state.tx_dropped = true;
+            state.rx_waker.take()
+        };
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+impl<T> fmt::Debug for Sender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Sender").finish()
+    }
+}
+
+/// The receiving half of a oneshot channel.
+///
+/// This is a future resolving to the value sent, or to [`Canceled`] if the
+/// sender was dropped without sending a value.
+pub struct Receiver<T> {
+    inner: Arc<Mutex<State<T>>>,
+}
+
+impl<T> Receiver<T> {
+    /// Attempts to receive the value without waiting.
+    ///
+    /// Returns `Ok(None)` if no value has been sent yet.
+    pub fn try_recv(&mut self) -> Result<Option<T>, Canceled> {
+        let mut state = self.inner.lock();
+        match state.value.take() {
+            Some(value) => Ok(Some(value)),
+            None if state.tx_dropped => Err(Canceled(())),
+            None => Ok(None),
+        }
+    }
+}
+
+impl<T> Unpin for Receiver<T> {}
+
+impl<T> Future for Receiver<T> {
+    type Output = Result<T, Canceled>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<T, Canceled>> {
+        let mut state = self.inner.lock();
+        if let Some(value) = state.value.take() {
+            Poll::Ready(Ok(value))
+        } else if state.tx_dropped {
+            Poll::Ready(Err(Canceled(())))
+        } else {
+            register(&mut state.rx_waker, cx.waker());
+            Poll::Pending
+        }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        let (waker, value) = {
+            let mut state = self.inner.lock();
+            state.rx_dropped = true;
+            (state.tx_waker.take(), state.value.take())
+        };
+        // Drop the value outside of the lock.
+        drop(value);
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+impl<T> fmt::Debug for Receiver<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Receiver").finish()
+    }
+}
+
+/// The error returned by a [`Receiver`] when its [`Sender`] was dropped
+/// without sending a value.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Canceled(());
+
+impl fmt::Display for Canceled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        "oneshot sender dropped without sending a value".fmt(f)
+    }
+}
+
+impl Error for Canceled {}
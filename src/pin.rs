@@ -0,0 +1,33 @@
+/// Pins values on the stack.
+///
+/// Each variable passed is shadowed by a `Pin<&mut _>` to its value. This
+/// makes it possible to poll `!Unpin` futures by hand, or to await them
+/// through `as_mut()` without giving them up. The original value is no longer
+/// accessible.
+///
+/// # Examples
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::pin;
+/// use std::future::Future;
+/// use std::pin::Pin;
+///
+/// let a = async { 1u8 };
+/// let b = async { 2u8 };
+/// pin!(a, b);
+///
+/// fn assert_pinned<F: Future>(_: &Pin<&mut F>) {}
+/// assert_pinned(&a);
+///
+/// assert_eq!(a.await + b.as_mut().await, 3);
+/// # });
+/// ```
+#[macro_export]
+macro_rules! pin {
+    ($($x:ident),* $(,)?) => {
+        $(
+            let mut $x = $crate::utils::pin::pin!($x);
+        )*
+    };
+}
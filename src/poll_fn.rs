@@ -15,14 +15,16 @@ impl<F> Unpin for PollFn<F> {}
 
 /// Creates a new future wrapping around a function returning [`Poll`].
 ///
-/// Polling the returned future delegates to the wrapped function.
+/// Polling the returned future delegates to the wrapped function. This is the
+/// building block the macros in this crate are implemented with, and can be
+/// used to write custom combinators in the same way.
 ///
 /// # Examples
 ///
 /// ```
 /// # futures::executor::block_on(async {
-/// use futures::future::poll_fn;
-/// use futures::task::{Context, Poll};
+/// use async_macros::poll_fn;
+/// use std::task::{Context, Poll};
 ///
 /// fn read_line(_cx: &mut Context<'_>) -> Poll<String> {
 ///     Poll::Ready("Hello, World!".into())
@@ -52,6 +54,66 @@ where
     type Output = T;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
-        (&mut self.f)(cx)
+        (self.f)(cx)
+    }
+}
+
+/// Future for the [`poll_state_fn`] function.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct PollStateFn<S, F> {
+    state: S,
+    f: F,
+}
+
+impl<S, F> Unpin for PollStateFn<S, F> {}
+
+/// Creates a new future wrapping around some state and a function returning
+/// [`Poll`].
+///
+/// Polling the returned future calls the wrapped function with a mutable
+/// reference to the state. This allows the future to own its state, rather
+/// than having to capture it in the closure.
+///
+/// # Examples
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::poll_state_fn;
+/// use std::task::Poll;
+///
+/// let countdown = poll_state_fn(3u8, |n, cx| {
+///     if *n == 0 {
+///         Poll::Ready("liftoff")
+///     } else {
+///         *n -= 1;
+///         cx.waker().wake_by_ref();
+///         Poll::Pending
+///     }
+/// });
+/// assert_eq!(countdown.await, "liftoff");
+/// # });
+/// ```
+pub fn poll_state_fn<S, T, F>(state: S, f: F) -> PollStateFn<S, F>
+where
+    F: FnMut(&mut S, &mut Context<'_>) -> Poll<T>,
+{
+    PollStateFn { state, f }
+}
+
+impl<S, F> fmt::Debug for PollStateFn<S, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PollStateFn").finish()
+    }
+}
+
+impl<S, T, F> Future for PollStateFn<S, F>
+where
+    F: FnMut(&mut S, &mut Context<'_>) -> Poll<T>,
+{
+    type Output = T;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        let this = &mut *self;
+        (this.f)(&mut this.state, cx)
     }
 }
@@ -0,0 +1,60 @@
+//! Definition of the `PollNextFn` adapter combinator
+
+use core::fmt;
+use core::pin::Pin;
+use futures_core::stream::Stream;
+use futures_core::task::{Context, Poll};
+
+/// Stream for the [`poll_next_fn`] function.
+#[must_use = "streams do nothing unless polled"]
+pub struct PollNextFn<F> {
+    f: F,
+}
+
+impl<F> Unpin for PollNextFn<F> {}
+
+/// Creates a new stream wrapping around a function returning [`Poll`].
+///
+/// Polling the returned stream delegates to the wrapped function. This is the
+/// stream counterpart of [`poll_fn`](crate::poll_fn), and makes it easy to
+/// build ad-hoc streams.
+///
+/// # Examples
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::poll_next_fn;
+/// use futures::stream::StreamExt;
+/// use std::task::Poll;
+///
+/// let mut n = 0;
+/// let counter = poll_next_fn(move |_cx| {
+///     n += 1;
+///     Poll::Ready(if n <= 3 { Some(n) } else { None })
+/// });
+/// assert_eq!(counter.collect::<Vec<_>>().await, vec![1, 2, 3]);
+/// # });
+/// ```
+pub fn poll_next_fn<T, F>(f: F) -> PollNextFn<F>
+where
+    F: FnMut(&mut Context<'_>) -> Poll<Option<T>>,
+{
+    PollNextFn { f }
+}
+
+impl<F> fmt::Debug for PollNextFn<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PollNextFn").finish()
+    }
+}
+
+impl<T, F> Stream for PollNextFn<F>
+where
+    F: FnMut(&mut Context<'_>) -> Poll<Option<T>>,
+{
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        (self.f)(cx)
+    }
+}
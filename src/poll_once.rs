@@ -0,0 +1,37 @@
+/// Polls a future a single time, returning the resulting [`Poll`].
+///
+/// The future must be `Unpin`. Pass `&mut fut`, or a pinned `fut.as_mut()`,
+/// to keep using the future afterwards.
+///
+/// [`Poll`]: core::task::Poll
+///
+/// This macro is only usable inside of async functions, closures, and blocks.
+///
+/// # Examples
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::{future, poll_once};
+/// use std::task::Poll;
+///
+/// let mut a = future::pending::<u8>();
+/// assert_eq!(poll_once!(&mut a), Poll::Pending);
+///
+/// let b = future::ready(1u8);
+/// assert_eq!(poll_once!(b), Poll::Ready(1));
+/// # });
+/// ```
+#[macro_export]
+macro_rules! poll_once {
+    ($fut:expr $(,)?) => {{
+        let mut fut = $fut;
+        $crate::utils::poll_fn(|cx| {
+            use $crate::utils::future::Future;
+            use $crate::utils::pin::Pin;
+            use $crate::utils::task::Poll;
+
+            Poll::Ready(Future::poll(Pin::new(&mut fut), cx))
+        })
+        .await
+    }};
+}
@@ -0,0 +1,83 @@
+#![allow(non_snake_case)]
+
+/// Awaits the first `n` of several similarly-typed futures to complete.
+///
+/// `quorum!(n; a, b, c)` polls all of the futures concurrently, and resolves
+/// to an array of `n` `(index, output)` pairs once `n` of them have completed,
+/// in the order they completed in. `index` is the position of the future
+/// among the futures passed to the macro. The other futures are dropped
+/// without being polled again. This covers what's in between `select!`,
+/// which waits for one future, and `join!`, which waits for all of them, such
+/// as reading from two out of three replicas.
+///
+/// `n` must be a constant expression, as it's the length of the array.
+///
+/// # Panics
+///
+/// Polling the returned future panics if `n` is larger than the number of
+/// futures.
+///
+/// This macro is only usable inside of async functions, closures, and blocks.
+///
+/// # Examples
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::{future, quorum};
+///
+/// let a = future::pending::<u8>();
+/// let b = future::ready(2u8);
+/// let c = future::ready(3u8);
+///
+/// assert_eq!(quorum!(2; a, b, c).await, [(1, 2), (2, 3)]);
+/// # });
+/// ```
+#[macro_export]
+macro_rules! quorum {
+    ($n:expr; $($fut:ident $(as $name:expr)?),* $(,)?) => { {
+        async {
+            use $crate::utils::future::Future;
+            use $crate::utils::option::Option;
+            use $crate::utils::task::Poll;
+
+            $crate::utils::assert_same_output!($($fut),*);
+
+            let mut index = 0;
+            $(
+                let $fut = $crate::utils::assert_future($fut);
+                let name = $crate::utils::future_name!($fut $(as $name)?);
+                let $fut = $crate::utils::child("quorum", index, name, $fut);
+                index += 1;
+                // Pin the future on the stack, so that it stays in one place and
+                // is no longer accessible by the end user.
+                let mut $fut = $crate::utils::pin::pin!($crate::MaybeDone::new($fut));
+            )*
+            assert!(
+                $n <= index,
+                "`quorum!` can't wait for more futures than it was given",
+            );
+
+            let mut outputs: [Option<_>; $n] = $crate::utils::array::from_fn(|_| Option::None);
+            let mut done = 0;
+            $crate::utils::poll_fn(move |cx| {
+                let mut index = 0;
+                $(
+                    if done < $n && $fut.is_future() && Future::poll($fut.as_mut(), cx).is_ready() {
+                        outputs[done] = $fut.as_mut().take().map(|output| (index, output));
+                        done += 1;
+                    }
+                    index += 1;
+                )*
+                let _ = index;
+                if done == $n {
+                    Poll::Ready(outputs.each_mut().map(|output| output.take().unwrap()))
+                } else {
+                    Poll::Pending
+                }
+            }).await
+        }
+    } };
+    ($n:expr; $($fut:expr),+ $(,)?) => {
+        $crate::utils::bind_futures!(quorum [$n;] [] $($fut),+)
+    };
+}
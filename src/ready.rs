@@ -10,3 +10,77 @@ macro_rules! ready {
         }
     };
 }
+
+/// Extracts the successful type of a `Poll<Result<T, E>>`.
+///
+/// This macro bakes in propagation of both `Pending` signals and errors by
+/// returning early. Errors are converted using [`From`], like `?` does.
+///
+/// # Examples
+///
+/// ```
+/// use async_macros::try_ready;
+/// use std::future::Future;
+/// use std::pin::Pin;
+/// use std::task::{Context, Poll};
+///
+/// fn poll_double<F>(fut: Pin<&mut F>, cx: &mut Context<'_>) -> Poll<Result<u8, String>>
+/// where
+///     F: Future<Output = Result<u8, &'static str>>,
+/// {
+///     let n = try_ready!(fut.poll(cx));
+///     Poll::Ready(Ok(n * 2))
+/// }
+/// ```
+#[macro_export]
+macro_rules! try_ready {
+    ($e:expr $(,)?) => {
+        match $e {
+            $crate::utils::task::Poll::Ready($crate::utils::result::Result::Ok(t)) => t,
+            $crate::utils::task::Poll::Ready($crate::utils::result::Result::Err(e)) => {
+                return $crate::utils::task::Poll::Ready($crate::utils::result::Result::Err(
+                    $crate::utils::convert::From::from(e),
+                ))
+            }
+            $crate::utils::task::Poll::Pending => return $crate::utils::task::Poll::Pending,
+        }
+    };
+}
+
+/// Extracts the item of a `Poll<Option<T>>`, as returned by
+/// [`Stream::poll_next`].
+///
+/// This macro bakes in propagation of `Pending` signals by returning early.
+/// When the inner stream has ended, `Poll::Ready(None)` is returned, ending
+/// the surrounding stream as well.
+///
+/// [`Stream::poll_next`]: futures_core::Stream::poll_next
+///
+/// # Examples
+///
+/// ```
+/// use async_macros::ready_some;
+/// use futures::stream::Stream;
+/// use std::pin::Pin;
+/// use std::task::{Context, Poll};
+///
+/// fn poll_double<S>(s: Pin<&mut S>, cx: &mut Context<'_>) -> Poll<Option<u8>>
+/// where
+///     S: Stream<Item = u8>,
+/// {
+///     let n = ready_some!(s.poll_next(cx));
+///     Poll::Ready(Some(n * 2))
+/// }
+/// ```
+#[macro_export]
+macro_rules! ready_some {
+    ($e:expr $(,)?) => {
+        match $e {
+            $crate::utils::task::Poll::Ready($crate::utils::option::Option::Some(t)) => t,
+            $crate::utils::task::Poll::Ready($crate::utils::option::Option::None) => {
+                return $crate::utils::task::Poll::Ready($crate::utils::option::Option::None)
+            }
+            $crate::utils::task::Poll::Pending => return $crate::utils::task::Poll::Pending,
+        }
+    };
+}
@@ -0,0 +1,101 @@
+//! Definition of the `remote` function
+
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+use pin_project_lite::pin_project;
+
+use crate::oneshot::{self, Receiver, Sender};
+
+/// Splits a future into a driver and a handle to its output.
+///
+/// The driver runs the future and resolves to `()` once it completes, so it
+/// can be joined together with other work in one place. The handle resolves
+/// to the output of the future, or to `None` if the driver was dropped before
+/// the future completed. Dropping the handle cancels the future the next time
+/// the driver is polled.
+///
+/// # Examples
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::{join, remote};
+///
+/// let (driver, handle) = remote(async { 1u8 + 1 });
+/// let waiter = async { handle.await };
+///
+/// assert_eq!(join!(driver, waiter).await, ((), Some(2)));
+/// # });
+/// ```
+pub fn remote<F: Future>(future: F) -> (Remote<F>, RemoteHandle<F::Output>) {
+    let (tx, rx) = oneshot::channel();
+    let remote = Remote {
+        future,
+        tx: Some(tx),
+    };
+    (remote, RemoteHandle { rx })
+}
+
+pin_project! {
+    /// The driver returned by [`remote`].
+    #[must_use = "futures do nothing unless you `.await` or poll them"]
+    pub struct Remote<F: Future> {
+        #[pin]
+        future: F,
+        tx: Option<Sender<F::Output>>,
+    }
+}
+
+impl<F: Future> Future for Remote<F> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let this = self.project();
+        let tx = match this.tx {
+            Some(tx) => tx,
+            None => return Poll::Ready(()),
+        };
+        if tx.poll_canceled(cx).is_ready() {
+            *this.tx = None;
+            return Poll::Ready(());
+        }
+
+        let output = match this.future.poll(cx) {
+            Poll::Ready(output) => output,
+            Poll::Pending => return Poll::Pending,
+        };
+        // The handle may have been dropped while polling.
+        let _ = this.tx.take().unwrap().send(output);
+        Poll::Ready(())
+    }
+}
+
+impl<F: Future> fmt::Debug for Remote<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Remote").finish()
+    }
+}
+
+/// The handle returned by [`remote`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct RemoteHandle<T> {
+    rx: Receiver<T>,
+}
+
+impl<T> Unpin for RemoteHandle<T> {}
+
+impl<T> Future for RemoteHandle<T> {
+    type Output = Option<T>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        Pin::new(&mut self.rx).poll(cx).map(Result::ok)
+    }
+}
+
+impl<T> fmt::Debug for RemoteHandle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RemoteHandle").finish()
+    }
+}
@@ -0,0 +1,109 @@
+//! Random number generators for the fair variants of the macros.
+//!
+//! `select!` and `try_select!` poll their futures in the order they were
+//! passed by default, which favors the first ones when several are ready at
+//! once. Passing `fair;` or `rng = <expr>;` before the futures makes them
+//! start polling from a random future instead:
+//!
+//! ```
+//! # futures::executor::block_on(async {
+//! use async_macros::rng::{SeedableRng, SmallRng};
+//! use async_macros::{future, select};
+//!
+//! let a = future::ready(1u8);
+//! let b = future::ready(2u8);
+//! let first = select!(rng = SmallRng::seed_from_u64(7); a, b).await;
+//!
+//! // The same seed always picks the same future.
+//! let a = future::ready(1u8);
+//! let b = future::ready(2u8);
+//! assert_eq!(select!(rng = SmallRng::seed_from_u64(7); a, b).await, first);
+//! # });
+//! ```
+//!
+//! `fair;` uses [`SmallRng::new`], so that scheduling is reproducible from
+//! one run of a program to the next. Tests which need a specific order can
+//! pass a seeded generator, or an [`Rng`] of their own.
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// A source of random numbers.
+///
+/// This is implemented for `&mut R`, so that a generator can be shared by
+/// several macros.
+pub trait Rng {
+    /// Returns the next random number.
+    fn next_u64(&mut self) -> u64;
+
+    /// Returns a random index in `0..len`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `len` is zero.
+    fn gen_index(&mut self, len: usize) -> usize {
+        assert!(len > 0, "cannot pick an index in an empty range");
+        ((u128::from(self.next_u64()) * len as u128) >> 64) as usize
+    }
+}
+
+impl<R: Rng + ?Sized> Rng for &mut R {
+    fn next_u64(&mut self) -> u64 {
+        (**self).next_u64()
+    }
+
+    fn gen_index(&mut self, len: usize) -> usize {
+        (**self).gen_index(len)
+    }
+}
+
+/// A random number generator which can be created from a seed.
+pub trait SeedableRng: Rng + Sized {
+    /// Create a new generator from `seed`.
+    ///
+    /// Generators created from the same seed return the same numbers.
+    fn seed_from_u64(seed: u64) -> Self;
+}
+
+/// A small and fast generator, using the wyrand algorithm.
+///
+/// This is not suitable for cryptography.
+#[derive(Debug, Clone)]
+pub struct SmallRng {
+    state: u64,
+}
+
+/// The number of generators created by `SmallRng::new`.
+static CREATED: AtomicUsize = AtomicUsize::new(0);
+
+impl SmallRng {
+    /// Create a new generator.
+    ///
+    /// Generators created this way are seeded with how many of them were
+    /// created before, rather than with a source of entropy, so a program
+    /// creating them in the same order gets the same numbers every time it
+    /// runs.
+    pub fn new() -> Self {
+        let created = CREATED.fetch_add(1, Ordering::Relaxed);
+        Self::seed_from_u64(created as u64)
+    }
+}
+
+impl Default for SmallRng {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Rng for SmallRng {
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0xa076_1d64_78bd_642f);
+        let t = u128::from(self.state) * u128::from(self.state ^ 0xe703_7ed1_a0b4_28db);
+        ((t >> 64) ^ t) as u64
+    }
+}
+
+impl SeedableRng for SmallRng {
+    fn seed_from_u64(seed: u64) -> Self {
+        Self { state: seed }
+    }
+}
@@ -11,6 +11,12 @@
 /// Note that this macro consumes all futures passed, and once a future is
 /// completed, all other futures are dropped.
 ///
+/// Passing `fair;` before the futures makes every poll start from a random
+/// future rather than the first one, so that no future is favored when
+/// several of them are ready. `rng = <expr>;` does the same with a
+/// [`Rng`](crate::rng::Rng) of your own, such as a seeded one to make tests
+/// deterministic. See the [`rng`](crate::rng) module.
+///
 /// This macro is only usable inside of async functions, closures, and blocks.
 ///
 /// # Examples
@@ -25,34 +31,95 @@
 /// let c = future::ready(2u8);
 ///
 /// assert_eq!(select!(a, b, c).await, 1u8);
+///
+/// let a = future::ready(1u8);
+/// let b = future::ready(2u8);
+/// let output = select!(fair; a, b).await;
+/// assert!(output == 1 || output == 2);
 /// # });
 /// ```
 #[macro_export]
 macro_rules! select {
-    ($($fut:ident),* $(,)?) => { {
+    (fair; $($fut:ident $(as $name:expr)?),+ $(,)?) => {
+        $crate::select!(rng = $crate::rng::SmallRng::new(); $($fut $(as $name)?),+)
+    };
+    (fair; $($fut:expr),+ $(,)?) => {
+        $crate::utils::bind_futures!(select [fair;] [] $($fut),+)
+    };
+    (rng = $rng:expr; $($fut:ident $(as $name:expr)?),+ $(,)?) => { {
         async {
+            let mut rng = $rng;
+            $crate::utils::assert_same_output!($($fut),*);
+            let mut index = 0;
             $(
-                // Move future into a local so that it is pinned in one place and
+                let $fut = $crate::utils::assert_future($fut);
+                let name = $crate::utils::future_name!($fut $(as $name)?);
+                let $fut = $crate::utils::child("select", index, name, $fut);
+                index += 1;
+                // Pin the future on the stack, so that it stays in one place and
                 // is no longer accessible by the end user.
-                let mut $fut = $crate::MaybeDone::new($fut);
+                let mut $fut = $crate::utils::pin::pin!($fut);
             )*
+            let len = index;
             $crate::utils::poll_fn(move |cx| {
                 use $crate::utils::future::Future;
+                use $crate::utils::pin::Pin;
                 use $crate::utils::task::Poll;
+
+                // Polling starts from a random future, and wraps around to the
+                // futures before it.
+                let start = $crate::rng::Rng::gen_index(&mut rng, len);
+                let futures: &mut [Pin<&mut dyn Future<Output = _>>] = &mut [$($fut.as_mut()),*];
+                for offset in 0..len {
+                    let turn = (start + offset) % len;
+                    if let Poll::Ready(output) = futures[turn].as_mut().poll(cx) {
+                        return Poll::Ready(output);
+                    }
+                }
+
+                // If nothing matched we return Pending.
+                Poll::Pending
+            }).await
+        }
+    } };
+    (rng = $rng:expr; $($fut:expr),+ $(,)?) => {
+        $crate::utils::bind_futures!(select [rng = $rng;] [] $($fut),+)
+    };
+    ($($fut:ident $(as $name:expr)?),* $(,)?) => { {
+        async {
+            $crate::utils::assert_same_output!($($fut),*);
+            let mut index = 0;
+            $(
+                let $fut = $crate::utils::assert_future($fut);
+                let name = $crate::utils::future_name!($fut $(as $name)?);
+                let $fut = $crate::utils::child("select", index, name, $fut);
+                index += 1;
+                // Pin the future on the stack, so that it stays in one place and
+                // is no longer accessible by the end user.
+                let mut $fut = $crate::utils::pin::pin!($fut);
+            )*
+            let _ = index;
+            $crate::utils::poll_fn(move |cx| {
+                use $crate::utils::future::Future;
                 use $crate::utils::pin::Pin;
+                use $crate::utils::task::Poll;
 
-                $(
-                    let fut = unsafe { Pin::new_unchecked(&mut $fut) };
-                    if Future::poll(fut, cx).is_ready() {
-                        let fut = unsafe { Pin::new_unchecked(&mut $fut) };
-                        let output = fut.take().unwrap();
+                // The futures are polled in a loop rather than one after the
+                // other, which keeps the expansion small for many futures. They
+                // are never polled again once one of them completed.
+                let futures: &mut [Pin<&mut dyn Future<Output = _>>] = &mut [$($fut.as_mut()),*];
+                for fut in futures.iter_mut() {
+                    if let Poll::Ready(output) = fut.as_mut().poll(cx) {
                         return Poll::Ready(output);
                     }
-                )*
+                }
 
                 // If nothing matched we return Pending.
                 Poll::Pending
             }).await
         }
-    } }
+    } };
+    ($($fut:expr),+ $(,)?) => {
+        $crate::utils::bind_futures!(select [] [] $($fut),+)
+    };
 }
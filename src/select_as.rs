@@ -0,0 +1,58 @@
+/// Waits for either one of several futures to complete, converting its output
+/// into a common type.
+///
+/// This is like [`select!`], except that the futures may have different output
+/// types, as long as each of them implements `Into<T>` for the type `T` passed
+/// before the futures, as in `select_as!(T; a, b, c)`. The output of the
+/// future which completed first is converted with `Into::into`. This avoids
+/// mapping every future to the common type by hand, for example to an enum
+/// with a variant for each future.
+///
+/// Note that this macro consumes all futures passed, and once a future is
+/// completed, all other futures are dropped.
+///
+/// This macro is only usable inside of async functions, closures, and blocks.
+///
+/// # Examples
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::{future, select_as};
+///
+/// #[derive(Debug, PartialEq)]
+/// enum Event {
+///     Message(String),
+///     Tick(u64),
+/// }
+///
+/// impl From<String> for Event {
+///     fn from(message: String) -> Self {
+///         Event::Message(message)
+///     }
+/// }
+///
+/// impl From<u64> for Event {
+///     fn from(tick: u64) -> Self {
+///         Event::Tick(tick)
+///     }
+/// }
+///
+/// let message = future::pending::<String>();
+/// let tick = future::ready(7u64);
+///
+/// assert_eq!(select_as!(Event; message, tick).await, Event::Tick(7));
+/// # });
+/// ```
+#[macro_export]
+macro_rules! select_as {
+    ($ty:ty; $($fut:ident $(as $name:expr)?),+ $(,)?) => { {
+        $(
+            let $fut = $crate::utils::assert_future($fut);
+            let $fut = async move { $crate::utils::convert::Into::<$ty>::into($fut.await) };
+        )*
+        $crate::select!($($fut $(as $name)?),+)
+    } };
+    ($ty:ty; $($fut:expr),+ $(,)?) => {
+        $crate::utils::bind_futures!(select_as [$ty;] [] $($fut),+)
+    };
+}
@@ -0,0 +1,58 @@
+/// Repeatedly waits on multiple sources, running the branch of the first one
+/// that becomes ready, until a branch breaks out of the loop.
+///
+/// `select_loop!` takes the same branches as [`stream_select!`] and wraps
+/// them in a `loop`. Branch bodies may `continue` to start the next round, or
+/// `break` with a value which becomes the output of the whole expression. The
+/// loop can be given a label, so bodies containing loops of their own can
+/// still break out of it. A `complete` branch can break out of the loop once
+/// every source is exhausted.
+///
+/// This macro is only usable inside of async functions, closures, and blocks.
+///
+/// # Examples
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::select_loop;
+/// use futures::stream::{self, StreamExt};
+///
+/// let mut shutdown = stream::pending::<()>();
+/// let mut numbers = stream::iter(vec![1u8, 2, 3, 4]);
+///
+/// let mut total = 0;
+/// let last = select_loop! {
+///     'events:
+///     _ = shutdown.next() => break 'events None,
+///     n = numbers.next() => {
+///         let n = match n {
+///             Some(n) => n,
+///             None => break 'events None,
+///         };
+///         if n % 2 == 0 {
+///             continue 'events;
+///         }
+///         total += n;
+///         if total > 3 {
+///             break 'events Some(n);
+///         }
+///     }
+/// };
+///
+/// assert_eq!(last, Some(3));
+/// assert_eq!(total, 4);
+/// # });
+/// ```
+#[macro_export]
+macro_rules! select_loop {
+    ($label:lifetime: $($arms:tt)*) => {
+        $label: loop {
+            $crate::stream_select! { $($arms)* }
+        }
+    };
+    ($($arms:tt)*) => {
+        loop {
+            $crate::stream_select! { $($arms)* }
+        }
+    };
+}
@@ -0,0 +1,107 @@
+#![allow(non_snake_case)]
+
+/// Waits for either one of several similarly-typed futures to complete,
+/// favoring the futures with the highest priority.
+///
+/// Every future is given a priority, as in `select_priority!(shutdown => 1,
+/// work => 0)`. Futures are polled from the highest priority to the lowest,
+/// so that when several of them are ready, one with the highest priority
+/// always wins. This makes it possible for control messages such as a
+/// shutdown to preempt regular work. Futures with the same priority are
+/// polled in a random order, so that none of them is favored, using a
+/// [`SmallRng`](crate::rng::SmallRng) or the [`Rng`](crate::rng::Rng) passed
+/// with `rng = <expr>;` before the futures.
+///
+/// Priorities can be of any type implementing `Ord`.
+///
+/// Note that this macro consumes all futures passed, and once a future is
+/// completed, all other futures are dropped.
+///
+/// This macro is only usable inside of async functions, closures, and blocks.
+///
+/// # Examples
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::{future, select_priority};
+///
+/// let work = future::ready("work");
+/// let shutdown = future::ready("shutdown");
+///
+/// let output = select_priority!(work => 0, shutdown => 1).await;
+/// assert_eq!(output, "shutdown");
+/// # });
+/// ```
+#[macro_export]
+macro_rules! select_priority {
+    ($($fut:ident => $priority:expr),+ $(,)?) => {
+        $crate::select_priority!(rng = $crate::rng::SmallRng::new(); $($fut => $priority),+)
+    };
+    (rng = $rng:expr; $($fut:ident => $priority:expr),+ $(,)?) => { {
+        async {
+            let mut rng = $rng;
+            let priorities = [$($priority),+];
+            $crate::utils::assert_same_output!($($fut),*);
+            let mut index = 0;
+            $(
+                let $fut = $crate::utils::assert_future($fut);
+                let $fut = $crate::utils::child("select_priority", index, stringify!($fut), $fut);
+                index += 1;
+                // Pin the future on the stack, so that it stays in one place and
+                // is no longer accessible by the end user.
+                let mut $fut = $crate::utils::pin::pin!($crate::MaybeDone::new($fut));
+            )*
+            let len = index;
+            // The futures from the highest priority to the lowest.
+            let mut order = [$({ let _ = stringify!($fut); 0 }),+];
+            for (index, slot) in order.iter_mut().enumerate() {
+                *slot = index;
+            }
+            order.sort_unstable_by(|&a, &b| $crate::utils::cmp::Ord::cmp(&priorities[b], &priorities[a]));
+            $crate::utils::poll_fn(move |cx| {
+                use $crate::utils::future::Future;
+                use $crate::utils::option::Option;
+                use $crate::utils::pin::Pin;
+                use $crate::utils::task::Poll;
+
+                // Shuffle the futures which have the same priority, so that
+                // none of them is favored.
+                let mut start = 0;
+                while start < len {
+                    let mut end = start + 1;
+                    while end < len && priorities[order[end]] == priorities[order[start]] {
+                        end += 1;
+                    }
+                    for i in (start + 1..end).rev() {
+                        let j = start + $crate::rng::Rng::gen_index(&mut rng, i - start + 1);
+                        order.swap(i, j);
+                    }
+                    start = end;
+                }
+
+                let mut winner = Option::None;
+                let children: &mut [Pin<&mut dyn Future<Output = ()>>] = &mut [$($fut.as_mut()),+];
+                for &turn in order.iter() {
+                    if children[turn].as_mut().poll(cx).is_ready() {
+                        winner = Option::Some(turn);
+                        break;
+                    }
+                }
+                let winner = match winner {
+                    Option::Some(winner) => winner,
+                    // If nothing matched we return Pending.
+                    Option::None => return Poll::Pending,
+                };
+                let mut index = 0;
+                $(
+                    if index == winner {
+                        return Poll::Ready($fut.as_mut().take().unwrap());
+                    }
+                    index += 1;
+                )*
+                let _ = index;
+                unreachable!()
+            }).await
+        }
+    } };
+}
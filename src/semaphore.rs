@@ -0,0 +1,179 @@
+//! Definition of the `Semaphore` concurrency limit
+
+use core::fmt;
+use core::future::Future;
+use core::mem;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+use crate::sync::Mutex;
+use crate::task::WakerSet;
+
+/// A counter of permits, for limiting how many futures run at once.
+///
+/// Futures wait for a permit with [`acquire`](Semaphore::acquire), and give
+/// it back by dropping the returned [`SemaphorePermit`]. This makes it
+/// possible to join many futures while only letting a few of them make
+/// progress at the same time.
+///
+/// Waiters are not woken in any particular order.
+///
+/// # Examples
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::{future, join, Semaphore};
+/// use std::cell::Cell;
+///
+/// let semaphore = Semaphore::new(2);
+/// let in_flight = Cell::new(0);
+/// let most_in_flight = Cell::new(0);
+///
+/// let work = || async {
+///     let _permit = semaphore.acquire().await;
+///     in_flight.set(in_flight.get() + 1);
+///     most_in_flight.set(most_in_flight.get().max(in_flight.get()));
+///     future::yield_now().await;
+///     in_flight.set(in_flight.get() - 1);
+/// };
+///
+/// let a = work();
+/// let b = work();
+/// let c = work();
+/// join!(a, b, c).await;
+/// assert_eq!(most_in_flight.get(), 2);
+/// # });
+/// ```
+pub struct Semaphore {
+    permits: Mutex<usize>,
+    waiters: WakerSet,
+}
+
+impl Semaphore {
+    /// Create a new semaphore with `permits` permits available.
+    pub fn new(permits: usize) -> Self {
+        Self {
+            permits: Mutex::new(permits),
+            waiters: WakerSet::new(),
+        }
+    }
+
+    /// Returns a future which resolves to a permit once one is available.
+    pub fn acquire(&self) -> Acquire<'_> {
+        Acquire {
+            semaphore: self,
+            key: None,
+        }
+    }
+
+    /// Takes a permit if one is available, without waiting.
+    pub fn try_acquire(&self) -> Option<SemaphorePermit<'_>> {
+        let mut permits = self.permits.lock();
+        if *permits == 0 {
+            return None;
+        }
+        *permits -= 1;
+        Some(SemaphorePermit { semaphore: self })
+    }
+
+    /// Returns the number of permits currently available.
+    pub fn available_permits(&self) -> usize {
+        *self.permits.lock()
+    }
+
+    /// Adds `n` permits to the semaphore.
+    pub fn add_permits(&self, n: usize) {
+        *self.permits.lock() += n;
+        for _ in 0..n {
+            if !self.waiters.notify_one() {
+                break;
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Semaphore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Semaphore")
+            .field("permits", &self.available_permits())
+            .finish()
+    }
+}
+
+/// Future for the [`Semaphore::acquire`] method.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct Acquire<'a> {
+    semaphore: &'a Semaphore,
+    key: Option<usize>,
+}
+
+impl<'a> Future for Acquire<'a> {
+    type Output = SemaphorePermit<'a>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<SemaphorePermit<'a>> {
+        let semaphore = self.semaphore;
+        // The waker is registered while holding the lock, so that a permit
+        // given back in the meantime can't be missed.
+        let mut permits = semaphore.permits.lock();
+        if *permits > 0 {
+            *permits -= 1;
+            if let Some(key) = self.key.take() {
+                semaphore.waiters.remove(key);
+            }
+            return Poll::Ready(SemaphorePermit { semaphore });
+        }
+
+        match self.key {
+            Some(key) if semaphore.waiters.update(key, cx.waker()) => {}
+            Some(key) => {
+                // Notified, but another future took the permit first.
+                semaphore.waiters.remove(key);
+                self.key = Some(semaphore.waiters.insert(cx.waker()));
+            }
+            None => self.key = Some(semaphore.waiters.insert(cx.waker())),
+        }
+        Poll::Pending
+    }
+}
+
+impl Drop for Acquire<'_> {
+    fn drop(&mut self) {
+        // Pass on a wakeup this future received but didn't use.
+        if let Some(key) = self.key {
+            if self.semaphore.waiters.remove(key) {
+                self.semaphore.waiters.notify_one();
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Acquire<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Acquire").finish()
+    }
+}
+
+/// A permit from a [`Semaphore`], which is given back when dropped.
+#[must_use = "the permit is given back as soon as it is dropped"]
+pub struct SemaphorePermit<'a> {
+    semaphore: &'a Semaphore,
+}
+
+impl SemaphorePermit<'_> {
+    /// Consumes the permit without giving it back to the semaphore.
+    pub fn forget(self) {
+        mem::forget(self);
+    }
+}
+
+impl Drop for SemaphorePermit<'_> {
+    fn drop(&mut self) {
+        self.semaphore.add_permits(1);
+    }
+}
+
+impl fmt::Debug for SemaphorePermit<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SemaphorePermit").finish()
+    }
+}
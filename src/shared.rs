@@ -0,0 +1,200 @@
+//! Definition of the `Shared` future
+
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use alloc::task::Wake;
+use alloc::vec::Vec;
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+
+use crate::sync::Mutex;
+
+/// A clonable handle to a single future.
+///
+/// All clones of a `Shared` drive the same underlying future, and resolve to
+/// a clone of its output once it completes. The handles may be awaited from
+/// different tasks, for example from several `select!` arms waiting on the
+/// same shutdown signal.
+///
+/// # Panics
+///
+/// If polling the future panics, the panic is propagated to the handle which
+/// was polling it, and every handle polled afterwards panics as well.
+///
+/// # Examples
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::{join, Shared};
+///
+/// let shutdown = Shared::new(async { "shutdown" });
+///
+/// let a = shutdown.clone();
+/// let b = shutdown;
+/// assert_eq!(join!(a, b).await, ("shutdown", "shutdown"));
+/// # });
+/// ```
+pub struct Shared<F: Future> {
+    inner: Arc<Inner<F>>,
+}
+
+struct Inner<F: Future> {
+    state: Mutex<State<F>>,
+    notifier: Arc<Notifier>,
+}
+
+enum State<F: Future> {
+    /// Nobody is polling the future.
+    Idle(Pin<Box<F>>),
+    /// A handle is polling the future. `repoll` is set if another handle was
+    /// woken in the meantime, so that the wakeup isn't lost.
+    Polling {
+        repoll: bool,
+    },
+    Done(Arc<F::Output>),
+    /// Polling the future panicked.
+    Poisoned,
+}
+
+/// Wakes every handle waiting on the future.
+struct Notifier {
+    wakers: Mutex<Vec<Waker>>,
+}
+
+impl Wake for Notifier {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        let wakers = core::mem::take(&mut *self.wakers.lock());
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+}
+
+impl Notifier {
+    fn register(&self, waker: &Waker) {
+        let mut wakers = self.wakers.lock();
+        if !wakers.iter().any(|w| w.will_wake(waker)) {
+            wakers.push(waker.clone());
+        }
+    }
+}
+
+impl<F: Future> Shared<F> {
+    /// Create a new `Shared` wrapping a future.
+    pub fn new(future: F) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                state: Mutex::new(State::Idle(Box::pin(future))),
+                notifier: Arc::new(Notifier {
+                    wakers: Mutex::new(Vec::new()),
+                }),
+            }),
+        }
+    }
+
+    /// Returns the output of the future if it has completed.
+    pub fn peek(&self) -> Option<F::Output>
+    where
+        F::Output: Clone,
+    {
+        let output = match &*self.inner.state.lock() {
+            State::Done(output) => output.clone(),
+            _ => return None,
+        };
+        Some((*output).clone())
+    }
+}
+
+impl<F: Future> Clone for Shared<F> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<F: Future> Unpin for Shared<F> {}
+
+impl<F: Future> Future for Shared<F>
+where
+    F::Output: Clone,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        let inner = &*self.inner;
+        inner.notifier.register(cx.waker());
+
+        let mut future = {
+            let mut state = inner.state.lock();
+            match &mut *state {
+                State::Done(output) => {
+                    let output = output.clone();
+                    drop(state);
+                    return Poll::Ready((*output).clone());
+                }
+                State::Polling { repoll } => {
+                    *repoll = true;
+                    return Poll::Pending;
+                }
+                State::Idle(_) => {}
+                State::Poisoned => panic!("`Shared` future panicked during poll"),
+            }
+            match core::mem::replace(&mut *state, State::Polling { repoll: false }) {
+                State::Idle(future) => future,
+                _ => unreachable!(),
+            }
+        };
+
+        // The future is polled without holding the lock, with a waker which
+        // wakes every handle.
+        let waker = Waker::from(inner.notifier.clone());
+        let mut notifier_cx = Context::from_waker(&waker);
+        loop {
+            let poison = Poison { inner };
+            let poll = future.as_mut().poll(&mut notifier_cx);
+            core::mem::forget(poison);
+            if let Poll::Ready(output) = poll {
+                let output = Arc::new(output);
+                *inner.state.lock() = State::Done(output.clone());
+                drop(future);
+                inner.notifier.wake_by_ref();
+                return Poll::Ready((*output).clone());
+            }
+
+            let mut state = inner.state.lock();
+            match &mut *state {
+                State::Polling { repoll } if *repoll => *repoll = false,
+                _ => {
+                    *state = State::Idle(future);
+                    return Poll::Pending;
+                }
+            }
+        }
+    }
+}
+
+/// Poisons the future on drop, which only happens if polling it panicked,
+/// and wakes every handle so that they don't wait for it forever.
+struct Poison<'a, F: Future> {
+    inner: &'a Inner<F>,
+}
+
+impl<F: Future> Drop for Poison<'_, F> {
+    fn drop(&mut self) {
+        *self.inner.state.lock() = State::Poisoned;
+        self.inner.notifier.wake_by_ref();
+    }
+}
+
+impl<F: Future> fmt::Debug for Shared<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Shared").finish()
+    }
+}
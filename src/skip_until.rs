@@ -0,0 +1,119 @@
+//! Definition of the `SkipUntil` stream adapter
+
+use core::fmt;
+use core::future::{Future, IntoFuture};
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+use futures_core::stream::{FusedStream, Stream};
+use pin_project_lite::pin_project;
+
+/// How many items a [`SkipUntil`] discards per poll, so that a stream which
+/// is always ready can't starve the executor.
+const SKIPS_PER_WAKEUP: usize = 32;
+
+/// Discards the items of a stream until a future completes, and then
+/// forwards all of them.
+///
+/// The future is polled before every item is pulled from the stream, so that
+/// the first item pulled after it completed is yielded. It is dropped as soon
+/// as it completes. This is the counterpart of [`take_until`](crate::take_until),
+/// for streams which should only be processed after a warm-up or once a
+/// subscription has been confirmed.
+///
+/// # Examples
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::{skip_until, Notify};
+/// use futures::stream::{self, StreamExt};
+///
+/// let subscribed = Notify::new();
+/// let events = stream::iter(vec![1u8, 2, 3]).inspect(|&event| {
+///     if event == 1 {
+///         subscribed.notify_one();
+///     }
+/// });
+///
+/// let events = skip_until(events, subscribed.notified());
+/// assert_eq!(events.collect::<Vec<_>>().await, vec![2, 3]);
+/// # });
+/// ```
+pub fn skip_until<S, F>(stream: S, future: F) -> SkipUntil<S, F::IntoFuture>
+where
+    S: Stream,
+    F: IntoFuture,
+{
+    SkipUntil {
+        stream,
+        future: Some(future.into_future()),
+        done: false,
+    }
+}
+
+pin_project! {
+    /// Stream for the [`skip_until`] function.
+    #[must_use = "streams do nothing unless polled"]
+    pub struct SkipUntil<S, F> {
+        #[pin]
+        stream: S,
+        // The future, until it completes.
+        #[pin]
+        future: Option<F>,
+        done: bool,
+    }
+}
+
+impl<S, F> SkipUntil<S, F> {
+    /// Returns `true` if the future completed, so that items are forwarded.
+    pub fn is_started(&self) -> bool {
+        self.future.is_none()
+    }
+}
+
+impl<S: Stream, F: Future> Stream for SkipUntil<S, F> {
+    type Item = S::Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<S::Item>> {
+        let this = self.project();
+        if *this.done {
+            return Poll::Ready(None);
+        }
+        let mut stream = this.stream;
+        let mut future = this.future;
+
+        for _ in 0..SKIPS_PER_WAKEUP {
+            if let Some(pending) = future.as_mut().as_pin_mut() {
+                if pending.poll(cx).is_ready() {
+                    future.set(None);
+                }
+            }
+
+            match stream.as_mut().poll_next(cx) {
+                Poll::Ready(Some(_)) if future.is_some() => {}
+                Poll::Ready(None) => {
+                    *this.done = true;
+                    return Poll::Ready(None);
+                }
+                item => return item,
+            }
+        }
+
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+impl<S: Stream, F: Future> FusedStream for SkipUntil<S, F> {
+    fn is_terminated(&self) -> bool {
+        self.done
+    }
+}
+
+impl<S, F> fmt::Debug for SkipUntil<S, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SkipUntil")
+            .field("started", &self.is_started())
+            .finish()
+    }
+}
@@ -0,0 +1,34 @@
+/// Creates a stream from an async block which `yield`s its items.
+///
+/// Every `yield value` hands `value` to the stream, and waits until the stream
+/// has yielded it before continuing. The stream ends once the block completes.
+/// The block may `.await` other futures in between items, and captures the
+/// variables it uses by value, like an `async move` block.
+///
+/// This expands to a call to [`stream_fn`](crate::stream_fn), and requires
+/// the `macros` feature.
+///
+/// # Examples
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::{future, stream};
+/// use futures::stream::StreamExt;
+///
+/// let limit = 3;
+/// let numbers = stream! {
+///     for n in 0..limit {
+///         future::yield_now().await;
+///         yield n * 2;
+///     }
+/// };
+///
+/// assert_eq!(numbers.collect::<Vec<u32>>().await, vec![0, 2, 4]);
+/// # });
+/// ```
+#[macro_export]
+macro_rules! stream {
+    ($($body:tt)*) => {
+        $crate::utils::stream_impl! { $crate; $($body)* }
+    };
+}
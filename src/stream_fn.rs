@@ -0,0 +1,179 @@
+//! Definition of the `StreamFn` stream
+
+use alloc::sync::Arc;
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+use futures_core::stream::{FusedStream, Stream};
+use pin_project_lite::pin_project;
+
+use crate::sync::Mutex;
+
+/// Creates a stream from an async closure, which sends the items of the
+/// stream through the [`Yielder`] it is passed.
+///
+/// Every item sent is yielded by the stream, and the stream ends once the
+/// future returned by the closure completes. The closure isn't called until
+/// the stream is first polled.
+///
+/// The [`stream!`](crate::stream!) macro, available with the `macros`
+/// feature, builds on this to write streams with `yield`.
+///
+/// # Examples
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::{future, stream_fn};
+/// use futures::stream::StreamExt;
+///
+/// let numbers = stream_fn(|yielder| async move {
+///     for n in 0..3u8 {
+///         future::yield_now().await;
+///         yielder.send(n).await;
+///     }
+/// });
+///
+/// assert_eq!(numbers.collect::<Vec<_>>().await, vec![0, 1, 2]);
+/// # });
+/// ```
+pub fn stream_fn<T, F, Fut>(f: F) -> StreamFn<T, F, Fut>
+where
+    F: FnOnce(Yielder<T>) -> Fut,
+    Fut: Future<Output = ()>,
+{
+    StreamFn {
+        slot: Arc::new(Mutex::new(None)),
+        state: State::Init { f: Some(f) },
+    }
+}
+
+pin_project! {
+    /// Stream for the [`stream_fn`] function.
+    #[must_use = "streams do nothing unless polled"]
+    pub struct StreamFn<T, F, Fut> {
+        slot: Arc<Mutex<Option<T>>>,
+        #[pin]
+        state: State<F, Fut>,
+    }
+}
+
+pin_project! {
+    #[project = StateProj]
+    enum State<F, Fut> {
+        Init { f: Option<F> },
+        Running { #[pin] future: Fut },
+        Done,
+    }
+}
+
+impl<T, F, Fut> Stream for StreamFn<T, F, Fut>
+where
+    F: FnOnce(Yielder<T>) -> Fut,
+    Fut: Future<Output = ()>,
+{
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let mut this = self.project();
+        if let StateProj::Init { f } = this.state.as_mut().project() {
+            let f = f.take().unwrap();
+            let yielder = Yielder {
+                slot: this.slot.clone(),
+            };
+            this.state.set(State::Running { future: f(yielder) });
+        }
+        let future = match this.state.as_mut().project() {
+            StateProj::Running { future } => future,
+            _ => return Poll::Ready(None),
+        };
+
+        let done = future.poll(cx).is_ready();
+        if done {
+            this.state.set(State::Done);
+        }
+        match this.slot.lock().take() {
+            Some(item) => Poll::Ready(Some(item)),
+            None if done => Poll::Ready(None),
+            None => Poll::Pending,
+        }
+    }
+}
+
+impl<T, F, Fut> FusedStream for StreamFn<T, F, Fut>
+where
+    F: FnOnce(Yielder<T>) -> Fut,
+    Fut: Future<Output = ()>,
+{
+    fn is_terminated(&self) -> bool {
+        matches!(self.state, State::Done) && self.slot.lock().is_none()
+    }
+}
+
+impl<T, F, Fut> fmt::Debug for StreamFn<T, F, Fut> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("StreamFn").finish()
+    }
+}
+
+/// The sending half of a [`stream_fn`] stream.
+pub struct Yielder<T> {
+    slot: Arc<Mutex<Option<T>>>,
+}
+
+impl<T> Yielder<T> {
+    /// Sends an item to the stream.
+    ///
+    /// The returned future completes once the stream has yielded the item.
+    /// It must be awaited from within the future driven by the stream.
+    pub fn send(&self, item: T) -> Yield<'_, T> {
+        Yield {
+            yielder: self,
+            item: Some(item),
+        }
+    }
+}
+
+impl<T> fmt::Debug for Yielder<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Yielder").finish()
+    }
+}
+
+/// Future for the [`Yielder::send`] method.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct Yield<'a, T> {
+    yielder: &'a Yielder<T>,
+    item: Option<T>,
+}
+
+impl<T> Unpin for Yield<'_, T> {}
+
+impl<T> Future for Yield<'_, T> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<()> {
+        let item = match self.item.take() {
+            Some(item) => item,
+            None => return Poll::Ready(()),
+        };
+        // The stream yields immediately when it finds an item, and polls this
+        // future again when the next item is requested. No wakeup is needed.
+        let mut slot = self.yielder.slot.lock();
+        if slot.is_none() {
+            *slot = Some(item);
+        } else {
+            // Another item is already waiting to be yielded.
+            drop(slot);
+            self.item = Some(item);
+        }
+        Poll::Pending
+    }
+}
+
+impl<T> fmt::Debug for Yield<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Yield").finish()
+    }
+}
@@ -0,0 +1,358 @@
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use futures_core::stream::FusedStream;
+
+/// Waits on multiple sources at once, running the branch of the first one
+/// that becomes ready.
+///
+/// Each branch has the form `pattern = future => body`. All futures are
+/// created and polled concurrently; the first one to complete has its output
+/// matched against its pattern, after which its body runs. Futures of the
+/// other branches are dropped. If the output does not match the pattern, no
+/// body runs.
+///
+/// `stream_select!` is designed to be used inside of a loop, with each branch
+/// awaiting the next item of a stream, e.g. `item = stream.next()`. The futures
+/// are dropped before the selected body runs, so bodies are free to use the
+/// streams again, as well as to `break` out of or `continue` the surrounding
+/// loop.
+///
+/// If multiple futures are ready at the same time, the branch listed first
+/// wins.
+///
+/// Streams can also be selected over directly, with branches of the form
+/// `pattern in stream => body`. The stream must be a [`FusedStream`] which is
+/// [`Unpin`], and its next item is matched against the pattern. Once the
+/// stream has ended, its branch no longer runs: the `None` it yields
+/// completes the select without running any body, and the branch is disabled
+/// from then on.
+///
+/// [`FusedStream`]: futures_core::stream::FusedStream
+///
+/// With the `futures-channel` feature, the receivers of
+/// `futures_channel::oneshot` can be selected over with `in` branches as
+/// well. Their output is matched against the pattern once it comes in, and
+/// the branch is disabled from then on, just like a stream which has ended.
+/// The receivers of `futures_channel::mpsc` are fused streams, so they can
+/// always be selected over this way.
+///
+/// A branch can be disabled with a guard, written `pattern = future, if
+/// condition => body` or `pattern in stream, if condition => body`. The
+/// condition is evaluated before the future is created, and if it is `false`
+/// the future is never created nor polled this time around. This is useful
+/// for sources which are only relevant in some states of a protocol.
+///
+/// A `complete => body` branch runs instead when every other branch is
+/// disabled, for example once all streams have ended. This lets loops over
+/// several sources stop cleanly once everything is exhausted.
+///
+/// # Panics
+///
+/// Panics if every branch is disabled and there is no `complete` branch, as
+/// nothing could ever complete.
+///
+/// This macro is only usable inside of async functions, closures, and blocks.
+///
+/// # Examples
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::stream_select;
+/// use futures::stream::{self, StreamExt};
+///
+/// let mut shutdown = stream::pending::<()>();
+/// let mut numbers = stream::iter(vec![1u8, 2, 3]);
+///
+/// let mut total = 0;
+/// loop {
+///     stream_select! {
+///         _ = shutdown.next() => break,
+///         n = numbers.next() => {
+///             match n {
+///                 Some(n) => total += n,
+///                 None => break,
+///             }
+///         }
+///     }
+/// }
+///
+/// assert_eq!(total, 6);
+/// # });
+/// ```
+///
+/// Selecting over streams directly, until all of them have ended:
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::stream_select;
+/// use futures::stream::{self, StreamExt};
+///
+/// let mut a = stream::iter(vec![1u8, 2]).fuse();
+/// let mut b = stream::iter(vec![10u8]).fuse();
+///
+/// let mut total = 0;
+/// loop {
+///     stream_select! {
+///         n in a => total += n,
+///         n in b => total += n,
+///         complete => break,
+///     }
+/// }
+///
+/// assert_eq!(total, 13);
+/// # });
+/// ```
+///
+/// Using a guard to stop listening for acks once none are outstanding:
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::stream_select;
+/// use futures::stream::{self, StreamExt};
+///
+/// let mut acks = stream::iter(vec![(), ()]).chain(stream::pending());
+/// let mut requests = stream::iter(vec![1u8, 2]);
+///
+/// let mut outstanding = 0;
+/// let mut log = vec![];
+/// loop {
+///     stream_select! {
+///         _ = acks.next(), if outstanding > 0 => {
+///             outstanding -= 1;
+///             log.push("ack");
+///         },
+///         request = requests.next() => match request {
+///             Some(_) => {
+///                 outstanding += 1;
+///                 log.push("request");
+///             }
+///             None => break,
+///         },
+///     }
+/// }
+///
+/// assert_eq!(log, ["request", "ack", "request", "ack"]);
+/// # });
+/// ```
+#[macro_export]
+macro_rules! stream_select {
+    (@collect [$($arms:tt)*] []) => {
+        $crate::stream_select!(@expand [$($arms)*] {
+            panic!("all branches of `stream_select!` are disabled")
+        })
+    };
+    (@collect [$($arms:tt)*] [$complete:block]) => {
+        $crate::stream_select!(@expand [$($arms)*] $complete)
+    };
+    (@collect [$($arms:tt)*] [] complete => $complete:block , $($rest:tt)*) => {
+        $crate::stream_select!(@collect [$($arms)*] [$complete] $($rest)*)
+    };
+    (@collect [$($arms:tt)*] [] complete => $complete:block $($rest:tt)*) => {
+        $crate::stream_select!(@collect [$($arms)*] [$complete] $($rest)*)
+    };
+    (@collect [$($arms:tt)*] [] complete => $complete:expr , $($rest:tt)*) => {
+        $crate::stream_select!(@collect [$($arms)*] [{ $complete }] $($rest)*)
+    };
+    (@collect [$($arms:tt)*] [] complete => $complete:expr) => {
+        $crate::stream_select!(@collect [$($arms)*] [{ $complete }])
+    };
+    // Every branch gets its own `slot` identifier: each step of `@collect`
+    // is a separate expansion, so the identifiers don't collide.
+    (@collect [$($arms:tt)*] [$($complete:tt)*] $pat:pat = $fut:expr , if $guard:expr => $($rest:tt)*) => {
+        $crate::stream_select!(@body [$($arms)*] [$($complete)*] (
+            slot,
+            $pat,
+            if $guard {
+                $crate::utils::option::Option::Some($fut)
+            } else {
+                $crate::utils::option::Option::None
+            }
+        ) $($rest)*)
+    };
+    (@collect [$($arms:tt)*] [$($complete:tt)*] $pat:pat = $fut:expr => $($rest:tt)*) => {
+        $crate::stream_select!(@body [$($arms)*] [$($complete)*] (
+            slot,
+            $pat,
+            $crate::utils::option::Option::Some($fut)
+        ) $($rest)*)
+    };
+    (@collect [$($arms:tt)*] [$($complete:tt)*] $pat:pat in $stream:expr , if $guard:expr => $($rest:tt)*) => {
+        $crate::stream_select!(@body [$($arms)*] [$($complete)*] (
+            slot,
+            $crate::utils::option::Option::Some($pat),
+            if $guard {
+                $crate::stream_select!(@next $stream)
+            } else {
+                $crate::utils::option::Option::None
+            }
+        ) $($rest)*)
+    };
+    (@collect [$($arms:tt)*] [$($complete:tt)*] $pat:pat in $stream:expr => $($rest:tt)*) => {
+        $crate::stream_select!(@body [$($arms)*] [$($complete)*] (
+            slot,
+            $crate::utils::option::Option::Some($pat),
+            $crate::stream_select!(@next $stream)
+        ) $($rest)*)
+    };
+    (@body [$($arms:tt)*] [$($complete:tt)*] ($($head:tt)*) $body:block , $($rest:tt)*) => {
+        $crate::stream_select!(@collect [$($arms)* ($($head)*, $body)] [$($complete)*] $($rest)*)
+    };
+    (@body [$($arms:tt)*] [$($complete:tt)*] ($($head:tt)*) $body:block $($rest:tt)*) => {
+        $crate::stream_select!(@collect [$($arms)* ($($head)*, $body)] [$($complete)*] $($rest)*)
+    };
+    (@body [$($arms:tt)*] [$($complete:tt)*] ($($head:tt)*) $body:expr , $($rest:tt)*) => {
+        $crate::stream_select!(@collect [$($arms)* ($($head)*, { $body })] [$($complete)*] $($rest)*)
+    };
+    (@body [$($arms:tt)*] [$($complete:tt)*] ($($head:tt)*) $body:expr) => {
+        $crate::stream_select!(@collect [$($arms)* ($($head)*, { $body })] [$($complete)*])
+    };
+    (@next $stream:expr) => {{
+        // Method resolution tries `Branch` by value before autoref, so that
+        // `StreamBranch` is picked for streams, and `ReceiverBranch`, which is
+        // implemented on `&mut Branch`, only for receivers which aren't.
+        #[allow(unused_imports)]
+        use $crate::utils::{ReceiverBranch as _, StreamBranch as _};
+        $crate::utils::Branch::new(&mut $stream).next_item()
+    }};
+    (@expand [$(($slot:ident, $pat:pat, $make:expr, $body:block))*] $complete:block) => {{
+        let slots = async {
+            let mut enabled = false;
+            $(
+                // Disabled branches never create their future.
+                let $slot = $make;
+                enabled |= $slot.is_some();
+                let $slot = $slot.map($crate::MaybeDone::new);
+                // Pin the future on the stack, so that it stays in one place and
+                // is no longer accessible by the end user.
+                let mut $slot = $crate::utils::pin::pin!($slot);
+            )*
+            if !enabled {
+                return $crate::utils::option::Option::None;
+            }
+            $crate::utils::option::Option::Some($crate::utils::poll_fn(move |cx| {
+                use $crate::utils::future::Future;
+                use $crate::utils::task::Poll;
+
+                let mut ready = false;
+                $(
+                    if !ready {
+                        if let $crate::utils::option::Option::Some(fut) = $slot.as_mut().as_pin_mut() {
+                            ready = Future::poll(fut, cx).is_ready();
+                        }
+                    }
+                )*
+                if ready {
+                    // Only the completed future has an output to take.
+                    Poll::Ready(($(
+                        $slot.as_mut().as_pin_mut().and_then(|fut| fut.take()),
+                    )*))
+                } else {
+                    Poll::Pending
+                }
+            }).await)
+        }.await;
+
+        match slots {
+            $crate::utils::option::Option::Some(($($slot,)*)) => {
+                $(
+                    if let $crate::utils::option::Option::Some($pat) = $slot $body else
+                )* {}
+            }
+            $crate::utils::option::Option::None => $complete,
+        }
+    }};
+    ($($tokens:tt)*) => {
+        $crate::stream_select!(@collect [] [] $($tokens)*)
+    };
+}
+
+/// The source of an `in` branch of `stream_select!`.
+///
+/// The methods of [`StreamBranch`] and [`ReceiverBranch`] return a future
+/// resolving to the next item of the source, or `None` if its branch is
+/// disabled because the source has ended.
+#[doc(hidden)]
+#[derive(Debug)]
+pub struct Branch<'a, S: ?Sized>(Option<&'a mut S>);
+
+impl<'a, S: ?Sized> Branch<'a, S> {
+    pub fn new(source: &'a mut S) -> Self {
+        Self(Some(source))
+    }
+}
+
+#[doc(hidden)]
+pub trait StreamBranch<'a> {
+    type Next: Future;
+
+    fn next_item(self) -> Option<Self::Next>;
+}
+
+impl<'a, S> StreamBranch<'a> for Branch<'a, S>
+where
+    S: FusedStream + Unpin + ?Sized,
+{
+    type Next = NextItem<'a, S>;
+
+    fn next_item(self) -> Option<NextItem<'a, S>> {
+        let stream = self.0.unwrap();
+        if stream.is_terminated() {
+            return None;
+        }
+        Some(NextItem(stream))
+    }
+}
+
+/// Implemented for the channel receivers which aren't streams, on
+/// `&mut Branch` so that it is only picked if `StreamBranch` isn't.
+#[doc(hidden)]
+pub trait ReceiverBranch<'a> {
+    type Next: Future;
+
+    fn next_item(self) -> Option<Self::Next>;
+}
+
+#[cfg(feature = "futures-channel")]
+impl<'a, T> ReceiverBranch<'a> for &mut Branch<'a, futures_channel::oneshot::Receiver<T>> {
+    type Next = NextOutput<'a, futures_channel::oneshot::Receiver<T>>;
+
+    fn next_item(self) -> Option<Self::Next> {
+        use futures_core::future::FusedFuture;
+
+        let receiver = self.0.take().unwrap();
+        if receiver.is_terminated() {
+            return None;
+        }
+        Some(NextOutput(receiver))
+    }
+}
+
+/// Future resolving to the next item of a stream.
+#[doc(hidden)]
+#[derive(Debug)]
+pub struct NextItem<'a, S: ?Sized>(&'a mut S);
+
+impl<S: FusedStream + Unpin + ?Sized> Future for NextItem<'_, S> {
+    type Output = Option<S::Item>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut *self.0).poll_next(cx)
+    }
+}
+
+/// Future resolving to the output of a future, as the single item of its
+/// branch.
+#[cfg(feature = "futures-channel")]
+#[doc(hidden)]
+#[derive(Debug)]
+pub struct NextOutput<'a, F: ?Sized>(&'a mut F);
+
+#[cfg(feature = "futures-channel")]
+impl<F: Future + Unpin + ?Sized> Future for NextOutput<'_, F> {
+    type Output = Option<F::Output>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut *self.0).poll(cx).map(Some)
+    }
+}
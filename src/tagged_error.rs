@@ -0,0 +1,57 @@
+//! Definition of the `TaggedError` error
+
+use core::fmt;
+
+/// An error, with the future which returned it among the futures passed to a
+/// macro.
+///
+/// This is returned by `try_join!(tagged; ...)`, so that it can be told which
+/// of the joined futures failed, to report or retry just that one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TaggedError<E> {
+    index: usize,
+    name: &'static str,
+    error: E,
+}
+
+impl<E> TaggedError<E> {
+    /// Create a new `TaggedError` for the future at `index` among the futures
+    /// passed to a macro, named `name`, which returned `error`.
+    pub fn new(index: usize, name: &'static str, error: E) -> Self {
+        Self { index, name, error }
+    }
+
+    /// Returns the index of the future among the futures passed to the macro.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Returns the name of the future: the name of its variable, or the
+    /// expression it was passed to the macro as.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Returns a reference to the error the future returned.
+    pub fn error(&self) -> &E {
+        &self.error
+    }
+
+    /// Consumes the `TaggedError`, returning the error the future returned.
+    pub fn into_error(self) -> E {
+        self.error
+    }
+}
+
+impl<E> fmt::Display for TaggedError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "future `{}` at index {} failed", self.name, self.index)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: std::error::Error + 'static> std::error::Error for TaggedError<E> {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
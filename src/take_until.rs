@@ -0,0 +1,104 @@
+//! Definition of the `TakeUntil` stream adapter
+
+use core::fmt;
+use core::future::{Future, IntoFuture};
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+use futures_core::stream::{FusedStream, Stream};
+use pin_project_lite::pin_project;
+
+/// Forwards the items of a stream until a future completes, and then ends.
+///
+/// The future is polled before the stream, so that no more items are
+/// yielded once it completed, even if the stream has more of them ready. It
+/// is dropped as soon as it completes. This is how a stream is shut down
+/// gracefully once a shutdown signal comes in.
+///
+/// # Examples
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::{take_until, Notify};
+/// use futures::stream::{self, StreamExt};
+///
+/// let shutdown = Notify::new();
+/// let mut requests = take_until(stream::iter(1u8..), shutdown.notified());
+///
+/// assert_eq!(requests.next().await, Some(1));
+/// assert_eq!(requests.next().await, Some(2));
+/// shutdown.notify_one();
+/// assert_eq!(requests.next().await, None);
+/// # });
+/// ```
+pub fn take_until<S, F>(stream: S, future: F) -> TakeUntil<S, F::IntoFuture>
+where
+    S: Stream,
+    F: IntoFuture,
+{
+    TakeUntil {
+        stream,
+        future: Some(future.into_future()),
+        done: false,
+    }
+}
+
+pin_project! {
+    /// Stream for the [`take_until`] function.
+    #[must_use = "streams do nothing unless polled"]
+    pub struct TakeUntil<S, F> {
+        #[pin]
+        stream: S,
+        // The future, until it completes.
+        #[pin]
+        future: Option<F>,
+        done: bool,
+    }
+}
+
+impl<S, F> TakeUntil<S, F> {
+    /// Returns `true` if the future completed, ending the stream.
+    pub fn is_stopped(&self) -> bool {
+        self.future.is_none()
+    }
+}
+
+impl<S: Stream, F: Future> Stream for TakeUntil<S, F> {
+    type Item = S::Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<S::Item>> {
+        let this = self.project();
+        if *this.done {
+            return Poll::Ready(None);
+        }
+        let mut future = this.future;
+
+        if let Some(pending) = future.as_mut().as_pin_mut() {
+            if pending.poll(cx).is_ready() {
+                future.set(None);
+                *this.done = true;
+                return Poll::Ready(None);
+            }
+        }
+
+        let item = this.stream.poll_next(cx);
+        if let Poll::Ready(None) = item {
+            *this.done = true;
+        }
+        item
+    }
+}
+
+impl<S: Stream, F: Future> FusedStream for TakeUntil<S, F> {
+    fn is_terminated(&self) -> bool {
+        self.done
+    }
+}
+
+impl<S, F> fmt::Debug for TakeUntil<S, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TakeUntil")
+            .field("stopped", &self.is_stopped())
+            .finish()
+    }
+}
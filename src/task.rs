@@ -0,0 +1,124 @@
+//! Helpers for polling futures by hand, building blocks for waking tasks, and
+//! task-local storage.
+//!
+//! The polling helpers make it possible to test futures and streams without an
+//! executor or the `futures-test` crate.
+
+use core::task::{Context, Waker};
+#[cfg(feature = "std")]
+use std::sync::{Arc, Mutex};
+#[cfg(feature = "std")]
+use std::task::Wake;
+#[cfg(feature = "std")]
+use std::thread::{self, ThreadId};
+
+pub use crate::atomic_waker::AtomicWaker;
+#[cfg(feature = "budget")]
+pub use crate::budget::{poll_budget, remaining_budget, with_budget, WithBudget};
+#[cfg(any(feature = "std", feature = "critical-section"))]
+pub use crate::task_context::{provide, with_context, Provide};
+#[cfg(feature = "critical-section")]
+pub use crate::task_local::assume_single_context;
+#[cfg(any(feature = "std", feature = "critical-section"))]
+pub use crate::task_local::{
+    on_task_end, with_locals, AccessError, DefaultProvider, LocalKey, Scope, TaskLocalProvider,
+    TaskLocals, WithLocals,
+};
+#[cfg(feature = "alloc")]
+pub use crate::waker_set::WakerSet;
+
+/// Returns a waker which does nothing when woken.
+///
+/// # Examples
+///
+/// ```
+/// use async_macros::task::noop_waker;
+///
+/// let waker = noop_waker();
+/// waker.wake_by_ref();
+/// ```
+pub fn noop_waker() -> Waker {
+    Waker::noop().clone()
+}
+
+/// Returns a context holding a waker which does nothing when woken.
+///
+/// # Examples
+///
+/// ```
+/// use async_macros::task::noop_context;
+/// use async_macros::{assert_ready, future};
+///
+/// let mut cx = noop_context();
+/// assert_eq!(assert_ready!(future::ready(1u8), &mut cx), 1);
+/// ```
+pub fn noop_context() -> Context<'static> {
+    Context::from_waker(Waker::noop())
+}
+
+/// A waker which records its wakeups.
+///
+/// This can be used to check that a future wakes its task when it should,
+/// and doesn't wake it when it shouldn't, without running it on an executor.
+/// Every wakeup records the thread it came from.
+///
+/// # Examples
+///
+/// ```
+/// use async_macros::task::MockWaker;
+/// use std::task::Context;
+///
+/// let mock = MockWaker::new();
+/// let waker = mock.waker();
+/// let cx = Context::from_waker(&waker);
+/// assert_eq!(mock.wake_count(), 0);
+///
+/// cx.waker().wake_by_ref();
+/// waker.clone().wake();
+/// assert_eq!(mock.wake_count(), 2);
+/// assert_eq!(mock.wakes(), vec![std::thread::current().id(); 2]);
+/// ```
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Default)]
+pub struct MockWaker {
+    wakes: Arc<Mutex<Vec<ThreadId>>>,
+}
+
+#[cfg(feature = "std")]
+impl MockWaker {
+    /// Create a new `MockWaker` which hasn't been woken yet.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a waker which records its wakeups in this `MockWaker`.
+    pub fn waker(&self) -> Waker {
+        Waker::from(Arc::new(self.clone()))
+    }
+
+    /// Returns the number of times the waker has been woken.
+    pub fn wake_count(&self) -> usize {
+        self.wakes.lock().unwrap().len()
+    }
+
+    /// Returns the threads the waker has been woken from, in order.
+    pub fn wakes(&self) -> Vec<ThreadId> {
+        self.wakes.lock().unwrap().clone()
+    }
+
+    /// Forget about all previous wakeups.
+    pub fn clear(&self) {
+        self.wakes.lock().unwrap().clear();
+    }
+}
+
+#[cfg(feature = "std")]
+impl Wake for MockWaker {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.wakes.lock().unwrap().push(thread::current().id());
+    }
+}
@@ -0,0 +1,167 @@
+//! A typed context map attached to tasks.
+//!
+//! Every task created with [`with_locals`](crate::task::with_locals) carries
+//! a map holding at most one value per type, such as a deadline, a trace ID or
+//! authentication info. Values are provided to a future with [`provide`], and
+//! can be read from anywhere inside of it with [`with_context`], including
+//! from the futures it joins or selects over.
+
+use alloc::boxed::Box;
+use alloc::collections::BTreeMap;
+use core::any::{Any, TypeId};
+use core::cell::RefCell;
+use core::fmt;
+use core::future::Future;
+use core::marker::PhantomData;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+use pin_project_lite::pin_project;
+
+type ContextMap = BTreeMap<TypeId, Box<dyn Any + Send>>;
+
+crate::task_local! {
+    static CONTEXT: RefCell<ContextMap> = RefCell::new(BTreeMap::new());
+}
+
+/// Calls `f` with the value of type `T` in the context of the current task.
+///
+/// `f` is passed `None` if no such value was provided, or if this is called
+/// outside of a task.
+///
+/// # Examples
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::{join, task};
+///
+/// struct TraceId(u64);
+///
+/// async fn trace_id() -> Option<u64> {
+///     task::with_context(|id: Option<&TraceId>| id.map(|id| id.0))
+/// }
+///
+/// task::with_locals(task::provide(TraceId(7), async {
+///     let a = trace_id();
+///     let b = trace_id();
+///     assert_eq!(join!(a, b).await, (Some(7), Some(7)));
+/// }))
+/// .await;
+/// # });
+/// ```
+pub fn with_context<T, F, R>(f: F) -> R
+where
+    T: 'static,
+    F: FnOnce(Option<&T>) -> R,
+{
+    let mut f = Some(f);
+    let output = CONTEXT.try_with(|map| {
+        let map = map.borrow();
+        let value = map
+            .get(&TypeId::of::<T>())
+            .map(|value| value.downcast_ref::<T>().unwrap());
+        (f.take().unwrap())(value)
+    });
+    match output {
+        Ok(output) => output,
+        Err(_) => (f.take().unwrap())(None),
+    }
+}
+
+/// Runs a future with `value` in the context of its task.
+///
+/// The value is provided whenever the future is polled, and the previous
+/// value of the same type, if any, is restored after every poll. Values of
+/// other types are left untouched.
+///
+/// # Panics
+///
+/// Polling the returned future panics if it is polled outside of a task.
+///
+/// # Examples
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::task;
+///
+/// struct Deadline(u32);
+///
+/// fn deadline() -> Option<u32> {
+///     task::with_context(|deadline: Option<&Deadline>| deadline.map(|d| d.0))
+/// }
+///
+/// task::with_locals(task::provide(Deadline(10), async {
+///     task::provide(Deadline(5), async { assert_eq!(deadline(), Some(5)) }).await;
+///     assert_eq!(deadline(), Some(10));
+/// }))
+/// .await;
+/// # });
+/// ```
+pub fn provide<T, F>(value: T, future: F) -> Provide<T, F>
+where
+    T: Send + 'static,
+    F: Future,
+{
+    Provide {
+        value: Some(Box::new(value)),
+        future,
+        _marker: PhantomData,
+    }
+}
+
+pin_project! {
+    /// Future for the [`provide`] function.
+    #[must_use = "futures do nothing unless you `.await` or poll them"]
+    pub struct Provide<T, F> {
+        value: Option<Box<dyn Any + Send>>,
+        #[pin]
+        future: F,
+        _marker: PhantomData<fn() -> T>,
+    }
+}
+
+impl<T: Send + 'static, F: Future> Future for Provide<T, F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        let this = self.project();
+        swap::<T>(this.value);
+
+        /// Swaps the previous value back in on drop, even if polling panics.
+        struct Restore<'a, T: 'static> {
+            value: &'a mut Option<Box<dyn Any + Send>>,
+            _marker: PhantomData<fn() -> T>,
+        }
+
+        impl<T: 'static> Drop for Restore<'_, T> {
+            fn drop(&mut self) {
+                swap::<T>(self.value);
+            }
+        }
+
+        let _restore = Restore::<T> {
+            value: this.value,
+            _marker: PhantomData,
+        };
+        this.future.poll(cx)
+    }
+}
+
+impl<T, F> fmt::Debug for Provide<T, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Provide").finish()
+    }
+}
+
+/// Swaps the value of type `T` in the context of the current task with
+/// `value`.
+fn swap<T: 'static>(value: &mut Option<Box<dyn Any + Send>>) {
+    CONTEXT.with(|map| {
+        let mut map = map.borrow_mut();
+        let key = TypeId::of::<T>();
+        *value = match value.take() {
+            Some(value) => map.insert(key, value),
+            None => map.remove(&key),
+        };
+    })
+}
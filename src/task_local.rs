@@ -0,0 +1,938 @@
+//! Task-local storage.
+//!
+//! The values of task locals are stored in [`TaskLocals`], one per task. A
+//! [`TaskLocalProvider`] finds the locals of the task currently being polled,
+//! which lets runtimes store them alongside their own tasks. The default
+//! provider doesn't depend on any runtime: whichever future is wrapped in
+//! [`with_locals`] acts as the task.
+//!
+//! With `std`, the task being polled is tracked per thread. Without it, the
+//! `critical-section` feature tracks a single task being polled at a time,
+//! which suits single-core embedded executors. As the execution contexts
+//! can't be told apart, this has to be enabled with
+//! [`assume_single_context`].
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::any::Any;
+use core::cell::RefCell;
+use core::error::Error;
+use core::fmt;
+use core::future::Future;
+use core::marker::PhantomData;
+use core::mem;
+use core::pin::Pin;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use core::task::{Context, Poll};
+
+use pin_project_lite::pin_project;
+
+/// Declares task-local values.
+///
+/// Every declaration creates a static [`LocalKey`], which gives access to the
+/// value of the task currently being polled. The value is initialized the
+/// first time it is accessed from a task, and dropped along with the task.
+///
+/// By default tasks are futures wrapped in [`with_locals`]. A different
+/// [`TaskLocalProvider`] can be picked with a `#[provider(..)]` attribute,
+/// anywhere among the attributes and doc comments of the declaration.
+///
+/// # Examples
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::task;
+/// use async_macros::{join, task_local};
+/// use std::cell::Cell;
+///
+/// task_local! {
+///     static REQUESTS: Cell<u32> = Cell::new(0);
+/// }
+///
+/// async fn handle() {
+///     REQUESTS.with(|n| n.set(n.get() + 1));
+/// }
+///
+/// let count = task::with_locals(async {
+///     let a = handle();
+///     let b = handle();
+///     join!(a, b).await;
+///     REQUESTS.with(|n| n.get())
+/// })
+/// .await;
+/// assert_eq!(count, 2);
+/// # });
+/// ```
+#[macro_export]
+macro_rules! task_local {
+    () => ();
+
+    // The attributes are collected one by one, picking out the provider.
+    (@parse [$($attrs:tt)*] [] #[provider($provider:ty)] $($rest:tt)*) => (
+        $crate::task_local!(@parse [$($attrs)*] [$provider] $($rest)*);
+    );
+
+    (@parse $attrs:tt [$($provider:ty)?] #[provider($($new:tt)*)] $($rest:tt)*) => (
+        compile_error!("a task local can only have one `#[provider(..)]` attribute");
+    );
+
+    (@parse [$($attrs:tt)*] $provider:tt #[$attr:meta] $($rest:tt)*) => (
+        $crate::task_local!(@parse [$($attrs)* #[$attr]] $provider $($rest)*);
+    );
+
+    (
+        @parse [$($attrs:tt)*] $provider:tt
+        $vis:vis static $name:ident: $t:ty = $init:expr $(; $($rest:tt)*)?
+    ) => (
+        $crate::task_local!(@declare [$($attrs)*] $provider $vis static $name: $t = $init);
+        $crate::task_local!($($($rest)*)?);
+    );
+
+    (@declare $attrs:tt [] $vis:vis static $name:ident: $t:ty = $init:expr) => (
+        $crate::task_local!(
+            @declare $attrs [$crate::task::DefaultProvider] $vis static $name: $t = $init
+        );
+    );
+
+    (
+        @declare [$(#[$attr:meta])*] [$provider:ty]
+        $vis:vis static $name:ident: $t:ty = $init:expr
+    ) => (
+        $(#[$attr])*
+        $vis static $name: $crate::task::LocalKey<$t, $provider> = {
+            #[inline]
+            fn __init() -> $t {
+                $init
+            }
+
+            $crate::task::LocalKey::__new(__init)
+        };
+    );
+
+    (@parse $($tokens:tt)*) => (
+        compile_error!("expected `static NAME: TYPE = INIT;` after the attributes of a task local");
+    );
+
+    ($($tokens:tt)+) => (
+        $crate::task_local!(@parse [] [] $($tokens)+);
+    );
+}
+
+/// Runs a future as a new task which inherits task locals from the current
+/// task.
+///
+/// The values of the listed keys are cloned from the current task when the
+/// macro is invoked, and set in the new task before the future is first
+/// polled. Keys which can't be accessed, because this is called outside of a
+/// task, are left at their initial value. The result is a [`WithLocals`]
+/// future, which can be handed to any executor to spawn.
+///
+/// The keys must use the [`DefaultProvider`], and their values must be
+/// `Clone`.
+///
+/// # Examples
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::{inherit_locals, task, task_local};
+///
+/// task_local! {
+///     static REQUEST_ID: u32 = 0;
+/// }
+///
+/// let child = task::with_locals(async {
+///     REQUEST_ID.set(7);
+///     inherit_locals!([REQUEST_ID], async { REQUEST_ID.with(|id| *id) })
+/// })
+/// .await;
+///
+/// let id = std::thread::spawn(move || futures::executor::block_on(child));
+/// assert_eq!(id.join().unwrap(), 7);
+/// # });
+/// ```
+#[macro_export]
+macro_rules! inherit_locals {
+    ([$($key:path),* $(,)?], $fut:expr $(,)?) => {{
+        let values = $crate::inherit_locals!(@capture [$($key),*]);
+        let future = $fut;
+        $crate::task::with_locals(async move {
+            $crate::inherit_locals!(@restore values [$($key),*]);
+            future.await
+        })
+    }};
+
+    (@capture []) => (());
+
+    (@capture [$key:path $(, $rest:path)*]) => ((
+        $key.try_with($crate::utils::clone::Clone::clone).ok(),
+        $crate::inherit_locals!(@capture [$($rest),*]),
+    ));
+
+    (@restore $values:ident []) => (let () = $values;);
+
+    (@restore $values:ident [$key:path $(, $rest:path)*]) => (
+        let (value, rest) = $values;
+        if let $crate::utils::option::Option::Some(value) = value {
+            $key.set(value);
+        }
+        $crate::inherit_locals!(@restore rest [$($rest),*]);
+    );
+}
+
+/// Finds the task-local values of the task currently being polled.
+///
+/// Implementing this lets a runtime keep the [`TaskLocals`] of a task
+/// alongside the task itself, and make them available to [`task_local!`]
+/// declarations using it as their provider.
+///
+/// # Examples
+///
+/// A provider treating every thread as a single task:
+///
+/// ```
+/// use async_macros::task::{TaskLocalProvider, TaskLocals};
+/// use async_macros::task_local;
+/// use std::cell::Cell;
+///
+/// thread_local! {
+///     static LOCALS: TaskLocals = TaskLocals::new();
+/// }
+///
+/// struct PerThread;
+///
+/// impl TaskLocalProvider for PerThread {
+///     fn with_current<F, R>(f: F) -> R
+///     where
+///         F: FnOnce(Option<&TaskLocals>) -> R,
+///     {
+///         LOCALS.with(|locals| f(Some(locals)))
+///     }
+/// }
+///
+/// task_local! {
+///     /// The ID of the current thread.
+///     #[provider(PerThread)]
+///     static ID: Cell<u32> = Cell::new(0);
+/// }
+///
+/// ID.with(|id| id.set(1));
+/// assert_eq!(ID.with(|id| id.get()), 1);
+/// ```
+pub trait TaskLocalProvider: 'static {
+    /// Calls `f` with the locals of the current task, or with `None` if no
+    /// task is being polled.
+    fn with_current<F, R>(f: F) -> R
+    where
+        F: FnOnce(Option<&TaskLocals>) -> R;
+}
+
+/// The provider for tasks created with [`with_locals`].
+///
+/// This is the provider used by [`task_local!`] unless another one is given.
+#[derive(Debug)]
+pub struct DefaultProvider(());
+
+/// Tracks the locals of the `WithLocals` currently being polled.
+#[cfg(feature = "std")]
+mod current {
+    use super::TaskLocals;
+    use std::cell::Cell;
+    use std::ptr;
+
+    thread_local! {
+        static CURRENT: Cell<*const TaskLocals> = const { Cell::new(ptr::null()) };
+    }
+
+    pub(super) fn get() -> *const TaskLocals {
+        CURRENT.with(Cell::get)
+    }
+
+    pub(super) fn replace(locals: *const TaskLocals) -> *const TaskLocals {
+        CURRENT.with(|current| current.replace(locals))
+    }
+}
+
+/// Tracks the locals of the `WithLocals` currently being polled, once
+/// [`assume_single_context`] was called.
+///
+/// Raw pointers can't be stored in a `static`, so the address is stored
+/// instead.
+#[cfg(not(feature = "std"))]
+mod current {
+    use super::TaskLocals;
+    use core::cell::Cell;
+    use core::ptr;
+    use core::sync::atomic::{AtomicBool, Ordering};
+    use critical_section::Mutex;
+
+    static ENABLED: AtomicBool = AtomicBool::new(false);
+    static CURRENT: Mutex<Cell<usize>> = Mutex::new(Cell::new(0));
+
+    pub(super) fn enable() {
+        ENABLED.store(true, Ordering::Release);
+    }
+
+    pub(super) fn get() -> *const TaskLocals {
+        if !ENABLED.load(Ordering::Acquire) {
+            return ptr::null();
+        }
+        critical_section::with(|cs| CURRENT.borrow(cs).get() as *const TaskLocals)
+    }
+
+    pub(super) fn replace(locals: *const TaskLocals) -> *const TaskLocals {
+        if !ENABLED.load(Ordering::Acquire) {
+            return ptr::null();
+        }
+        critical_section::with(|cs| {
+            CURRENT.borrow(cs).replace(locals as usize) as *const TaskLocals
+        })
+    }
+}
+
+/// Enables task locals using the [`DefaultProvider`] without `std`.
+///
+/// Without `std` there is no way to tell execution contexts, such as cores and
+/// interrupt handlers, apart. The `critical-section` feature tracks the task
+/// being polled in a single global instead, which is only sound if there is a
+/// single execution context. Until this is called, task locals can't be
+/// accessed at all without `std`, as if every access happened outside of a
+/// task.
+///
+/// With `std` the task being polled is tracked per thread, and this does
+/// nothing.
+///
+/// # Safety
+///
+/// Tasks created with [`with_locals`] must only be polled from a single
+/// execution context, and task locals using the [`DefaultProvider`] must only
+/// be accessed from it: from one core, and not from interrupt handlers.
+/// Anything else would see the locals of the task being polled, and could
+/// access them at the same time as the task.
+///
+/// # Examples
+///
+/// ```
+/// use async_macros::task;
+///
+/// // SAFETY: this program polls all of its tasks from the main thread.
+/// unsafe { task::assume_single_context() };
+/// ```
+#[cfg(feature = "critical-section")]
+pub unsafe fn assume_single_context() {
+    #[cfg(not(feature = "std"))]
+    current::enable();
+}
+
+impl TaskLocalProvider for DefaultProvider {
+    fn with_current<F, R>(f: F) -> R
+    where
+        F: FnOnce(Option<&TaskLocals>) -> R,
+    {
+        let current = current::get();
+        // SAFETY: the current locals are only set while the `WithLocals` they
+        // point into is being polled, which is the case for as long as `f`
+        // runs.
+        f(unsafe { current.as_ref() })
+    }
+}
+
+/// The key for accessing a task-local value.
+///
+/// Keys are created with the [`task_local!`] macro.
+pub struct LocalKey<T: Send + 'static, P: TaskLocalProvider = DefaultProvider> {
+    init: fn() -> T,
+    // The index of the value in the locals of a task, plus one. Assigned on
+    // first use, so that keys can be created in a `static`.
+    key: AtomicUsize,
+    _provider: PhantomData<fn() -> P>,
+}
+
+impl<T: Send + 'static, P: TaskLocalProvider> LocalKey<T, P> {
+    #[doc(hidden)]
+    pub const fn __new(init: fn() -> T) -> Self {
+        Self {
+            init,
+            key: AtomicUsize::new(0),
+            _provider: PhantomData,
+        }
+    }
+
+    /// Gets a reference to the value of this task local in the current task.
+    ///
+    /// The value is initialized first if this is the first time it is accessed
+    /// from the current task.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if it is called outside of a task, or from within
+    /// [`set`](Self::set), [`replace`](Self::replace) or [`take`](Self::take)
+    /// on the same key.
+    pub fn with<F, R>(&'static self, f: F) -> R
+    where
+        F: FnOnce(&T) -> R,
+    {
+        self.try_with(f)
+            .expect("`LocalKey::with` called outside the context of a task")
+    }
+
+    /// Attempts to get a reference to the value of this task local in the
+    /// current task.
+    ///
+    /// This is like [`with`](Self::with), but returns an error instead of
+    /// panicking if it is called outside of a task.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use async_macros::task_local;
+    ///
+    /// task_local! {
+    ///     static ID: u32 = 0;
+    /// }
+    ///
+    /// assert!(ID.try_with(|id| *id).is_err());
+    /// ```
+    pub fn try_with<F, R>(&'static self, f: F) -> Result<R, AccessError>
+    where
+        F: FnOnce(&T) -> R,
+    {
+        self.try_locals(|locals, key| {
+            let value = locals.get_or_init(key, self.init);
+            let value = value.borrow();
+            f(&value)
+        })
+    }
+
+    /// Sets the value of this task local in the current task.
+    ///
+    /// Unlike [`replace`](Self::replace), this doesn't initialize the value
+    /// first if it wasn't set yet.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if it is called outside of a task, or from within
+    /// [`with`](Self::with) on the same key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # futures::executor::block_on(async {
+    /// use async_macros::{task, task_local};
+    ///
+    /// task_local! {
+    ///     static NAME: String = String::from("anonymous");
+    /// }
+    ///
+    /// task::with_locals(async {
+    ///     NAME.set(String::from("worker"));
+    ///     assert_eq!(NAME.with(|name| name.clone()), "worker");
+    /// })
+    /// .await;
+    /// # });
+    /// ```
+    pub fn set(&'static self, value: T) {
+        let mut value = Some(Box::new(RefCell::new(value)));
+        self.try_locals(|locals, key| locals.swap(key, &mut value))
+            .expect("`LocalKey::set` called outside the context of a task");
+    }
+
+    /// Replaces the value of this task local in the current task, returning
+    /// the previous value.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if it is called outside of a task, or from within
+    /// [`with`](Self::with) on the same key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # futures::executor::block_on(async {
+    /// use async_macros::{task, task_local};
+    ///
+    /// task_local! {
+    ///     static ATTEMPT: u32 = 1;
+    /// }
+    ///
+    /// task::with_locals(async {
+    ///     assert_eq!(ATTEMPT.replace(2), 1);
+    ///     assert_eq!(ATTEMPT.replace(3), 2);
+    /// })
+    /// .await;
+    /// # });
+    /// ```
+    pub fn replace(&'static self, value: T) -> T {
+        self.try_locals(|locals, key| {
+            let mut current = locals
+                .get_or_init(key, self.init)
+                .try_borrow_mut()
+                .expect("task local replaced while it is borrowed");
+            mem::replace(&mut *current, value)
+        })
+        .expect("`LocalKey::replace` called outside the context of a task")
+    }
+
+    /// Takes the value of this task local in the current task, leaving
+    /// `Default::default()` in its place.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if it is called outside of a task, or from within
+    /// [`with`](Self::with) on the same key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # futures::executor::block_on(async {
+    /// use async_macros::{task, task_local};
+    ///
+    /// task_local! {
+    ///     static EVENTS: Vec<&'static str> = Vec::new();
+    /// }
+    ///
+    /// task::with_locals(async {
+    ///     EVENTS.set(vec!["started"]);
+    ///     assert_eq!(EVENTS.take(), vec!["started"]);
+    ///     assert!(EVENTS.with(|events| events.is_empty()));
+    /// })
+    /// .await;
+    /// # });
+    /// ```
+    pub fn take(&'static self) -> T
+    where
+        T: Default,
+    {
+        self.replace(T::default())
+    }
+
+    /// Runs a future with this task local set to `value`.
+    ///
+    /// The value is set whenever the future is polled, and the previous value
+    /// of the task is restored after every poll. This makes it possible to
+    /// scope values to a part of a task, such as a single request.
+    ///
+    /// # Panics
+    ///
+    /// Polling the returned future panics if it is polled outside of a task,
+    /// or from within [`with`](Self::with) on the same key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # futures::executor::block_on(async {
+    /// use async_macros::{task, task_local};
+    ///
+    /// task_local! {
+    ///     static REQUEST_ID: u32 = 0;
+    /// }
+    ///
+    /// task::with_locals(async {
+    ///     let id = REQUEST_ID.scope(7, async { REQUEST_ID.with(|id| *id) }).await;
+    ///     assert_eq!(id, 7);
+    ///     assert_eq!(REQUEST_ID.with(|id| *id), 0);
+    /// })
+    /// .await;
+    /// # });
+    /// ```
+    pub fn scope<F: Future>(&'static self, value: T, future: F) -> Scope<T, P, F> {
+        Scope {
+            key: self,
+            value: Some(Box::new(RefCell::new(value))),
+            future,
+        }
+    }
+
+    /// Swaps the value of the current task with `value`.
+    fn swap(&'static self, value: &mut Option<Box<RefCell<T>>>) {
+        self.try_locals(|locals, key| locals.swap(key, value))
+            .expect("`LocalKey::scope` polled outside the context of a task")
+    }
+
+    fn try_locals<F, R>(&'static self, f: F) -> Result<R, AccessError>
+    where
+        F: FnOnce(&TaskLocals, usize) -> R,
+    {
+        let key = self.key();
+        P::with_current(|locals| Ok(f(locals.ok_or(AccessError(()))?, key)))
+    }
+
+    fn key(&self) -> usize {
+        static COUNTER: AtomicUsize = AtomicUsize::new(1);
+
+        let key = self.key.load(Ordering::Acquire);
+        if key != 0 {
+            return key - 1;
+        }
+
+        let new = COUNTER.fetch_add(1, Ordering::Relaxed);
+        match self
+            .key
+            .compare_exchange(0, new, Ordering::AcqRel, Ordering::Acquire)
+        {
+            Ok(_) => new - 1,
+            Err(key) => key - 1,
+        }
+    }
+}
+
+impl<T: Send + 'static, P: TaskLocalProvider> fmt::Debug for LocalKey<T, P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad("LocalKey { .. }")
+    }
+}
+
+/// An error returned by [`LocalKey::try_with`] when called outside of a task.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct AccessError(());
+
+impl fmt::Display for AccessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        "task local accessed outside the context of a task".fmt(f)
+    }
+}
+
+impl Error for AccessError {}
+
+/// Registers a callback to run when the current task ends.
+///
+/// This is for tasks created with [`with_locals`]. The callback runs when the
+/// task completes or is dropped, while its task locals can still be accessed.
+///
+/// # Panics
+///
+/// This function panics if it is called outside of a task.
+///
+/// # Examples
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::task;
+/// use std::sync::atomic::{AtomicBool, Ordering};
+/// use std::sync::Arc;
+///
+/// let flushed = Arc::new(AtomicBool::new(false));
+/// let flag = flushed.clone();
+/// task::with_locals(async move {
+///     task::on_task_end(move || flag.store(true, Ordering::SeqCst));
+/// })
+/// .await;
+/// assert!(flushed.load(Ordering::SeqCst));
+/// # });
+/// ```
+pub fn on_task_end<F>(f: F)
+where
+    F: FnOnce() + Send + 'static,
+{
+    DefaultProvider::with_current(|locals| {
+        locals
+            .expect("`on_task_end` called outside the context of a task")
+            .on_end(f)
+    })
+}
+
+/// The task-local values of a single task.
+///
+/// Tasks created with [`with_locals`] manage their own locals. This only needs
+/// to be used directly when implementing a [`TaskLocalProvider`].
+#[derive(Default)]
+pub struct TaskLocals {
+    values: RefCell<Vec<Option<Box<dyn Value>>>>,
+    hooks: RefCell<Vec<Box<dyn FnOnce() + Send>>>,
+}
+
+/// A type-erased `RefCell<T>`.
+trait Value: Send {
+    fn as_any(&self) -> &dyn Any;
+    fn into_any(self: Box<Self>) -> Box<dyn Any>;
+    fn is_borrowed(&self) -> bool;
+}
+
+impl<T: Send + 'static> Value for RefCell<T> {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        self
+    }
+
+    fn is_borrowed(&self) -> bool {
+        self.try_borrow_mut().is_err()
+    }
+}
+
+impl TaskLocals {
+    /// Create a new set of locals, without any values.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a callback to run when [`finish`](Self::finish) is called.
+    pub fn on_end<F>(&self, f: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        self.hooks.borrow_mut().push(Box::new(f));
+    }
+
+    /// Ends the task these locals belong to.
+    ///
+    /// This runs the callbacks registered with [`on_end`](Self::on_end) in
+    /// reverse order, and then drops all values. Providers should call it when
+    /// their task completes, while the locals are still current, so that the
+    /// callbacks and destructors can access other task locals.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if a value is still borrowed.
+    pub fn finish(&self) {
+        loop {
+            let hook = self.hooks.borrow_mut().pop();
+            match hook {
+                Some(hook) => hook(),
+                None => break,
+            }
+        }
+
+        loop {
+            let value = self.values.borrow_mut().pop();
+            match value {
+                Some(Some(value)) => {
+                    assert!(
+                        !value.is_borrowed(),
+                        "task local dropped while it is borrowed"
+                    );
+                    drop(value);
+                }
+                Some(None) => {}
+                None => break,
+            }
+        }
+    }
+
+    // Values are stored as `RefCell<T>`. They are boxed so that they don't
+    // move while borrowed, and only removed or replaced while their `RefCell`
+    // isn't borrowed.
+
+    fn get_or_init<T: Send + 'static>(&self, key: usize, init: fn() -> T) -> &RefCell<T> {
+        let value = self.get::<T>(key).unwrap_or_else(|| {
+            // Initialize without holding the borrow, so that the initializer
+            // can access other task locals.
+            let value = init();
+            let mut values = self.values.borrow_mut();
+            if values.len() <= key {
+                values.resize_with(key + 1, || None);
+            }
+            // The initializer may have already accessed the same key.
+            let value = values[key].get_or_insert_with(|| Box::new(RefCell::new(value)));
+            value.as_any().downcast_ref::<RefCell<T>>().unwrap() as *const _
+        });
+
+        // SAFETY: the value is boxed, and callers borrow it before running any
+        // code which could remove it.
+        unsafe { &*value }
+    }
+
+    fn get<T: 'static>(&self, key: usize) -> Option<*const RefCell<T>> {
+        let values = self.values.borrow();
+        let value = values.get(key)?.as_ref()?;
+        Some(value.as_any().downcast_ref::<RefCell<T>>().unwrap() as *const _)
+    }
+
+    fn swap<T: Send + 'static>(&self, key: usize, value: &mut Option<Box<RefCell<T>>>) {
+        let mut values = self.values.borrow_mut();
+        if values.len() <= key {
+            values.resize_with(key + 1, || None);
+        }
+        if let Some(current) = &values[key] {
+            assert!(
+                !current.is_borrowed(),
+                "task local replaced while it is borrowed"
+            );
+        }
+        let current = values[key].take();
+        values[key] = value.take().map(|value| value as Box<dyn Value>);
+        *value = current.map(|current| current.into_any().downcast().unwrap());
+    }
+}
+
+impl fmt::Debug for TaskLocals {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad("TaskLocals { .. }")
+    }
+}
+
+pin_project! {
+    /// Future for the [`LocalKey::scope`] method.
+    #[must_use = "futures do nothing unless you `.await` or poll them"]
+    pub struct Scope<T, P: TaskLocalProvider, F>
+    where
+        T: Send,
+        T: 'static,
+    {
+        key: &'static LocalKey<T, P>,
+        value: Option<Box<RefCell<T>>>,
+        #[pin]
+        future: F,
+    }
+}
+
+impl<T: Send + 'static, P: TaskLocalProvider, F: Future> Future for Scope<T, P, F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        let this = self.project();
+        let key = *this.key;
+        key.swap(this.value);
+
+        /// Swaps the previous value back in on drop, even if polling panics.
+        struct Restore<'a, T: Send + 'static, P: TaskLocalProvider> {
+            key: &'static LocalKey<T, P>,
+            value: &'a mut Option<Box<RefCell<T>>>,
+        }
+
+        impl<T: Send + 'static, P: TaskLocalProvider> Drop for Restore<'_, T, P> {
+            fn drop(&mut self) {
+                self.key.swap(self.value);
+            }
+        }
+
+        let _restore = Restore {
+            key,
+            value: this.value,
+        };
+        this.future.poll(cx)
+    }
+}
+
+impl<T: Send + 'static, P: TaskLocalProvider, F> fmt::Debug for Scope<T, P, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Scope").finish()
+    }
+}
+
+pin_project! {
+    /// Future for the [`with_locals`] function.
+    #[must_use = "futures do nothing unless you `.await` or poll them"]
+    pub struct WithLocals<F> {
+        // Dropped in place once it completes, before the locals are finished.
+        #[pin]
+        future: Option<F>,
+        task: Task,
+    }
+
+    impl<F> PinnedDrop for WithLocals<F> {
+        fn drop(this: Pin<&mut Self>) {
+            let mut this = this.project();
+            if this.future.is_some() {
+                let _enter = Enter::new(&this.task.locals);
+                #[cfg(feature = "tracing")]
+                let _span = this.task.span.enter();
+                this.future.set(None);
+                this.task.locals.finish();
+            }
+        }
+    }
+}
+
+/// The parts of a [`WithLocals`] which belong to the task rather than to its
+/// future.
+struct Task {
+    locals: TaskLocals,
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+}
+
+/// Runs a future as a task with its own task-local values.
+///
+/// All task locals using the [`DefaultProvider`] which are accessed while
+/// polling the future belong to it, including from the futures it joins or
+/// selects over. Nested tasks get their own values.
+///
+/// When the future completes, or is dropped before completing, the callbacks
+/// registered with [`on_task_end`] are run and the values are dropped.
+///
+/// With the `tracing` feature the task also keeps the `tracing` span which was
+/// current when it was created, and enters it every time it is polled. This
+/// way spans follow the task, including the spans of the futures it joins or
+/// selects over, rather than whichever thread polls it.
+///
+/// # Examples
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::{task, task_local};
+/// use std::cell::Cell;
+///
+/// task_local! {
+///     static ID: Cell<u32> = Cell::new(0);
+/// }
+///
+/// task::with_locals(async {
+///     ID.with(|id| id.set(1));
+///     task::with_locals(async { ID.with(|id| assert_eq!(id.get(), 0)) }).await;
+///     ID.with(|id| assert_eq!(id.get(), 1));
+/// })
+/// .await;
+/// # });
+/// ```
+pub fn with_locals<F: Future>(future: F) -> WithLocals<F> {
+    WithLocals {
+        future: Some(future),
+        task: Task {
+            locals: TaskLocals::new(),
+            #[cfg(feature = "tracing")]
+            span: tracing::Span::current(),
+        },
+    }
+}
+
+impl<F: Future> Future for WithLocals<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        let mut this = self.project();
+        let future = this
+            .future
+            .as_mut()
+            .as_pin_mut()
+            .expect("WithLocals polled after completion");
+        let _enter = Enter::new(&this.task.locals);
+        #[cfg(feature = "tracing")]
+        let _span = this.task.span.enter();
+        let output = match future.poll(cx) {
+            Poll::Ready(output) => output,
+            Poll::Pending => return Poll::Pending,
+        };
+        this.future.set(None);
+        this.task.locals.finish();
+        Poll::Ready(output)
+    }
+}
+
+impl<F> fmt::Debug for WithLocals<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WithLocals").finish()
+    }
+}
+
+/// Makes the locals of a task current while it is being polled.
+///
+/// The locals of the enclosing task, if any, are restored on drop, even if
+/// polling panics.
+struct Enter {
+    previous: *const TaskLocals,
+}
+
+impl Enter {
+    fn new(locals: &TaskLocals) -> Self {
+        let previous = current::replace(locals);
+        Self { previous }
+    }
+}
+
+impl Drop for Enter {
+    fn drop(&mut self) {
+        current::replace(self.previous);
+    }
+}
@@ -0,0 +1,163 @@
+//! Definition of the `Throttle` stream adapter
+
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use core::time::Duration;
+
+use futures_core::stream::{FusedStream, Stream};
+use pin_project_lite::pin_project;
+
+use crate::time::Timer;
+
+/// How many items a [`Throttle`] drops per poll, so that a stream which is
+/// always ready can't starve the executor.
+const DROPS_PER_WAKEUP: usize = 32;
+
+/// What a [`Throttle`] does with the items which come in too early.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ThrottlePolicy {
+    /// Wait until the interval has passed before pulling the next item from
+    /// the stream, so that no item is lost.
+    #[default]
+    Delay,
+    /// Keep pulling items from the stream, and drop those which come in
+    /// before the interval has passed.
+    Drop,
+}
+
+/// Limits the rate of a stream, so that at least `interval` passes between
+/// the items it yields.
+///
+/// The interval starts whenever an item is yielded, and is measured with
+/// `timer`. By default the next item is only pulled from the stream once the
+/// interval has passed; [`Throttle::with_policy`] can make the stream drop
+/// the items coming in too early instead, such as UI updates which are stale
+/// by the time they could be shown.
+///
+/// # Examples
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::time::{self, ThreadTimer};
+/// use futures::stream::{self, StreamExt};
+/// use std::time::{Duration, Instant};
+///
+/// let interval = Duration::from_millis(10);
+/// let requests = stream::iter(vec![1u8, 2, 3]);
+///
+/// let start = Instant::now();
+/// let sent = time::throttle(ThreadTimer, requests, interval).collect::<Vec<_>>().await;
+/// assert_eq!(sent, vec![1, 2, 3]);
+/// assert!(start.elapsed() >= 2 * interval);
+/// # });
+/// ```
+pub fn throttle<T, S>(timer: T, stream: S, interval: Duration) -> Throttle<T, S>
+where
+    T: Timer,
+    S: Stream,
+{
+    Throttle {
+        stream,
+        timer,
+        interval,
+        policy: ThrottlePolicy::Delay,
+        sleep: None,
+    }
+}
+
+pin_project! {
+    /// Stream for the [`throttle`] function.
+    #[must_use = "streams do nothing unless polled"]
+    pub struct Throttle<T: Timer, S> {
+        #[pin]
+        stream: S,
+        timer: T,
+        interval: Duration,
+        policy: ThrottlePolicy,
+        // The interval since the last item, if it hasn't passed yet.
+        #[pin]
+        sleep: Option<T::Sleep>,
+    }
+}
+
+impl<T: Timer, S> Throttle<T, S> {
+    /// Sets what is done with the items which come in before the interval
+    /// has passed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # futures::executor::block_on(async {
+    /// use async_macros::time::{self, ThreadTimer, ThrottlePolicy};
+    /// use futures::stream::{self, StreamExt};
+    /// use std::time::Duration;
+    ///
+    /// let updates = stream::iter(vec![1u8, 2, 3]);
+    /// let shown = time::throttle(ThreadTimer, updates, Duration::from_secs(60))
+    ///     .with_policy(ThrottlePolicy::Drop)
+    ///     .collect::<Vec<_>>()
+    ///     .await;
+    /// assert_eq!(shown, vec![1]);
+    /// # });
+    /// ```
+    pub fn with_policy(mut self, policy: ThrottlePolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+}
+
+impl<T: Timer, S: Stream> Stream for Throttle<T, S> {
+    type Item = S::Item;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<S::Item>> {
+        let this = self.project();
+        let mut stream = this.stream;
+        let mut sleep = this.sleep;
+
+        if *this.policy == ThrottlePolicy::Delay {
+            if let Some(pending) = sleep.as_mut().as_pin_mut() {
+                if pending.poll(cx).is_pending() {
+                    return Poll::Pending;
+                }
+                sleep.set(None);
+            }
+        }
+
+        for _ in 0..DROPS_PER_WAKEUP {
+            let item = match stream.as_mut().poll_next(cx) {
+                Poll::Ready(Some(item)) => item,
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
+            };
+            if let Some(pending) = sleep.as_mut().as_pin_mut() {
+                if pending.poll(cx).is_pending() {
+                    // Only the `Drop` policy gets here with an interval which
+                    // hasn't passed.
+                    continue;
+                }
+            }
+            sleep.set(Some(this.timer.sleep(*this.interval)));
+            return Poll::Ready(Some(item));
+        }
+
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+impl<T: Timer, S: FusedStream> FusedStream for Throttle<T, S> {
+    fn is_terminated(&self) -> bool {
+        self.stream.is_terminated()
+    }
+}
+
+impl<T: Timer, S> fmt::Debug for Throttle<T, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Throttle")
+            .field("interval", &self.interval)
+            .field("policy", &self.policy)
+            .finish()
+    }
+}
@@ -0,0 +1,350 @@
+//! Timers for the macros which wait for some time.
+//!
+//! The crate doesn't depend on a runtime, so macros such as [`hedge!`] are
+//! given a [`Timer`] to sleep with. Runtimes usually have a `sleep` function
+//! which a timer can forward to, and the `std` feature provides a minimal
+//! [`ThreadTimer`]. On `wasm32` targets, where threads usually aren't
+//! available, the `wasm-timer` feature provides a `WasmTimer` built on the
+//! `setTimeout` function of JavaScript.
+//!
+//! Any future can be given a deadline with [`timeout`], or with
+//! [`with_timeout`] for deadlines which are futures of their own. Streams can
+//! be rate limited with [`throttle`], and bursts of items can be reduced to
+//! their last item with [`debounce`]. Stalled streams can be detected with
+//! [`idle_timeout`].
+
+use core::future::Future;
+use core::time::Duration;
+
+pub use crate::debounce::{debounce, Debounce};
+pub use crate::idle_timeout::{idle_timeout, IdleTimeout};
+pub use crate::throttle::{throttle, Throttle, ThrottlePolicy};
+pub use crate::timeout::{timeout, with_timeout, Elapsed, Timeout};
+#[cfg(feature = "std")]
+pub use thread_timer::{Sleep, ThreadTimer};
+#[cfg(all(feature = "wasm-timer", target_arch = "wasm32"))]
+pub use wasm_timer::{WasmSleep, WasmTimer};
+
+/// A source of futures which complete after some time.
+///
+/// # Examples
+///
+/// Implementing a timer on top of the `sleep` function of a runtime:
+///
+/// ```
+/// use async_macros::time::Timer;
+/// use std::future::{self, Ready};
+/// use std::time::Duration;
+///
+/// /// A timer whose sleeps complete right away.
+/// struct Instant;
+///
+/// impl Timer for Instant {
+///     type Sleep = Ready<()>;
+///
+///     fn sleep(&self, _duration: Duration) -> Ready<()> {
+///         future::ready(())
+///     }
+/// }
+/// ```
+pub trait Timer {
+    /// The future returned by [`sleep`](Timer::sleep).
+    type Sleep: Future<Output = ()>;
+
+    /// Returns a future which completes once `duration` has passed.
+    fn sleep(&self, duration: Duration) -> Self::Sleep;
+}
+
+impl<T: Timer + ?Sized> Timer for &T {
+    type Sleep = T::Sleep;
+
+    fn sleep(&self, duration: Duration) -> T::Sleep {
+        (**self).sleep(duration)
+    }
+}
+
+#[cfg(feature = "std")]
+mod thread_timer {
+    use core::fmt;
+    use core::future::Future;
+    use core::pin::Pin;
+    use core::task::{Context, Poll, Waker};
+    use core::time::Duration;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::Instant;
+
+    use super::Timer;
+
+    /// A timer which sleeps on threads of its own.
+    ///
+    /// Every sleep spawns a thread the first time it is polled, which wakes
+    /// the task once the duration has passed. This is meant for examples,
+    /// tests and small tools: code running on a runtime should use the timer
+    /// of the runtime instead. Sleeps whose deadline is too far out for an
+    /// `Instant` to represent never complete, and don't spawn a thread.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use async_macros::time::{ThreadTimer, Timer};
+    /// use futures::executor::block_on;
+    /// use std::time::{Duration, Instant};
+    ///
+    /// let start = Instant::now();
+    /// block_on(ThreadTimer.sleep(Duration::from_millis(10)));
+    /// assert!(start.elapsed() >= Duration::from_millis(10));
+    /// ```
+    #[derive(Debug, Clone, Copy, Default)]
+    pub struct ThreadTimer;
+
+    impl Timer for ThreadTimer {
+        type Sleep = Sleep;
+
+        fn sleep(&self, duration: Duration) -> Sleep {
+            Sleep {
+                // A deadline too far out to be represented never passes.
+                deadline: Instant::now().checked_add(duration),
+                state: None,
+            }
+        }
+    }
+
+    /// Future for the [`ThreadTimer::sleep`] method.
+    #[must_use = "futures do nothing unless you `.await` or poll them"]
+    pub struct Sleep {
+        deadline: Option<Instant>,
+        state: Option<Arc<Mutex<State>>>,
+    }
+
+    /// The state shared with the thread of a sleep.
+    struct State {
+        fired: bool,
+        waker: Option<Waker>,
+    }
+
+    impl Future for Sleep {
+        type Output = ();
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+            let deadline = match self.deadline {
+                Some(deadline) => deadline,
+                None => return Poll::Pending,
+            };
+            if Instant::now() >= deadline {
+                return Poll::Ready(());
+            }
+            match &self.state {
+                Some(state) => {
+                    let mut state = state.lock().unwrap();
+                    if state.fired {
+                        return Poll::Ready(());
+                    }
+                    match &state.waker {
+                        Some(waker) if waker.will_wake(cx.waker()) => {}
+                        _ => state.waker = Some(cx.waker().clone()),
+                    }
+                }
+                None => {
+                    let state = Arc::new(Mutex::new(State {
+                        fired: false,
+                        waker: Some(cx.waker().clone()),
+                    }));
+                    let thread_state = state.clone();
+                    thread::spawn(move || {
+                        let now = Instant::now();
+                        if deadline > now {
+                            thread::sleep(deadline - now);
+                        }
+                        let waker = {
+                            let mut state = thread_state.lock().unwrap();
+                            state.fired = true;
+                            state.waker.take()
+                        };
+                        if let Some(waker) = waker {
+                            waker.wake();
+                        }
+                    });
+                    self.state = Some(state);
+                }
+            }
+            Poll::Pending
+        }
+    }
+
+    impl Drop for Sleep {
+        fn drop(&mut self) {
+            // Let go of the waker of the task early.
+            if let Some(state) = &self.state {
+                state.lock().unwrap().waker = None;
+            }
+        }
+    }
+
+    impl fmt::Debug for Sleep {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("Sleep")
+                .field("deadline", &self.deadline)
+                .finish()
+        }
+    }
+}
+
+#[cfg(all(feature = "wasm-timer", target_arch = "wasm32"))]
+mod wasm_timer {
+    use core::cell::RefCell;
+    use core::fmt;
+    use core::future::Future;
+    use core::pin::Pin;
+    use core::task::{Context, Poll, Waker};
+    use core::time::Duration;
+    use std::rc::Rc;
+    use wasm_bindgen::closure::Closure;
+    use wasm_bindgen::prelude::wasm_bindgen;
+    use wasm_bindgen::JsValue;
+
+    use super::Timer;
+
+    #[wasm_bindgen]
+    extern "C" {
+        #[wasm_bindgen(js_name = setTimeout)]
+        fn set_timeout(handler: &Closure<dyn FnMut()>, timeout: i32) -> JsValue;
+
+        #[wasm_bindgen(js_name = clearTimeout)]
+        fn clear_timeout(id: &JsValue);
+
+        #[wasm_bindgen(js_namespace = performance)]
+        fn now() -> f64;
+    }
+
+    /// A timer which sleeps with the `setTimeout` function of JavaScript.
+    ///
+    /// This works in browsers, in web workers and in Node.js, on the executor
+    /// of `wasm-bindgen-futures` or any other one. The sleeps aren't `Send`,
+    /// as JavaScript calls them back on the thread which started them.
+    ///
+    /// Like with [`ThreadTimer`](super::ThreadTimer), the deadline of a sleep
+    /// is set when it is created, measured with `performance.now()`, and the
+    /// timeout is only started for the time left once it is first polled.
+    ///
+    /// This requires the `wasm-timer` feature, and is only available on
+    /// `wasm32` targets.
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// use async_macros::time::{self, WasmTimer};
+    /// use std::time::Duration;
+    ///
+    /// let output = time::timeout(WasmTimer, Duration::from_secs(1), fetch()).await;
+    /// ```
+    #[derive(Debug, Clone, Copy, Default)]
+    pub struct WasmTimer;
+
+    impl Timer for WasmTimer {
+        type Sleep = WasmSleep;
+
+        fn sleep(&self, duration: Duration) -> WasmSleep {
+            WasmSleep {
+                duration,
+                deadline: now() + duration.as_secs_f64() * 1000.0,
+                timeout: None,
+            }
+        }
+    }
+
+    /// Future for the [`WasmTimer::sleep`] method.
+    #[must_use = "futures do nothing unless you `.await` or poll them"]
+    pub struct WasmSleep {
+        duration: Duration,
+        /// The value of `performance.now()` at which the sleep completes.
+        deadline: f64,
+        timeout: Option<Timeout>,
+    }
+
+    /// A call of `setTimeout` which hasn't been cleared.
+    struct Timeout {
+        id: JsValue,
+        state: Rc<RefCell<State>>,
+        // Kept alive until the timeout either fired or was cleared, as
+        // JavaScript would otherwise call a dropped closure.
+        _handler: Closure<dyn FnMut()>,
+    }
+
+    /// The state shared with the handler of a timeout.
+    struct State {
+        fired: bool,
+        waker: Option<Waker>,
+    }
+
+    /// Converts milliseconds to the whole milliseconds `setTimeout` takes,
+    /// rounding up so that the sleep is never too short.
+    fn millis(millis: f64) -> i32 {
+        // Larger timeouts overflow in browsers, and fire right away. The cast
+        // saturates.
+        millis.ceil().min(f64::from(i32::MAX)) as i32
+    }
+
+    impl Future for WasmSleep {
+        type Output = ();
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+            match &self.timeout {
+                Some(timeout) => {
+                    let mut state = timeout.state.borrow_mut();
+                    if state.fired {
+                        return Poll::Ready(());
+                    }
+                    match &state.waker {
+                        Some(waker) if waker.will_wake(cx.waker()) => {}
+                        _ => state.waker = Some(cx.waker().clone()),
+                    }
+                }
+                None => {
+                    let left = self.deadline - now();
+                    if left <= 0.0 {
+                        return Poll::Ready(());
+                    }
+                    let state = Rc::new(RefCell::new(State {
+                        fired: false,
+                        waker: Some(cx.waker().clone()),
+                    }));
+                    let handler_state = state.clone();
+                    let handler = Closure::new(move || {
+                        let waker = {
+                            let mut state = handler_state.borrow_mut();
+                            state.fired = true;
+                            state.waker.take()
+                        };
+                        if let Some(waker) = waker {
+                            waker.wake();
+                        }
+                    });
+                    let id = set_timeout(&handler, millis(left));
+                    self.timeout = Some(Timeout {
+                        id,
+                        state,
+                        _handler: handler,
+                    });
+                }
+            }
+            Poll::Pending
+        }
+    }
+
+    impl Drop for Timeout {
+        fn drop(&mut self) {
+            if !self.state.borrow().fired {
+                clear_timeout(&self.id);
+            }
+        }
+    }
+
+    impl fmt::Debug for WasmSleep {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("WasmSleep")
+                .field("duration", &self.duration)
+                .finish()
+        }
+    }
+}
@@ -0,0 +1,182 @@
+//! Definition of the `Timeout` future
+
+use core::fmt;
+use core::future::{Future, IntoFuture};
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use core::time::Duration;
+
+use pin_project_lite::pin_project;
+
+use crate::time::Timer;
+
+/// The error returned by a [`Timeout`] whose deadline passed before its
+/// future completed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Elapsed(());
+
+impl Elapsed {
+    /// Create a new `Elapsed` error.
+    pub fn new() -> Self {
+        Self(())
+    }
+}
+
+impl fmt::Display for Elapsed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("deadline has elapsed")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Elapsed {}
+
+/// Waits for a future to complete, giving up after `duration` has passed.
+///
+/// The returned future resolves to the output of `future`, or to an
+/// [`Elapsed`] error if `timer` slept for `duration` before it completed. In
+/// that case `future` is dropped along with the returned future. The error
+/// can be replaced by one of your own with [`Timeout::with_error`].
+///
+/// # Examples
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::future;
+/// use async_macros::time::{self, Elapsed, ThreadTimer};
+/// use std::time::Duration;
+///
+/// let duration = Duration::from_millis(10);
+///
+/// let fast = time::timeout(ThreadTimer, duration, future::ready(1u8));
+/// assert_eq!(fast.await, Ok(1));
+///
+/// let slow = time::timeout(ThreadTimer, duration, future::pending::<u8>());
+/// assert_eq!(slow.await, Err(Elapsed::new()));
+/// # });
+/// ```
+pub fn timeout<T, F>(timer: T, duration: Duration, future: F) -> Timeout<F::IntoFuture, T::Sleep>
+where
+    T: Timer,
+    F: IntoFuture,
+{
+    Timeout::new(future.into_future(), timer.sleep(duration))
+}
+
+/// Waits for a future to complete, giving up once `deadline` completes.
+///
+/// This is like [`timeout`], except that the deadline is any future rather
+/// than a sleep of a [`Timer`]: a sleep of a runtime, a shutdown signal, or a
+/// future a test completes by hand. Nothing about it depends on an executor
+/// or a clock. The output of `deadline` is ignored.
+///
+/// # Examples
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::time::{self, Elapsed};
+/// use async_macros::{future, join, Notify};
+///
+/// let shutdown = Notify::new();
+/// let work = time::with_timeout(future::pending::<u8>(), shutdown.notified());
+/// let trigger = async { shutdown.notify_one() };
+///
+/// assert_eq!(join!(work, trigger).await, (Err(Elapsed::new()), ()));
+/// # });
+/// ```
+pub fn with_timeout<F, D>(future: F, deadline: D) -> Timeout<F::IntoFuture, D::IntoFuture>
+where
+    F: IntoFuture,
+    D: IntoFuture,
+{
+    Timeout::new(future.into_future(), deadline.into_future())
+}
+
+pin_project! {
+    /// Future for the [`timeout`] and [`with_timeout`] functions.
+    ///
+    /// `M` makes the error returned once the deadline has passed, which is an
+    /// [`Elapsed`] unless it was changed with [`with_error`](Timeout::with_error).
+    #[must_use = "futures do nothing unless you `.await` or poll them"]
+    pub struct Timeout<F, D, M = fn() -> Elapsed> {
+        #[pin]
+        future: F,
+        #[pin]
+        deadline: D,
+        error: Option<M>,
+    }
+}
+
+impl<F, D> Timeout<F, D> {
+    fn new(future: F, deadline: D) -> Self {
+        Self {
+            future,
+            deadline,
+            error: Some(Elapsed::new),
+        }
+    }
+}
+
+impl<F, D, M> Timeout<F, D, M> {
+    /// Makes the error returned once the deadline has passed with `error`,
+    /// rather than returning an [`Elapsed`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # futures::executor::block_on(async {
+    /// use async_macros::future;
+    /// use async_macros::time::{self, ThreadTimer};
+    /// use std::io;
+    /// use std::time::Duration;
+    ///
+    /// let read = future::pending::<io::Result<Vec<u8>>>();
+    /// let output = time::timeout(ThreadTimer, Duration::from_millis(10), read)
+    ///     .with_error(|| io::Error::from(io::ErrorKind::TimedOut))
+    ///     .await;
+    /// assert_eq!(output.unwrap_err().kind(), io::ErrorKind::TimedOut);
+    /// # });
+    /// ```
+    pub fn with_error<N, E>(self, error: N) -> Timeout<F, D, N>
+    where
+        N: FnOnce() -> E,
+    {
+        Timeout {
+            future: self.future,
+            deadline: self.deadline,
+            error: Some(error),
+        }
+    }
+}
+
+impl<F, D, M, E> Future for Timeout<F, D, M>
+where
+    F: Future,
+    D: Future,
+    M: FnOnce() -> E,
+{
+    type Output = Result<F::Output, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        if let Poll::Ready(output) = this.future.poll(cx) {
+            return Poll::Ready(Ok(output));
+        }
+        match this.deadline.poll(cx) {
+            Poll::Ready(_) => {
+                let error = this
+                    .error
+                    .take()
+                    .expect("`Timeout` polled after completion");
+                Poll::Ready(Err(error()))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl<F, D, M> fmt::Debug for Timeout<F, D, M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Timeout").finish()
+    }
+}
@@ -4,6 +4,25 @@
 /// `try_join!` is similar to [`join!`], but completes immediately if any of
 /// the futures return an error.
 ///
+/// When a future returns an error, all of the other futures are dropped right
+/// away, before `try_join!` resolves to the error, rather than whenever the
+/// `try_join!` future itself is dropped. This includes the outputs of the
+/// futures which have already succeeded.
+///
+/// A hook can be run for every future which was cancelled this way, that is
+/// every future which hadn't completed yet, to tear down gracefully. With
+/// `on_cancel = <closure>;` before the futures, the closure is called with
+/// the [`Cancelled`](crate::Cancelled) future right after it was dropped.
+/// With `on_cancel_async = <closure>;` the closure returns a future instead,
+/// and the hooks are awaited one after another before `try_join!` resolves to
+/// the error.
+///
+/// Passing `tagged;` first wraps the error in a
+/// [`TaggedError`](crate::TaggedError), which holds the index and the name of
+/// the future which returned it, so that fan-out code can report or retry
+/// just the failing branch. It can be followed by `on_cancel` or
+/// `on_cancel_async`.
+///
 /// This macro is only usable inside of async functions, closures, and blocks.
 ///
 /// # Examples
@@ -37,56 +56,184 @@
 /// assert_eq!(try_join!(a, b).await, Err(2));
 /// # });
 /// ```
+///
+/// Running a hook for the cancelled futures:
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::{future, try_join};
+///
+/// let mut cancelled = Vec::new();
+///
+/// let upload = future::pending::<Result<u8, &str>>();
+/// let check = future::ready(Err::<u8, _>("invalid"));
+///
+/// let output = try_join!(
+///     on_cancel = |child: async_macros::Cancelled| cancelled.push(child.name());
+///     upload, check
+/// )
+/// .await;
+/// assert_eq!(output, Err("invalid"));
+/// assert_eq!(cancelled, ["upload"]);
+/// # });
+/// ```
+///
+/// Finding out which future failed:
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::{future, try_join};
+///
+/// let users = future::ready(Ok::<u8, &str>(1));
+/// let posts = future::ready(Err::<u8, _>("timed out"));
+///
+/// let err = try_join!(tagged; users, posts).await.unwrap_err();
+/// assert_eq!((err.index(), err.name()), (1, "posts"));
+/// assert_eq!(err.into_error(), "timed out");
+///
+/// // Futures passed as expressions are named after the expression.
+/// let err = try_join!(tagged; async { Err::<u8, _>("offline") })
+///     .await
+///     .unwrap_err();
+/// assert_eq!(err.name(), r#"async { Err::<u8, _>("offline") }"#);
+/// # });
+/// ```
 #[macro_export]
 macro_rules! try_join {
-    ($($fut:ident),* $(,)?) => { {
+    (tagged; $($tokens:tt)*) => {
+        $crate::try_join!(@with [tagged] $($tokens)*)
+    };
+    // `@with` is followed by `[tagged]` if the errors are tagged, or by `[]`.
+    (@with $tag:tt on_cancel = $hook:expr; $($fut:ident $(as $name:expr)?),* $(,)?) => {
+        $crate::try_join!(@record $tag $hook; $($fut $(as $name)?),*)
+    };
+    (@with $tag:tt on_cancel = $hook:expr; $($fut:expr),+ $(,)?) => {
+        $crate::utils::bind_futures!(try_join [@with $tag on_cancel = $hook;] [] $($fut),+)
+    };
+    (@with $tag:tt on_cancel_async = $hook:expr; $($fut:ident $(as $name:expr)?),+ $(,)?) => { {
+        async {
+            use $crate::utils::option::Option;
+            use $crate::utils::result::Result;
+
+            let mut hook = $hook;
+            let mut cancelled = [$({ let _ = stringify!($fut); Option::None }),+];
+            let mut next = 0;
+            let res = $crate::try_join!(@record $tag |child| {
+                cancelled[next] = Option::Some(child);
+                next += 1;
+            }; $($fut $(as $name)?),+).await;
+            if let Result::Err(_) = res {
+                for child in cancelled.iter_mut() {
+                    if let Option::Some(child) = child.take() {
+                        hook(child).await;
+                    }
+                }
+            }
+            res
+        }
+    } };
+    (@with $tag:tt on_cancel_async = $hook:expr; $($fut:expr),+ $(,)?) => {
+        $crate::utils::bind_futures!(try_join [@with $tag on_cancel_async = $hook;] [] $($fut),+)
+    };
+    (@record $tag:tt $record:expr; $($fut:ident $(as $name:expr)?),* $(,)?) => { {
         async {
             use $crate::utils::future::Future;
-            use $crate::utils::pin::Pin;
             use $crate::utils::poll_fn;
             use $crate::utils::result::Result;
-            use $crate::utils::task::Poll;
+            use $crate::utils::task::{Context, Poll};
 
+            let mut index = 0;
             $(
-                // Move future into a local so that it is pinned in one place and
+                let $fut = $crate::utils::assert_try_future($fut);
+                let name = $crate::utils::future_name!($fut $(as $name)?);
+                let $fut = $crate::utils::child("try_join", index, name, $fut);
+                index += 1;
+                // Pin the future on the stack, so that it stays in one place and
                 // is no longer accessible by the end user.
-                let mut $fut = $crate::MaybeDone::new($fut);
+                let mut $fut = $crate::utils::pin::pin!($crate::TryMaybeDone::new($fut));
             )*
 
+            let mut record = $record;
+            let router = $crate::utils::WakerRouter::new(index);
+            let mut done = [$({ let _ = stringify!($fut); false }),*];
             let res: Result<_, _> = poll_fn(move |cx| {
+                use $crate::utils::pin::Pin;
+
+                router.register(cx);
                 let mut all_done = true;
-                $(
-                    let fut = unsafe { Pin::new_unchecked(&mut $fut) };
-                    if Future::poll(fut, cx).is_pending() {
-                        all_done = false;
-                    } else if unsafe { Pin::new_unchecked(&mut $fut) }.output_mut().unwrap().is_err() {
-                        // `.err().unwrap()` rather than `.unwrap_err()` so that we don't introduce
-                        // a `T: Debug` bound.
-                        return Poll::Ready(
-                            Result::Err(unsafe { Pin::new_unchecked(&mut $fut) }
-                                .take()
-                                .unwrap()
-                                .err()
-                                .unwrap()
-                        ));
+                let mut failed = None;
+                // The children are polled in a loop rather than one after the
+                // other, which keeps the expansion small for many futures.
+                let children: &mut [Pin<&mut dyn Future<Output = Result<(), ()>>>] =
+                    &mut [$($fut.as_mut()),*];
+                for (index, child) in children.iter_mut().enumerate() {
+                    // Only poll the children which have been woken since they
+                    // were last polled.
+                    if !done[index] && router.take_woken(index) {
+                        let mut cx = Context::from_waker(router.waker(index, cx));
+                        match child.as_mut().poll(&mut cx) {
+                            Poll::Ready(Result::Ok(())) => done[index] = true,
+                            Poll::Ready(Result::Err(())) => {
+                                failed = Some(index);
+                                break;
+                            }
+                            Poll::Pending => {}
+                        }
                     }
-                )*
+                    all_done &= done[index];
+                }
+                if let Some(failed) = failed {
+                    let mut err = None;
+                    // Drop every other future right away.
+                    let mut index = 0;
+                    $(
+                        let name = $crate::utils::future_name!($fut $(as $name)?);
+                        if index == failed {
+                            err = $fut
+                                .as_mut()
+                                .take_err()
+                                .map(|err| $crate::try_join!(@tag $tag index, name, err));
+                        }
+                        let cancelled = $fut.is_future();
+                        $fut.set($crate::TryMaybeDone::Gone);
+                        if cancelled {
+                            record($crate::Cancelled::new(index, name));
+                        }
+                        index += 1;
+                    )*
+                    let _ = index;
+                    return Poll::Ready(Result::Err(err.unwrap()));
+                }
                 if all_done {
-                    let res = ($(
-                        // `.ok().unwrap()` rather than `.unwrap()` so that we don't introduce
-                        // an `E: Debug` bound.
-                        unsafe { Pin::new_unchecked(&mut $fut) }
-                            .take()
-                            .unwrap()
-                            .ok()
-                            .unwrap(),
-                    )*);
-                    Poll::Ready(Result::Ok(res))
+                    Poll::Ready(Result::Ok(($(
+                        $fut.as_mut().take_ok().unwrap(),
+                    )*)))
                 } else {
                     Poll::Pending
                 }
             }).await;
             res
         }
-    } }
+    } };
+    (@tag [tagged] $index:ident, $name:ident, $err:ident) => {
+        $crate::TaggedError::new($index, $name, $err)
+    };
+    (@tag [] $index:ident, $name:ident, $err:ident) => {
+        $err
+    };
+    (@with $tag:tt $($fut:ident $(as $name:expr)?),* $(,)?) => {
+        $crate::try_join!(@record $tag |_: $crate::Cancelled| {}; $($fut $(as $name)?),*)
+    };
+    (@with $tag:tt $($fut:expr),+ $(,)?) => {
+        $crate::utils::bind_futures!(try_join [@with $tag] [] $($fut),+)
+    };
+    (@with $($tokens:tt)*) => {
+        compile_error!(concat!(
+            "`try_join!` takes futures, which may be preceded by `tagged;`, ",
+            "and by `on_cancel = <closure>;` or `on_cancel_async = <closure>;`"
+        ))
+    };
+    ($($tokens:tt)*) => {
+        $crate::try_join!(@with [] $($tokens)*)
+    };
 }
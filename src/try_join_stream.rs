@@ -0,0 +1,88 @@
+/// Combines multiple fallible streams into a single stream of all their
+/// outputs, ending on the first error.
+///
+/// `try_join_stream!` is similar to [`join_stream!`], but for streams of
+/// `Result<T, E>`. `Ok` items are passed through as they become available,
+/// with polling starting after the stream that yielded last so that every
+/// stream gets the first chance in turn. As soon as any of the streams yields
+/// an `Err`, that error is yielded and the combined stream ends, without
+/// polling any of the streams again.
+///
+/// All streams must be `Unpin` and yield the same item type.
+///
+/// # Examples
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::try_join_stream;
+/// use futures::stream::{self, StreamExt};
+///
+/// let a = stream::iter(vec![Ok::<u8, u8>(1), Err(2), Ok(3)]);
+/// let b = stream::iter(vec![Ok::<u8, u8>(4)]);
+///
+/// let mut s = try_join_stream!(a, b);
+///
+/// assert_eq!(s.next().await, Some(Ok(1)));
+/// assert_eq!(s.next().await, Some(Ok(4)));
+/// assert_eq!(s.next().await, Some(Err(2)));
+/// assert_eq!(s.next().await, None);
+/// # });
+/// ```
+#[macro_export]
+macro_rules! try_join_stream {
+    ($($stream:ident $(as $name:expr)?),* $(,)?) => {{
+        let mut index = 0;
+        $(
+            let $stream = $crate::utils::assert_stream($stream);
+            let name = $crate::utils::future_name!($stream $(as $name)?);
+            let $stream = $crate::utils::child_stream("try_join_stream", index, name, $stream);
+            index += 1;
+            let mut $stream = $crate::MaybeDoneStream::new($stream);
+        )*
+        let len = index;
+        let mut start = 0;
+        let mut failed = false;
+        $crate::utils::poll_next_fn(move |cx| {
+            use $crate::utils::marker::Unpin;
+            use $crate::utils::pin::Pin;
+            use $crate::utils::result::Result;
+            use $crate::utils::stream::{FusedStream, Stream};
+            use $crate::utils::task::Poll;
+
+            if failed {
+                return Poll::Ready(None);
+            }
+
+            // Polling starts from `start`, and wraps around to the streams
+            // before it, like in `join_stream!`.
+            let streams: &mut [&mut (dyn FusedStream<Item = _> + Unpin)] = &mut [$(&mut $stream),*];
+            for offset in 0..len {
+                let index = (start + offset) % len;
+                let stream = &mut *streams[index];
+                if stream.is_terminated() {
+                    continue;
+                }
+                match Stream::poll_next(Pin::new(stream), cx) {
+                    Poll::Ready(Some(Result::Ok(item))) => {
+                        start = (index + 1) % len;
+                        return Poll::Ready(Some(Result::Ok(item)));
+                    }
+                    Poll::Ready(Some(Result::Err(err))) => {
+                        failed = true;
+                        return Poll::Ready(Some(Result::Err(err)));
+                    }
+                    Poll::Ready(None) | Poll::Pending => {}
+                }
+            }
+
+            if streams.iter().all(|stream| stream.is_terminated()) {
+                Poll::Ready(None)
+            } else {
+                Poll::Pending
+            }
+        })
+    }};
+    ($($stream:expr),+ $(,)?) => {
+        $crate::utils::bind_futures!(try_join_stream [] [] $($stream),+)
+    };
+}
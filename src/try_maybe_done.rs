@@ -0,0 +1,187 @@
+//! A type that wraps a fallible future to keep track of its completion status.
+
+use core::future::Future;
+use core::mem;
+use core::pin::Pin;
+
+use futures_core::future::{FusedFuture, TryFuture};
+use futures_core::ready;
+use futures_core::task::{Context, Poll};
+
+/// A fallible future that may have completed.
+///
+/// This is like [`MaybeDone`](crate::MaybeDone), but it keeps track of whether
+/// the future succeeded or failed. Polling it resolves to `Ok(())` or
+/// `Err(())` once the inner future has completed, after which the value can be
+/// taken with [`take_ok`](TryMaybeDone::take_ok) or
+/// [`take_err`](TryMaybeDone::take_err).
+///
+/// # Examples
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::TryMaybeDone;
+/// use futures::future;
+/// use std::pin::Pin;
+///
+/// let mut fut = TryMaybeDone::new(future::ready(Err::<u8, &str>("oh no")));
+///
+/// assert_eq!((&mut fut).await, Err(()));
+/// assert_eq!(Pin::new(&mut fut).take_ok(), None);
+/// assert_eq!(Pin::new(&mut fut).take_err(), Some("oh no"));
+/// # });
+/// ```
+#[derive(Debug)]
+pub enum TryMaybeDone<Fut: TryFuture> {
+    /// A not-yet-completed future
+    Future(Fut),
+    /// The output of the future, if it succeeded
+    Ok(Fut::Ok),
+    /// The error of the future, if it failed
+    Err(Fut::Error),
+    /// The empty variant after the result of a [`TryMaybeDone`] has been
+    /// taken using the [`take_ok`](TryMaybeDone::take_ok) or
+    /// [`take_err`](TryMaybeDone::take_err) methods.
+    Gone,
+}
+
+impl<Fut: TryFuture> TryMaybeDone<Fut> {
+    /// Create a new instance of `TryMaybeDone`.
+    pub fn new(future: Fut) -> TryMaybeDone<Fut> {
+        Self::Future(future)
+    }
+
+    /// Returns `true` if the inner future has not completed yet.
+    #[inline]
+    pub fn is_future(&self) -> bool {
+        matches!(self, TryMaybeDone::Future(_))
+    }
+
+    /// Returns an [`Option`] containing a reference to the output of the
+    /// future, if it succeeded and the output has not been taken yet.
+    #[inline]
+    pub fn ok(self: Pin<&Self>) -> Option<&Fut::Ok> {
+        match self.get_ref() {
+            TryMaybeDone::Ok(output) => Some(output),
+            _ => None,
+        }
+    }
+
+    /// Returns an [`Option`] containing a reference to the error of the
+    /// future, if it failed and the error has not been taken yet.
+    #[inline]
+    pub fn err(self: Pin<&Self>) -> Option<&Fut::Error> {
+        match self.get_ref() {
+            TryMaybeDone::Err(err) => Some(err),
+            _ => None,
+        }
+    }
+
+    /// Attempt to take the output of a `TryMaybeDone` without driving it
+    /// towards completion.
+    ///
+    /// Returns `None` if the future hasn't completed or has failed.
+    #[inline]
+    pub fn take_ok(self: Pin<&mut Self>) -> Option<Fut::Ok> {
+        if !matches!(&*self, TryMaybeDone::Ok(_)) {
+            return None;
+        }
+        self.project_replace(TryMaybeDone::Gone)?.ok()
+    }
+
+    /// Attempt to take the error of a `TryMaybeDone` without driving it
+    /// towards completion.
+    ///
+    /// Returns `None` if the future hasn't completed or has succeeded.
+    #[inline]
+    pub fn take_err(self: Pin<&mut Self>) -> Option<Fut::Error> {
+        if !matches!(&*self, TryMaybeDone::Err(_)) {
+            return None;
+        }
+        self.project_replace(TryMaybeDone::Gone)?.err()
+    }
+
+    /// Replaces `self` with `replacement`, returning the result if it was the
+    /// `Ok` or `Err` variant. A future in the `Future` variant is dropped in
+    /// place.
+    #[inline]
+    fn project_replace(
+        self: Pin<&mut Self>,
+        replacement: Self,
+    ) -> Option<Result<Fut::Ok, Fut::Error>> {
+        // SAFETY: the output and error are not pinned, so they may be moved
+        // out, and the future is only ever dropped in place through
+        // `Pin::set`.
+        //
+        // This can't be generated by `pin-project-lite`, which only projects
+        // enums with named fields, while the variants of `TryMaybeDone` are
+        // public tuple variants. Together with `future_pin_mut` this is the
+        // only `unsafe` code of `TryMaybeDone`.
+        unsafe {
+            let this = self.get_unchecked_mut();
+            if let TryMaybeDone::Future(_) = this {
+                Pin::new_unchecked(this).set(replacement);
+                return None;
+            }
+            match mem::replace(this, replacement) {
+                TryMaybeDone::Ok(output) => Some(Ok(output)),
+                TryMaybeDone::Err(err) => Some(Err(err)),
+                _ => None,
+            }
+        }
+    }
+
+    /// Returns a pinned reference to the inner future, if it hasn't completed.
+    ///
+    /// All pin projections of `TryMaybeDone` go through here.
+    #[inline]
+    fn future_pin_mut(self: Pin<&mut Self>) -> Option<Pin<&mut Fut>> {
+        // SAFETY: the inner future is structurally pinned: it is never moved
+        // out of a pinned `TryMaybeDone`, only dropped in place when the
+        // variant is replaced through `Pin::set`.
+        unsafe {
+            match self.get_unchecked_mut() {
+                TryMaybeDone::Future(fut) => Some(Pin::new_unchecked(fut)),
+                _ => None,
+            }
+        }
+    }
+}
+
+// The output and error are never pinned, so only the future needs to be
+// `Unpin`.
+impl<Fut: TryFuture + Unpin> Unpin for TryMaybeDone<Fut> {}
+
+impl<Fut: TryFuture> Future for TryMaybeDone<Fut> {
+    type Output = Result<(), ()>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let res = match self.as_mut().future_pin_mut() {
+            Some(fut) => ready!(fut.try_poll(cx)),
+            None => match &*self {
+                TryMaybeDone::Ok(_) => return Poll::Ready(Ok(())),
+                TryMaybeDone::Err(_) => return Poll::Ready(Err(())),
+                _ => panic!("TryMaybeDone polled after value taken"),
+            },
+        };
+        match res {
+            Ok(output) => {
+                self.set(TryMaybeDone::Ok(output));
+                Poll::Ready(Ok(()))
+            }
+            Err(err) => {
+                self.set(TryMaybeDone::Err(err));
+                Poll::Ready(Err(()))
+            }
+        }
+    }
+}
+
+impl<Fut: TryFuture> FusedFuture for TryMaybeDone<Fut> {
+    fn is_terminated(&self) -> bool {
+        match self {
+            TryMaybeDone::Future(_) => false,
+            TryMaybeDone::Ok(_) | TryMaybeDone::Err(_) | TryMaybeDone::Gone => true,
+        }
+    }
+}
@@ -5,8 +5,26 @@
 /// Awaits multiple futures simultaneously, returning all results once complete.
 ///
 /// `try_select!` is similar to [`select!`], but keeps going if a future
-/// resolved to an error until all futures have been resolved. In which case
-/// the error of the last item in the list will be returned.
+/// resolved to an error until all futures have been resolved. Which error is
+/// returned then is set by passing an error policy as `errors = <policy>;`
+/// before the futures:
+///
+/// - `first` (the default): the error of the first future in the list.
+/// - `last`: the error of the last future in the list.
+/// - `all`: all of the errors, as an array in the order of the list.
+///
+/// Like `select!`, it accepts `fair;` or `rng = <expr>;` before the futures
+/// to start every poll from a random future. The error returned when all of
+/// them fail doesn't depend on the order they were polled in.
+///
+/// Passing `indexed;` makes the macro also return the index of the future
+/// which produced the result, as `Ok((index, value))` or `Err((index,
+/// error))`, so that it can be told which of the futures answered, such as
+/// which replica of a service did. With `errors = all;` the errors stay an
+/// array, as their indices are their positions in it.
+///
+/// These options can be combined, in any order, as in `try_select!(indexed;
+/// errors = last; fair; a, b)`.
 ///
 /// This macro is only usable inside of async functions, closures, and blocks.
 ///
@@ -29,56 +47,182 @@
 /// # main().await.unwrap();
 /// # });
 /// ```
+///
+/// Finding out which future answered:
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::{future, try_select};
+///
+/// let primary = future::ready(Err::<u8, _>("primary is down"));
+/// let replica = future::ready(Ok(1u8));
+///
+/// assert_eq!(try_select!(indexed; primary, replica).await, Ok((1, 1)));
+/// # });
+/// ```
+///
+/// Getting all of the errors:
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::{future, try_select};
+///
+/// let a = future::ready(Err::<u8, _>("a is down"));
+/// let b = future::ready(Err::<u8, _>("b is down"));
+///
+/// let output = try_select!(errors = all; a, b).await;
+/// assert_eq!(output, Err(["a is down", "b is down"]));
+/// # });
+/// ```
 #[macro_export]
 macro_rules! try_select {
-    ($($fut:ident),+ $(,)?) => { {
+    // The options before the futures are collected first, as
+    // `[indexed] [errors] [rng]` with `indexed` and `rng` possibly empty.
+    (@options [$($indexed:ident)?] $errors:tt [$($rng:expr)?] indexed; $($rest:tt)+) => {
+        $crate::try_select!(@options [indexed] $errors [$($rng)?] $($rest)+)
+    };
+    (@options $indexed:tt $errors:tt [$($rng:expr)?] errors = $policy:ident; $($rest:tt)+) => {
+        $crate::try_select!(@options $indexed [$policy] [$($rng)?] $($rest)+)
+    };
+    (@options $indexed:tt $errors:tt [$($rng:expr)?] fair; $($rest:tt)+) => {
+        $crate::try_select!(@options $indexed $errors [$crate::rng::SmallRng::new()] $($rest)+)
+    };
+    (@options $indexed:tt $errors:tt [$($rng:expr)?] rng = $new_rng:expr; $($rest:tt)+) => {
+        $crate::try_select!(@options $indexed $errors [$new_rng] $($rest)+)
+    };
+    (@options $indexed:tt $errors:tt $rng:tt $($fut:ident $(as $name:expr)?),+ $(,)?) => {
+        $crate::try_select!(@select $indexed $errors $rng $($fut $(as $name)?),+)
+    };
+    (@options $indexed:tt $errors:tt $rng:tt $($fut:expr),+ $(,)?) => {
+        $crate::utils::bind_futures!(try_select [@select $indexed $errors $rng] [] $($fut),+)
+    };
+    (@select $indexed:tt [$errors:ident] [$($rng:expr)?] $($fut:ident $(as $name:expr)?),+) => { {
         async {
             use $crate::utils::future::Future;
+            use $crate::utils::option::Option;
             use $crate::utils::pin::Pin;
             use $crate::utils::poll_fn;
             use $crate::utils::result::Result;
             use $crate::utils::task::Poll;
 
+            $(let mut rng = $rng;)?
+            $crate::utils::assert_same_output!($($fut),*);
+            let mut index = 0;
             $(
-                // Move future into a local so that it is pinned in one place and
+                let $fut = $crate::utils::assert_try_future($fut);
+                let name = $crate::utils::future_name!($fut $(as $name)?);
+                let $fut = $crate::utils::child("try_select", index, name, $fut);
+                index += 1;
+                // Pin the future on the stack, so that it stays in one place and
                 // is no longer accessible by the end user.
-                let mut $fut = $crate::MaybeDone::new($fut);
+                let mut $fut = $crate::utils::pin::pin!($crate::TryMaybeDone::new($fut));
             )*
+            let len = index;
 
             let res: Result<_, _> = poll_fn(move |cx| {
                 let mut all_done = true;
 
-                $(
-                    let fut = unsafe { Pin::new_unchecked(&mut $fut) };
-                    if Future::poll(fut, cx).is_ready() {
-                        let fut = Pin::new(&$fut);
-                        if fut.output().unwrap().is_ok() {
-                            let fut = unsafe { Pin::new_unchecked(&mut $fut) };
-                            let res = fut.take().unwrap();
-                            return Poll::Ready(res);
-                        } else {
-                            all_done = false;
+                // Polling starts from `start`, and wraps around to the futures
+                // before it.
+                let start = 0 $(+ {
+                    let _ = stringify!($rng);
+                    $crate::rng::Rng::gen_index(&mut rng, len)
+                })?;
+                let mut winner = Option::None;
+                {
+                    let futures: &mut [Pin<&mut dyn Future<Output = Result<(), ()>>>] =
+                        &mut [$($fut.as_mut()),*];
+                    for offset in 0..len {
+                        let turn = (start + offset) % len;
+                        match futures[turn].as_mut().poll(cx) {
+                            Poll::Ready(Result::Ok(())) => {
+                                winner = Option::Some(turn);
+                                break;
+                            }
+                            Poll::Ready(Result::Err(())) => {}
+                            Poll::Pending => all_done = false,
                         }
-                    } else {
-                        all_done = false;
                     }
-                )*
-
-                if all_done {
-                    // We need to iterate over all items to get the last error.
-                    let mut err = None;
+                }
+                if let Option::Some(winner) = winner {
+                    let mut index = 0;
                     $(
-                        if err.is_none() {
-                            let fut = unsafe { Pin::new_unchecked(&mut $fut) };
-                            err = Some(fut.take().unwrap());
+                        if index == winner {
+                            let output = $fut.as_mut().take_ok().unwrap();
+                            return Poll::Ready(Result::Ok((index, output)));
                         }
+                        index += 1;
                     )*
-                    return Poll::Ready(err.unwrap());
+                    let _ = index;
+                }
+
+                if all_done {
+                    let err = $crate::try_select!(@errors $errors $($fut),+);
+                    Poll::Ready(Result::Err(err))
                 } else {
                     Poll::Pending
                 }
             }).await;
-            res
+            $crate::try_select!(@output $indexed $errors res)
+        }
+    } };
+    // The error of every policy, once all of the futures failed.
+    (@errors first $($fut:ident),+) => { {
+        let mut err = Option::None;
+        let mut index = 0;
+        $(
+            if err.is_none() {
+                err = $fut.as_mut().take_err().map(|err| (index, err));
+            }
+            index += 1;
+        )*
+        let _ = index;
+        err.unwrap()
+    } };
+    (@errors last $($fut:ident),+) => { {
+        let mut err = Option::None;
+        let mut index = 0;
+        $(
+            if let Option::Some(last) = $fut.as_mut().take_err() {
+                err = Option::Some((index, last));
+            }
+            index += 1;
+        )*
+        let _ = index;
+        err.unwrap()
+    } };
+    (@errors all $($fut:ident),+) => {
+        [$($fut.as_mut().take_err().unwrap()),+]
+    };
+    (@errors $policy:ident $($fut:ident),+) => {
+        compile_error!(concat!(
+            "unknown error policy `",
+            stringify!($policy),
+            "`, expected `first`, `last` or `all`"
+        ))
+    };
+    (@output [indexed] $errors:ident $res:ident) => {
+        $res
+    };
+    (@output [] all $res:ident) => {
+        match $res {
+            Result::Ok((_, output)) => Result::Ok(output),
+            Result::Err(errs) => Result::Err(errs),
+        }
+    };
+    (@output [] $errors:ident $res:ident) => {
+        match $res {
+            Result::Ok((_, output)) => Result::Ok(output),
+            Result::Err((_, err)) => Result::Err(err),
         }
-    } }
+    };
+    (@options $($tokens:tt)*) => {
+        compile_error!(concat!(
+            "`try_select!` takes futures, which may be preceded by `indexed;`, ",
+            "`errors = <policy>;`, and `fair;` or `rng = <expr>;`"
+        ))
+    };
+    ($($tokens:tt)+) => {
+        $crate::try_select!(@options [] [first] [] $($tokens)+)
+    };
 }
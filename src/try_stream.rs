@@ -0,0 +1,39 @@
+/// Creates a fallible stream from an async block which `yield`s its items.
+///
+/// This is like [`stream!`], except that the stream yields `Result`s: every
+/// `yield value` yields `Ok(value)`, and the block may use the `?` operator.
+/// If it returns early with an error, the error is yielded as an `Err` item
+/// and the stream ends.
+///
+/// The error type often can't be inferred from the uses of `?` alone, in
+/// which case the type of the stream has to be annotated.
+///
+/// This requires the `macros` feature.
+///
+/// # Examples
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::try_stream;
+/// use futures::stream::{Stream, StreamExt};
+///
+/// fn parse<'a>(lines: &'a [&'a str]) -> impl Stream<Item = Result<u8, std::num::ParseIntError>> + 'a {
+///     try_stream! {
+///         for line in lines {
+///             yield line.parse::<u8>()?;
+///         }
+///     }
+/// }
+///
+/// let items = parse(&["1", "2", "x", "4"]).collect::<Vec<_>>().await;
+/// assert_eq!(items.len(), 3);
+/// assert_eq!(items[..2], [Ok(1), Ok(2)]);
+/// assert!(items[2].is_err());
+/// # });
+/// ```
+#[macro_export]
+macro_rules! try_stream {
+    ($($body:tt)*) => {
+        $crate::utils::try_stream_impl! { $crate; $($body)* }
+    };
+}
@@ -0,0 +1,142 @@
+//! Per-child wakers for the joining macros.
+//!
+//! Every child of a `join!` gets its own waker. Waking it marks the child in a
+//! shared bitset before waking the task polling the join, so that only the
+//! children which were actually woken need to be polled again.
+//!
+//! This needs to allocate the wakers, so it is only enabled with the
+//! `waker-routing` feature. Without it, all children are polled with the
+//! waker of the task, and every child is polled on every wakeup.
+
+#[cfg(feature = "waker-routing")]
+mod imp {
+    use alloc::sync::Arc;
+    use alloc::task::Wake;
+    use alloc::vec::Vec;
+    use core::fmt;
+    use core::sync::atomic::{AtomicUsize, Ordering};
+    use core::task::{Context, Waker};
+
+    use crate::sync::Mutex;
+
+    const BITS: usize = usize::BITS as usize;
+
+    /// Routes wakeups of child futures back to the task polling them,
+    /// recording which children were woken.
+    pub struct WakerRouter {
+        shared: Arc<Shared>,
+        wakers: Vec<Waker>,
+    }
+
+    struct Shared {
+        woken: Vec<AtomicUsize>,
+        parent: Mutex<Option<Waker>>,
+    }
+
+    struct Child {
+        index: usize,
+        shared: Arc<Shared>,
+    }
+
+    impl Wake for Child {
+        fn wake(self: Arc<Self>) {
+            self.wake_by_ref();
+        }
+
+        fn wake_by_ref(self: &Arc<Self>) {
+            let bit = 1 << (self.index % BITS);
+            self.shared.woken[self.index / BITS].fetch_or(bit, Ordering::AcqRel);
+            // Wake outside of the lock, as waking may run arbitrary code.
+            let parent = self.shared.parent.lock().clone();
+            if let Some(parent) = parent {
+                parent.wake();
+            }
+        }
+    }
+
+    impl WakerRouter {
+        /// Create a new router for `len` children.
+        ///
+        /// All children start out marked as woken, so they all get polled once.
+        pub fn new(len: usize) -> Self {
+            let words = len.div_ceil(BITS);
+            let woken = (0..words).map(|_| AtomicUsize::new(usize::MAX)).collect();
+            let shared = Arc::new(Shared {
+                woken,
+                parent: Mutex::new(None),
+            });
+            let wakers = (0..len)
+                .map(|index| {
+                    Waker::from(Arc::new(Child {
+                        index,
+                        shared: shared.clone(),
+                    }))
+                })
+                .collect();
+            Self { shared, wakers }
+        }
+
+        /// Register the waker of the task polling the children.
+        pub fn register(&self, cx: &Context<'_>) {
+            let mut parent = self.shared.parent.lock();
+            match &*parent {
+                Some(waker) if waker.will_wake(cx.waker()) => {}
+                _ => *parent = Some(cx.waker().clone()),
+            }
+        }
+
+        /// Returns whether the child at `index` was woken, clearing the mark.
+        ///
+        /// The mark is cleared before the child is polled, so wakeups happening
+        /// during the poll are not lost.
+        pub fn take_woken(&self, index: usize) -> bool {
+            let bit = 1 << (index % BITS);
+            self.shared.woken[index / BITS].fetch_and(!bit, Ordering::AcqRel) & bit != 0
+        }
+
+        /// Returns the waker to poll the child at `index` with.
+        pub fn waker<'a>(&'a self, index: usize, _cx: &'a Context<'_>) -> &'a Waker {
+            &self.wakers[index]
+        }
+    }
+
+    impl fmt::Debug for WakerRouter {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("WakerRouter")
+                .field("len", &self.wakers.len())
+                .finish()
+        }
+    }
+}
+
+#[cfg(not(feature = "waker-routing"))]
+mod imp {
+    use core::task::{Context, Waker};
+
+    /// Polls every child with the waker of the task polling them.
+    #[derive(Debug)]
+    pub struct WakerRouter(());
+
+    impl WakerRouter {
+        /// Create a new router for `len` children.
+        pub fn new(_len: usize) -> Self {
+            Self(())
+        }
+
+        /// Register the waker of the task polling the children.
+        pub fn register(&self, _cx: &Context<'_>) {}
+
+        /// Returns whether the child at `index` needs to be polled, which is
+        /// always the case.
+        pub fn take_woken(&self, _index: usize) -> bool {
+            true
+        }
+
+        /// Returns the waker to poll the child at `index` with.
+        pub fn waker<'a>(&'a self, _index: usize, cx: &'a Context<'_>) -> &'a Waker {
+            cx.waker()
+        }
+    }
+}
+
+pub use imp::WakerRouter;
@@ -0,0 +1,163 @@
+//! Definition of the `WakerSet` slab
+
+use alloc::vec::Vec;
+use core::fmt;
+use core::task::Waker;
+
+use crate::sync::Mutex;
+
+/// A set of wakers registered by many tasks waiting on the same thing.
+///
+/// Every waiter [`insert`](WakerSet::insert)s its waker and holds on to the
+/// returned key. Notifying a waiter wakes it and marks its entry as
+/// notified, but the entry stays reserved until the waiter
+/// [`remove`](WakerSet::remove)s it, so keys are never reused while they are
+/// held. A waiter which is dropped after being notified can pass the
+/// notification on to another one.
+///
+/// # Examples
+///
+/// ```
+/// use async_macros::task::{MockWaker, WakerSet};
+///
+/// let set = WakerSet::new();
+/// let (a, b) = (MockWaker::new(), MockWaker::new());
+/// let key_a = set.insert(&a.waker());
+/// let key_b = set.insert(&b.waker());
+///
+/// assert!(set.notify_one());
+/// assert_eq!(a.wake_count() + b.wake_count(), 1);
+///
+/// assert!(set.notify_all());
+/// assert_eq!(a.wake_count() + b.wake_count(), 2);
+///
+/// assert!(set.remove(key_a));
+/// assert!(set.remove(key_b));
+/// ```
+#[derive(Default)]
+pub struct WakerSet {
+    slab: Mutex<Slab>,
+}
+
+#[derive(Default)]
+struct Slab {
+    entries: Vec<Entry>,
+    vacant: Vec<usize>,
+}
+
+enum Entry {
+    Vacant,
+    Waiting(Waker),
+    Notified,
+}
+
+impl WakerSet {
+    /// Create a new, empty set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a waker, returning the key of its entry.
+    pub fn insert(&self, waker: &Waker) -> usize {
+        let mut slab = self.slab.lock();
+        let entry = Entry::Waiting(waker.clone());
+        match slab.vacant.pop() {
+            Some(key) => {
+                slab.entries[key] = entry;
+                key
+            }
+            None => {
+                slab.entries.push(entry);
+                slab.entries.len() - 1
+            }
+        }
+    }
+
+    /// Replaces the waker of an entry.
+    ///
+    /// Returns `false`, leaving the entry untouched, if it has been notified
+    /// already.
+    pub fn update(&self, key: usize, waker: &Waker) -> bool {
+        let mut slab = self.slab.lock();
+        match &mut slab.entries[key] {
+            Entry::Waiting(current) => {
+                if !current.will_wake(waker) {
+                    *current = waker.clone();
+                }
+                true
+            }
+            Entry::Notified => false,
+            Entry::Vacant => panic!("invalid `WakerSet` key"),
+        }
+    }
+
+    /// Removes an entry, returning whether it had been notified.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the key was removed already, leaving the set untouched.
+    pub fn remove(&self, key: usize) -> bool {
+        let mut slab = self.slab.lock();
+        let notified = match core::mem::replace(&mut slab.entries[key], Entry::Vacant) {
+            Entry::Waiting(_) => false,
+            Entry::Notified => true,
+            // The key is already on the free list; pushing it again would hand
+            // the same slot to two entries.
+            Entry::Vacant => {
+                drop(slab);
+                panic!("invalid `WakerSet` key");
+            }
+        };
+        slab.vacant.push(key);
+        notified
+    }
+
+    /// Wakes one waiting entry, returning whether there was one.
+    pub fn notify_one(&self) -> bool {
+        let waker = {
+            let mut slab = self.slab.lock();
+            slab.entries.iter_mut().find_map(|entry| match entry {
+                Entry::Waiting(_) => match core::mem::replace(entry, Entry::Notified) {
+                    Entry::Waiting(waker) => Some(waker),
+                    _ => unreachable!(),
+                },
+                _ => None,
+            })
+        };
+        match waker {
+            Some(waker) => {
+                waker.wake();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Wakes every waiting entry, returning whether there were any.
+    pub fn notify_all(&self) -> bool {
+        let wakers: Vec<Waker> = {
+            let mut slab = self.slab.lock();
+            slab.entries
+                .iter_mut()
+                .filter_map(|entry| match entry {
+                    Entry::Waiting(_) => match core::mem::replace(entry, Entry::Notified) {
+                        Entry::Waiting(waker) => Some(waker),
+                        _ => unreachable!(),
+                    },
+                    _ => None,
+                })
+                .collect()
+        };
+        let notified = !wakers.is_empty();
+        for waker in wakers {
+            waker.wake();
+        }
+        notified
+    }
+}
+
+impl fmt::Debug for WakerSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WakerSet").finish()
+    }
+}
@@ -0,0 +1,38 @@
+/// Yields to the executor once.
+///
+/// This lets long-running loops inside of joined futures give their siblings
+/// a chance to make progress. See [`future::yield_now`](crate::future::yield_now).
+///
+/// This macro is only usable inside of async functions, closures, and blocks.
+///
+/// # Examples
+///
+/// ```
+/// # futures::executor::block_on(async {
+/// use async_macros::{join, yield_now};
+/// use std::cell::RefCell;
+///
+/// let log = RefCell::new(vec![]);
+/// let a = async {
+///     for i in 0..2 {
+///         log.borrow_mut().push(("a", i));
+///         yield_now!();
+///     }
+/// };
+/// let b = async {
+///     for i in 0..2 {
+///         log.borrow_mut().push(("b", i));
+///         yield_now!();
+///     }
+/// };
+/// join!(a, b).await;
+///
+/// assert_eq!(*log.borrow(), vec![("a", 0), ("b", 0), ("a", 1), ("b", 1)]);
+/// # });
+/// ```
+#[macro_export]
+macro_rules! yield_now {
+    () => {
+        $crate::future::yield_now().await
+    };
+}
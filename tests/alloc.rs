@@ -0,0 +1,99 @@
+//! Checks that the core macros never allocate.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use async_macros::task::noop_context;
+use async_macros::{future, select, try_select};
+
+struct CountingAlloc;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|n| n.set(n.get() + 1));
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+/// Polls `fut` to completion, returning its output and the number of
+/// allocations made on this thread in the meantime.
+fn count_allocations<F: std::future::Future>(fut: F) -> (F::Output, usize) {
+    let before = ALLOCATIONS.with(|n| n.get());
+    let mut fut = std::pin::pin!(fut);
+    let mut cx = noop_context();
+    let output = loop {
+        if let std::task::Poll::Ready(output) = fut.as_mut().poll(&mut cx) {
+            break output;
+        }
+    };
+    (output, ALLOCATIONS.with(|n| n.get()) - before)
+}
+
+#[cfg(not(feature = "waker-routing"))]
+#[test]
+fn join_does_not_allocate() {
+    let (output, allocs) = count_allocations(async {
+        let a = future::ready(1u8);
+        let b = async {
+            future::yield_now().await;
+            2u8
+        };
+        async_macros::join!(a, b).await
+    });
+    assert_eq!(output, (1, 2));
+    assert_eq!(allocs, 0);
+}
+
+#[cfg(not(feature = "waker-routing"))]
+#[test]
+fn try_join_does_not_allocate() {
+    let (output, allocs) = count_allocations(async {
+        let a = future::ready(Ok::<u8, ()>(1));
+        let b = async {
+            future::yield_now().await;
+            Ok::<u8, ()>(2)
+        };
+        async_macros::try_join!(a, b).await
+    });
+    assert_eq!(output, Ok((1, 2)));
+    assert_eq!(allocs, 0);
+}
+
+#[test]
+fn select_does_not_allocate() {
+    let (output, allocs) = count_allocations(async {
+        let a = future::pending::<u8>();
+        let b = async {
+            future::yield_now().await;
+            2u8
+        };
+        select!(a, b).await
+    });
+    assert_eq!(output, 2);
+    assert_eq!(allocs, 0);
+}
+
+#[test]
+fn try_select_does_not_allocate() {
+    let (output, allocs) = count_allocations(async {
+        let a = future::ready(Err::<u8, ()>(()));
+        let b = async {
+            future::yield_now().await;
+            Ok::<u8, ()>(2)
+        };
+        try_select!(a, b).await
+    });
+    assert_eq!(output, Ok(2));
+    assert_eq!(allocs, 0);
+}
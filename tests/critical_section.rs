@@ -0,0 +1,35 @@
+//! Checks task locals without `std`, tracked with `critical-section`.
+//!
+//! Run with `cargo test --no-default-features --features critical-section`.
+#![cfg(all(feature = "critical-section", not(feature = "std")))]
+
+use core::future::Future;
+use core::pin::pin;
+use core::task::Poll;
+
+use async_macros::task::{self, noop_context};
+use async_macros::{poll_fn, task_local};
+
+task_local! {
+    static ID: u32 = 7;
+}
+
+#[test]
+fn task_locals_need_a_single_context_to_be_assumed() {
+    let mut cx = noop_context();
+    let read = || poll_fn(|_| Poll::Ready(ID.try_with(|id| *id)));
+
+    // Until then no execution context can see the locals of the task.
+    let mut unchecked = pin!(task::with_locals(read()));
+    assert!(matches!(
+        unchecked.as_mut().poll(&mut cx),
+        Poll::Ready(Err(_))
+    ));
+
+    // SAFETY: this test polls its tasks from a single thread, and nothing else
+    // accesses task locals.
+    unsafe { task::assume_single_context() };
+    let mut assumed = pin!(task::with_locals(read()));
+    assert!(matches!(assumed.as_mut().poll(&mut cx), Poll::Ready(Ok(7))));
+    assert!(ID.try_with(|id| *id).is_err());
+}
@@ -4,3 +4,1998 @@ use std::error::Error;
 fn should_work() -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
     Ok(())
 }
+
+#[test]
+fn interleave_stream_waits_for_its_turn() {
+    use async_macros::interleave_stream;
+    use futures::stream::{self, StreamExt};
+
+    futures::executor::block_on(async {
+        // `b` is not ready on its first poll, but `c` must still wait for it.
+        let a = stream::iter(vec![1u8, 4]);
+        let mut first = true;
+        let b = stream::poll_fn(move |cx| {
+            if first {
+                first = false;
+                cx.waker().wake_by_ref();
+                std::task::Poll::Pending
+            } else {
+                std::task::Poll::Ready(None)
+            }
+        });
+        let c = stream::iter(vec![3u8, 5]);
+
+        let s = interleave_stream!(a, b, c);
+        assert_eq!(s.collect::<Vec<_>>().await, vec![1, 3, 4, 5]);
+    });
+}
+
+#[test]
+fn named_join_polls_futures_concurrently() {
+    use async_macros::{future, join};
+    use std::cell::RefCell;
+
+    futures::executor::block_on(async {
+        let log = RefCell::new(vec![]);
+        let step = |name: &'static str| {
+            let log = &log;
+            async move {
+                log.borrow_mut().push((name, 1));
+                future::yield_now().await;
+                log.borrow_mut().push((name, 2));
+                name.len()
+            }
+        };
+        let output = join!(first = step("a"), second = step("bb")).await;
+        assert_eq!((output.first, output.second), (1, 2));
+        assert_eq!(*log.borrow(), [("a", 1), ("bb", 1), ("a", 2), ("bb", 2)]);
+    });
+}
+
+#[test]
+fn join_all_limited_refills_slots_as_futures_complete() {
+    use async_macros::{join_all_limited, oneshot};
+    use std::future::Future;
+    use std::pin::Pin;
+
+    futures::executor::block_on(async {
+        let (sender, receiver) = oneshot::channel();
+        // The first future only completes once the third one has started, so
+        // this would deadlock if slots were only refilled in order.
+        let futures: Vec<Pin<Box<dyn Future<Output = u8>>>> = vec![
+            Box::pin(async { receiver.await.unwrap() }),
+            Box::pin(async { 2 }),
+            Box::pin(async {
+                sender.send(1).unwrap();
+                3
+            }),
+        ];
+        assert_eq!(join_all_limited(futures, 2).await, vec![1, 2, 3]);
+    });
+}
+
+#[test]
+fn buffer_unordered_limits_futures_in_flight() {
+    use async_macros::{buffer_unordered, future};
+    use futures::stream::{self, StreamExt};
+    use std::cell::Cell;
+
+    futures::executor::block_on(async {
+        let in_flight = Cell::new(0);
+        let most_in_flight = Cell::new(0);
+        let outputs = buffer_unordered(stream::iter(0..5u8), 2, |n| {
+            let (in_flight, most_in_flight) = (&in_flight, &most_in_flight);
+            async move {
+                in_flight.set(in_flight.get() + 1);
+                most_in_flight.set(most_in_flight.get().max(in_flight.get()));
+                for _ in 0..n {
+                    future::yield_now().await;
+                }
+                in_flight.set(in_flight.get() - 1);
+                n
+            }
+        });
+        let mut outputs = outputs.collect::<Vec<_>>().await;
+        outputs.sort();
+        assert_eq!(outputs, vec![0, 1, 2, 3, 4]);
+        assert_eq!(most_in_flight.get(), 2);
+    });
+}
+
+#[test]
+fn stream_select_releases_streams_before_body() {
+    use async_macros::stream_select;
+    use futures::stream::{self, StreamExt};
+
+    futures::executor::block_on(async {
+        let mut a = stream::iter(vec![1u8, 2, 3]);
+        let mut b = stream::pending::<u8>();
+        let mut seen = vec![];
+        loop {
+            stream_select! {
+                n = b.next() => seen.extend(n),
+                n = a.next() => match n {
+                    Some(n) => {
+                        // The branch body may poll the stream it came from.
+                        seen.push(n);
+                        seen.extend(a.next().await);
+                    }
+                    None => break,
+                },
+            }
+        }
+        assert_eq!(seen, vec![1, 2, 3]);
+    });
+}
+
+#[test]
+fn stream_select_skips_disabled_branches() {
+    use async_macros::stream_select;
+    use futures::stream::{self, StreamExt};
+
+    futures::executor::block_on(async {
+        let mut a = stream::iter(vec![1u8, 2]);
+        let mut b = stream::iter(vec![10u8, 20]);
+        let mut created = 0;
+        let mut seen = vec![];
+        for round in 0..3 {
+            stream_select! {
+                // Listed first, so it would always win if it were enabled.
+                n = { created += 1; a.next() }, if round == 1 => seen.extend(n),
+                n = b.next() => seen.extend(n),
+            }
+        }
+        assert_eq!(created, 1);
+        assert_eq!(seen, vec![10, 1, 20]);
+    });
+}
+
+#[test]
+fn stream_select_disables_ended_streams() {
+    use async_macros::stream_select;
+    use futures::stream::{self, StreamExt};
+
+    futures::executor::block_on(async {
+        let mut a = stream::iter(vec![1u8]).fuse();
+        let mut b = stream::iter(vec![10u8, 20, 30]).fuse();
+        let mut rounds = 0;
+        let mut seen = vec![];
+        while !b.is_done() {
+            rounds += 1;
+            stream_select! {
+                n in a => seen.push(n),
+                n in b => seen.push(n),
+            }
+        }
+        // One round for every item, plus one for the end of each stream.
+        assert_eq!(rounds, 6);
+        assert_eq!(seen, vec![1, 10, 20, 30]);
+    });
+}
+
+#[test]
+fn select_loop_breaks_from_complete_branch() {
+    use async_macros::select_loop;
+    use futures::stream::{self, StreamExt};
+
+    futures::executor::block_on(async {
+        let mut a = stream::iter(vec![1u8, 2]).fuse();
+        let mut b = stream::iter(vec![10u8]).fuse();
+        let mut enabled = true;
+        let mut total = 0;
+        let output = select_loop! {
+            _ = async {}, if enabled => enabled = false,
+            n in a => total += n,
+            n in b => total += n,
+            complete => break total,
+        };
+        assert_eq!(output, 13);
+    });
+}
+
+#[test]
+#[should_panic(expected = "all branches of `stream_select!` are disabled")]
+fn stream_select_panics_if_every_branch_is_disabled() {
+    use async_macros::stream_select;
+    use futures::stream::{self, StreamExt};
+
+    futures::executor::block_on(async {
+        let mut a = stream::iter(vec![1u8]);
+        let enabled = false;
+        stream_select! {
+            _ = a.next(), if enabled => {},
+        }
+    });
+}
+
+#[test]
+fn join_stream_is_fair_and_waits_for_all_streams() {
+    use async_macros::join_stream;
+    use futures::stream::{self, StreamExt};
+
+    futures::executor::block_on(async {
+        let a = stream::iter(vec![1u8, 2, 3]);
+        let b = stream::iter(vec![4u8, 5, 6]);
+        // `c` stays pending for a while after the other streams have ended.
+        let mut polls = 0;
+        let c = stream::poll_fn(move |cx| {
+            polls += 1;
+            match polls {
+                1..=8 => {
+                    cx.waker().wake_by_ref();
+                    std::task::Poll::Pending
+                }
+                9 => std::task::Poll::Ready(Some(7)),
+                _ => std::task::Poll::Ready(None),
+            }
+        });
+
+        let s = join_stream!(a, b, c);
+        assert_eq!(s.collect::<Vec<_>>().await, vec![1, 4, 2, 5, 3, 6, 7]);
+    });
+}
+
+#[test]
+fn try_join_stream_takes_turns_between_streams() {
+    use async_macros::try_join_stream;
+    use futures::stream::{self, StreamExt};
+
+    futures::executor::block_on(async {
+        let a = stream::iter(vec![Ok::<u8, u8>(1), Ok(2), Ok(3)]);
+        let b = stream::iter(vec![Ok::<u8, u8>(4), Ok(5)]);
+        let c = stream::iter(vec![Ok::<u8, u8>(6), Err(7), Ok(8)]);
+
+        let s = try_join_stream!(a, b, c);
+        assert_eq!(
+            s.collect::<Vec<_>>().await,
+            vec![Ok(1), Ok(4), Ok(6), Ok(2), Ok(5), Err(7)]
+        );
+    });
+}
+
+#[test]
+fn futures_unordered_only_polls_woken_futures() {
+    use async_macros::FuturesUnordered;
+    use futures::channel::oneshot;
+    use futures::future::{self, FutureExt};
+    use futures::stream::StreamExt;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    futures::executor::block_on(async {
+        let polls = Rc::new(Cell::new(0));
+        let (tx, rx) = oneshot::channel::<u8>();
+
+        let mut set = FuturesUnordered::new();
+        let counter = polls.clone();
+        set.push(
+            future::poll_fn(move |_| {
+                // Never woken, so this should only be polled once.
+                counter.set(counter.get() + 1);
+                std::task::Poll::Pending
+            })
+            .boxed_local(),
+        );
+        set.push(rx.map(|res| res.unwrap()).boxed_local());
+        tx.send(3).unwrap();
+
+        assert_eq!(set.next().await, Some(3));
+        assert_eq!(set.len(), 1);
+        set.push(future::ready(4).boxed_local());
+        assert_eq!(set.next().await, Some(4));
+        assert_eq!(polls.get(), 1);
+    });
+}
+
+#[cfg(feature = "waker-routing")]
+#[test]
+fn join_only_polls_woken_futures() {
+    use async_macros::join;
+    use futures::channel::oneshot;
+    use futures::future;
+    use std::cell::Cell;
+
+    futures::executor::block_on(async {
+        let polls = Cell::new(0);
+        let (tx, rx) = oneshot::channel::<u8>();
+
+        let a = future::poll_fn(|_| {
+            polls.set(polls.get() + 1);
+            if polls.get() > 1 {
+                std::task::Poll::Ready(())
+            } else {
+                std::task::Poll::Pending
+            }
+        });
+        let b = async { rx.await.unwrap() };
+        let c = async {
+            tx.send(1).unwrap();
+        };
+
+        // `b` is woken by `c` during the first poll, but `a` is never woken
+        // and must not be polled again.
+        let joined = join!(a, b, c);
+        futures::pin_mut!(joined);
+        assert!(futures::poll!(joined.as_mut()).is_pending());
+        assert!(futures::poll!(joined.as_mut()).is_pending());
+        assert_eq!(polls.get(), 1);
+    });
+}
+
+#[test]
+fn maybe_done_projects_pinned_futures() {
+    use async_macros::MaybeDone;
+
+    futures::executor::block_on(async {
+        // Async blocks are `!Unpin`, so this exercises the pin projection.
+        let fut = MaybeDone::new(async { 3u8 });
+        futures::pin_mut!(fut);
+
+        assert_eq!(fut.as_mut().output_mut(), None);
+        assert_eq!(fut.as_mut().take(), None);
+        fut.as_mut().await;
+        assert_eq!(fut.as_mut().output_mut(), Some(&mut 3));
+        assert_eq!(fut.as_mut().take(), Some(3));
+        assert!(matches!(*fut, MaybeDone::Gone));
+        assert_eq!(fut.as_mut().take(), None);
+    });
+}
+
+#[test]
+fn try_select_resolves_when_all_futures_fail() {
+    use async_macros::try_select;
+    use futures::future;
+
+    futures::executor::block_on(async {
+        let a = future::ready(Err::<u8, u8>(1));
+        let b = future::ready(Err::<u8, u8>(2));
+        assert!(try_select!(a, b).await.is_err());
+    });
+}
+
+#[test]
+fn futures_unordered_is_fused_until_pushed_into() {
+    use async_macros::FuturesUnordered;
+    use futures::future;
+    use futures::stream::{FusedStream, StreamExt};
+
+    futures::executor::block_on(async {
+        let mut set = FuturesUnordered::new();
+        assert!(!set.is_terminated());
+        assert_eq!(set.next().await, None);
+        assert!(set.is_terminated());
+
+        set.push(future::ready(1u8));
+        assert!(!set.is_terminated());
+        assert_eq!(set.next().await, Some(1));
+    });
+}
+
+#[test]
+fn join_wakes_its_task_once_per_child_wakeup() {
+    use async_macros::join;
+    use async_macros::task::MockWaker;
+    use futures::channel::oneshot;
+    use std::future::Future;
+    use std::task::Context;
+
+    let (tx, rx) = oneshot::channel::<u8>();
+    let a = async { rx.await.unwrap() };
+    let b = async { 2u8 };
+    let fut = join!(a, b);
+    futures::pin_mut!(fut);
+
+    let mock = MockWaker::new();
+    let waker = mock.waker();
+    let mut cx = Context::from_waker(&waker);
+    assert!(fut.as_mut().poll(&mut cx).is_pending());
+    assert_eq!(mock.wake_count(), 0);
+
+    tx.send(1).unwrap();
+    assert_eq!(mock.wake_count(), 1);
+    assert_eq!(fut.as_mut().poll(&mut cx), std::task::Poll::Ready((1, 2)));
+    assert_eq!(mock.wake_count(), 1);
+}
+
+#[cfg(feature = "executor")]
+#[test]
+fn block_on_is_woken_from_other_threads() {
+    use async_macros::executor::block_on;
+    use futures::channel::oneshot;
+
+    let (tx, rx) = oneshot::channel::<u8>();
+    let handle = std::thread::spawn(move || {
+        std::thread::sleep(std::time::Duration::from_millis(10));
+        tx.send(1).unwrap();
+    });
+    assert_eq!(block_on(rx), Ok(1));
+    handle.join().unwrap();
+}
+
+#[test]
+fn task_locals_are_separate_for_joined_tasks() {
+    use async_macros::{future, join, task, task_local};
+    use std::cell::Cell;
+
+    task_local! {
+        static ID: Cell<u32> = Cell::new(0);
+    }
+
+    async fn run(id: u32) -> u32 {
+        ID.with(|cell| cell.set(id));
+        future::yield_now().await;
+        ID.with(|cell| cell.get())
+    }
+
+    futures::executor::block_on(async {
+        let a = task::with_locals(run(1));
+        let b = task::with_locals(run(2));
+        assert_eq!(join!(a, b).await, (1, 2));
+    });
+}
+
+#[test]
+fn task_local_scope_only_applies_to_its_future() {
+    use async_macros::{future, join, task, task_local};
+
+    task_local! {
+        static ID: u32 = 0;
+    }
+
+    futures::executor::block_on(task::with_locals(async {
+        let a = ID.scope(1, async {
+            future::yield_now().await;
+            ID.with(|id| *id)
+        });
+        let b = async {
+            future::yield_now().await;
+            ID.with(|id| *id)
+        };
+        assert_eq!(join!(a, b).await, (1, 0));
+    }));
+}
+
+#[test]
+fn task_locals_are_dropped_when_the_task_is_cancelled() {
+    use async_macros::{future, task, task_local};
+    use std::future::Future;
+    use std::sync::{Arc, Mutex};
+
+    struct Flush(Arc<Mutex<Vec<&'static str>>>);
+
+    impl Drop for Flush {
+        fn drop(&mut self) {
+            self.0.lock().unwrap().push("value dropped");
+        }
+    }
+
+    task_local! {
+        static FLUSH: Option<Flush> = None;
+    }
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let task = {
+        let events = events.clone();
+        task::with_locals(async move {
+            FLUSH.set(Some(Flush(events.clone())));
+            task::on_task_end(move || events.lock().unwrap().push("hook ran"));
+            future::pending::<()>().await;
+        })
+    };
+
+    let mut task = Box::pin(task);
+    let mut cx = task::noop_context();
+    assert!(task.as_mut().poll(&mut cx).is_pending());
+    assert!(events.lock().unwrap().is_empty());
+
+    drop(task);
+    assert_eq!(*events.lock().unwrap(), vec!["hook ran", "value dropped"]);
+}
+
+#[test]
+fn lazy_wakes_every_joined_branch() {
+    use async_macros::{future, join, Lazy};
+    use futures::channel::oneshot;
+
+    futures::executor::block_on(async {
+        let (tx, rx) = oneshot::channel::<u8>();
+        let value = Lazy::new(async { rx.await.unwrap() });
+
+        let a = value.get();
+        let b = value.get();
+        let c = async {
+            future::yield_now().await;
+            tx.send(7).unwrap();
+        };
+        assert_eq!(join!(a, b, c).await, (&7, &7, ()));
+    });
+}
+
+#[test]
+fn lazy_getters_take_over_from_a_dropped_one() {
+    use async_macros::{task, Lazy};
+    use futures::channel::oneshot;
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::task::{Context, Poll, Wake, Waker};
+
+    struct Flag(AtomicBool);
+
+    impl Wake for Flag {
+        fn wake(self: Arc<Self>) {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    let (tx, rx) = oneshot::channel::<u8>();
+    let lazy = Lazy::new(async { rx.await.unwrap() });
+    let flag = Arc::new(Flag(AtomicBool::new(false)));
+    let waker = Waker::from(flag.clone());
+    let mut cx = Context::from_waker(&waker);
+
+    let mut second = lazy.get();
+    assert!(Pin::new(&mut second).poll(&mut cx).is_pending());
+    // The first getter polls the initialization future last, and so holds on
+    // to its wakeup.
+    let mut first = lazy.get();
+    assert!(Pin::new(&mut first)
+        .poll(&mut task::noop_context())
+        .is_pending());
+    flag.0.store(false, Ordering::SeqCst);
+
+    drop(first);
+    assert!(flag.0.swap(false, Ordering::SeqCst));
+    assert!(Pin::new(&mut second).poll(&mut cx).is_pending());
+
+    tx.send(5).unwrap();
+    assert!(flag.0.load(Ordering::SeqCst));
+    assert_eq!(Pin::new(&mut second).poll(&mut cx), Poll::Ready(&5));
+}
+
+#[test]
+fn shared_resolves_on_every_thread() {
+    use async_macros::Shared;
+    use futures::channel::oneshot;
+
+    let (tx, rx) = oneshot::channel::<u8>();
+    let shared = Shared::new(async { rx.await.unwrap() });
+
+    let handles: Vec<_> = (0..4)
+        .map(|_| {
+            let shared = shared.clone();
+            std::thread::spawn(move || futures::executor::block_on(shared))
+        })
+        .collect();
+    tx.send(3).unwrap();
+    for handle in handles {
+        assert_eq!(handle.join().unwrap(), 3);
+    }
+    assert_eq!(shared.peek(), Some(3));
+}
+
+#[test]
+fn shared_handles_panic_after_the_future_panicked() {
+    use async_macros::{future, Shared};
+    use std::future::Future;
+    use std::panic::{self, AssertUnwindSafe};
+    use std::pin::Pin;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::task::{Context, Wake, Waker};
+
+    struct Flag(AtomicBool);
+
+    impl Wake for Flag {
+        fn wake(self: Arc<Self>) {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    let shared = Shared::new(async {
+        future::yield_now().await;
+        panic!("oh no");
+    });
+    let mut a = shared.clone();
+    let mut b = shared;
+
+    let flag = Arc::new(Flag(AtomicBool::new(false)));
+    let waker = Waker::from(flag.clone());
+    let mut cx = Context::from_waker(&waker);
+    assert!(Pin::new(&mut b).poll(&mut cx).is_pending());
+    flag.0.store(false, Ordering::SeqCst);
+
+    let panicked = panic::catch_unwind(AssertUnwindSafe(|| Pin::new(&mut a).poll(&mut cx)));
+    assert!(panicked.is_err());
+    // The other handle is woken rather than left waiting, and panics too.
+    assert!(flag.0.load(Ordering::SeqCst));
+    let panicked = panic::catch_unwind(AssertUnwindSafe(|| Pin::new(&mut b).poll(&mut cx)));
+    assert!(panicked.is_err());
+}
+
+#[test]
+fn remote_is_canceled_when_its_handle_is_dropped() {
+    use async_macros::{future, remote, task};
+    use std::future::Future;
+
+    let (driver, handle) = remote(future::pending::<u8>());
+    let mut driver = Box::pin(driver);
+    let mut cx = task::noop_context();
+    assert!(driver.as_mut().poll(&mut cx).is_pending());
+
+    drop(handle);
+    assert!(driver.as_mut().poll(&mut cx).is_ready());
+}
+
+#[test]
+fn notify_waiters_only_wakes_existing_futures() {
+    use async_macros::{now_or_never, Notify};
+
+    let notify = Notify::new();
+    let mut before = Box::pin(notify.notified());
+    assert_eq!(now_or_never!(before.as_mut()), None);
+
+    notify.notify_waiters();
+    let after = notify.notified();
+    assert_eq!(now_or_never!(before), Some(()));
+    assert_eq!(now_or_never!(after), None);
+
+    // A dropped waiter passes its notification on.
+    let mut first = Box::pin(notify.notified());
+    let mut second = Box::pin(notify.notified());
+    assert_eq!(now_or_never!(first.as_mut()), None);
+    assert_eq!(now_or_never!(second.as_mut()), None);
+    notify.notify_one();
+    drop(first);
+    assert_eq!(now_or_never!(second), Some(()));
+}
+
+#[test]
+fn semaphore_hands_permits_to_other_threads() {
+    use async_macros::Semaphore;
+    use std::sync::Arc;
+
+    let semaphore = Arc::new(Semaphore::new(1));
+    let permit = semaphore.try_acquire().unwrap();
+    let handles: Vec<_> = (0..4)
+        .map(|_| {
+            let semaphore = semaphore.clone();
+            std::thread::spawn(move || {
+                futures::executor::block_on(async {
+                    drop(semaphore.acquire().await);
+                })
+            })
+        })
+        .collect();
+    drop(permit);
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    assert_eq!(semaphore.available_permits(), 1);
+}
+
+#[test]
+#[cfg(feature = "macros")]
+fn nested_streams_yield_to_their_own_stream() {
+    use async_macros::stream;
+    use futures::stream::StreamExt;
+
+    futures::executor::block_on(async {
+        let outer = stream! {
+            for n in 0..2u8 {
+                let inner = stream! {
+                    yield n;
+                    yield n + 10;
+                };
+                let items = inner.collect::<Vec<_>>().await;
+                yield items;
+            }
+        };
+        assert_eq!(outer.collect::<Vec<_>>().await, [[0, 10], [1, 11]]);
+    });
+}
+
+#[test]
+#[cfg(feature = "macros")]
+fn streams_can_yield_from_match_arms() {
+    use async_macros::{stream, try_stream};
+    use futures::stream::StreamExt;
+
+    futures::executor::block_on(async {
+        let numbers = stream! {
+            for n in 0..3u8 {
+                match n {
+                    0 => yield Result::<u8, u8>::Err(n),
+                    1 => yield Ok(n + 1),
+                    _ => yield Ok(n * 2),
+                }
+            }
+        };
+        let numbers = numbers.collect::<Vec<_>>().await;
+        assert_eq!(numbers, [Err(0), Ok(2), Ok(4)]);
+
+        let closures = stream! {
+            yield |a: u8, b: u8| a + b;
+        };
+        let closures = closures.collect::<Vec<_>>().await;
+        assert_eq!(closures[0](1, 2), 3);
+
+        let parsed = try_stream! {
+            for s in ["1", "x"] {
+                match s.parse::<u8>() {
+                    Ok(n) => yield n,
+                    Err(_) => Err("not a number")?,
+                }
+            }
+        };
+        let parsed = parsed.collect::<Vec<_>>().await;
+        assert_eq!(parsed, [Ok(1), Err("not a number")]);
+    });
+}
+
+#[test]
+#[cfg(all(feature = "macros", feature = "executor"))]
+fn main_attribute_blocks_on_the_default_executor() {
+    use async_macros::future;
+
+    #[async_macros::main]
+    async fn main() -> Result<u8, ()> {
+        future::yield_now().await;
+        Ok(1)
+    }
+
+    assert_eq!(main(), Ok(1));
+}
+
+#[cfg(feature = "macros")]
+#[async_macros::test(block_on = futures::executor::block_on)]
+async fn test_attribute_runs_async_tests() {
+    use async_macros::{future, join};
+
+    let a = async {
+        future::yield_now().await;
+        1u8
+    };
+    let b = future::ready(2u8);
+    assert_eq!(join!(a, b).await, (1, 2));
+}
+
+#[test]
+#[cfg(feature = "tracing")]
+fn joined_futures_are_polled_in_spans_of_their_own() {
+    use async_macros::{interleave_stream, join, join_catch, task, try_join_stream};
+    use futures::stream::{self, StreamExt};
+    use std::cell::RefCell;
+    use std::sync::Mutex;
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata};
+    use tracing_core::span::Current;
+
+    /// The fields of every span, with the ID of its parent.
+    type Span = (String, Option<u64>, &'static Metadata<'static>);
+    static SPANS: Mutex<Vec<Span>> = Mutex::new(Vec::new());
+    static ENTERED: Mutex<Vec<u64>> = Mutex::new(Vec::new());
+
+    thread_local! {
+        static STACK: RefCell<Vec<u64>> = const { RefCell::new(Vec::new()) };
+    }
+
+    struct Fields(String);
+
+    impl Visit for Fields {
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            self.0 += &format!(" {}={:?}", field.name(), value);
+        }
+    }
+
+    struct Recorder;
+
+    impl tracing::Subscriber for Recorder {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            let mut fields = Fields(span.metadata().name().to_string());
+            span.record(&mut fields);
+            let parent = match span.parent() {
+                Some(parent) => Some(parent.into_u64()),
+                None if span.is_contextual() => STACK.with(|stack| stack.borrow().last().copied()),
+                None => None,
+            };
+            let mut spans = SPANS.lock().unwrap();
+            spans.push((fields.0, parent, span.metadata()));
+            Id::from_u64(spans.len() as u64)
+        }
+        fn record(&self, _: &Id, _: &Record<'_>) {}
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+        fn event(&self, _: &Event<'_>) {}
+        fn enter(&self, span: &Id) {
+            ENTERED.lock().unwrap().push(span.into_u64());
+            STACK.with(|stack| stack.borrow_mut().push(span.into_u64()));
+        }
+        fn exit(&self, _: &Id) {
+            STACK.with(|stack| stack.borrow_mut().pop());
+        }
+        fn current_span(&self) -> Current {
+            match STACK.with(|stack| stack.borrow().last().copied()) {
+                Some(id) => {
+                    let metadata = SPANS.lock().unwrap()[id as usize - 1].2;
+                    Current::new(Id::from_u64(id), metadata)
+                }
+                None => Current::none(),
+            }
+        }
+    }
+
+    let find = |expected: &str| {
+        let spans = SPANS.lock().unwrap();
+        let index = spans.iter().position(|(span, ..)| span == expected);
+        let index = index.unwrap_or_else(|| panic!("no span {}", expected));
+        (index as u64 + 1, spans[index].1)
+    };
+
+    tracing::subscriber::set_global_default(Recorder).unwrap();
+    futures::executor::block_on(async {
+        let traced_a = async { 1u8 };
+        let traced_b = async { 2u8 };
+        assert_eq!(join!(traced_a, traced_b).await, (1, 2));
+        let (caught,) = join_catch!(async { 3u8 }).await;
+        assert_eq!(caught.unwrap(), 3);
+
+        let interleaved = stream::iter(vec![4u8]);
+        assert_eq!(
+            interleave_stream!(interleaved).collect::<Vec<_>>().await,
+            [4]
+        );
+        let fallible = stream::iter(vec![Ok::<u8, ()>(5)]);
+        let items = try_join_stream!(fallible).collect::<Vec<_>>().await;
+        assert_eq!(items, [Ok(5)]);
+    });
+
+    for (index, name) in ["traced_a", "traced_b"].iter().enumerate() {
+        let expected = format!(
+            "child combinator=\"join\" index={} name=\"{}\"",
+            index, name
+        );
+        let (id, _) = find(&expected);
+        assert!(ENTERED.lock().unwrap().contains(&id));
+    }
+    // `join_catch!` names its futures after the expressions, not `catch_unwind`.
+    find("child combinator=\"join\" index=0 name=\"async { 3u8 }\"");
+    find("child combinator=\"interleave_stream\" index=0 name=\"interleaved\"");
+    find("child combinator=\"try_join_stream\" index=0 name=\"fallible\"");
+
+    // The span of a task follows it to the thread polling it.
+    let request = tracing::trace_span!("request", id = 7u8);
+    let task = request.in_scope(|| {
+        task::with_locals(async {
+            let moved_a = async { 1u8 };
+            let moved_b = async { 2u8 };
+            join!(moved_a, moved_b).await
+        })
+    });
+    let output = std::thread::spawn(|| futures::executor::block_on(task));
+    assert_eq!(output.join().unwrap(), (1, 2));
+
+    let (request, _) = find("request id=7");
+    let (_, parent) = find("child combinator=\"join\" index=0 name=\"moved_a\"");
+    assert_eq!(parent, Some(request));
+}
+
+#[test]
+fn seeded_select_is_deterministic_and_fair() {
+    use async_macros::rng::{SeedableRng, SmallRng};
+    use async_macros::{future, select, try_select};
+
+    futures::executor::block_on(async {
+        let mut firsts = Vec::new();
+        for seed in 0..32 {
+            let a = future::ready(1u8);
+            let b = future::ready(2u8);
+            let first = select!(rng = SmallRng::seed_from_u64(seed); a, b).await;
+
+            let a = future::ready(1u8);
+            let b = future::ready(2u8);
+            assert_eq!(
+                select!(rng = SmallRng::seed_from_u64(seed); a, b).await,
+                first
+            );
+            firsts.push(first);
+        }
+        assert!(firsts.contains(&1) && firsts.contains(&2));
+
+        let mut rng = SmallRng::seed_from_u64(3);
+        let a = future::ready(Err::<u8, _>("a"));
+        let b = future::ready(Err::<u8, _>("b"));
+        let expected = try_select!(a, b).await;
+        let a = future::ready(Err::<u8, _>("a"));
+        let b = future::ready(Err::<u8, _>("b"));
+        assert_eq!(try_select!(rng = &mut rng; a, b).await, expected);
+    });
+}
+
+#[test]
+#[cfg(feature = "budget")]
+fn nested_budgets_use_the_smaller_one() {
+    use async_macros::{future, join, task};
+    use std::future::Future;
+    use std::pin::pin;
+
+    let inner = async {
+        assert_eq!(task::remaining_budget(), Some(2));
+        let a = future::ready(1u8);
+        join!(a).await;
+    };
+    let mut outer = pin!(task::with_budget(2, async {
+        task::with_budget(10, inner).await;
+        assert_eq!(task::remaining_budget(), Some(1));
+    }));
+    let mut cx = task::noop_context();
+    assert!(outer.as_mut().poll(&mut cx).is_ready());
+    assert_eq!(task::remaining_budget(), None);
+}
+
+#[test]
+fn join_catch_keeps_polling_siblings_after_a_panic() {
+    use async_macros::{future, join_catch};
+
+    futures::executor::block_on(async {
+        let panicking = async {
+            future::yield_now().await;
+            panic!("oh no");
+        };
+        let sibling = async {
+            for _ in 0..3 {
+                future::yield_now().await;
+            }
+            "done"
+        };
+        let (panicking, sibling) = join_catch!(panicking, sibling).await;
+        let panicking: Result<(), _> = panicking;
+        assert_eq!(panicking.unwrap_err().message(), Some("oh no"));
+        assert_eq!(sibling.unwrap(), "done");
+    });
+}
+
+#[test]
+fn join_drops_siblings_before_propagating_a_panic() {
+    use async_macros::{future, join, task};
+    use std::cell::Cell;
+    use std::future::Future;
+    use std::panic::{self, AssertUnwindSafe};
+    use std::pin::pin;
+
+    struct Guard<'a>(&'a Cell<bool>);
+
+    impl Drop for Guard<'_> {
+        fn drop(&mut self) {
+            self.0.set(true);
+        }
+    }
+
+    let dropped = Cell::new(false);
+    let guard = Guard(&dropped);
+    let sibling = async move {
+        let _guard = guard;
+        future::pending::<()>().await;
+    };
+    let panicking = async { panic!("oh no") };
+
+    let mut join = pin!(join!(drop_on_panic; sibling, panicking));
+    let mut cx = task::noop_context();
+    let output = panic::catch_unwind(AssertUnwindSafe(|| join.as_mut().poll(&mut cx)));
+    assert!(output.is_err());
+    // The join itself is still alive.
+    assert!(dropped.get());
+}
+
+#[test]
+fn try_join_drops_siblings_eagerly_and_runs_async_hooks() {
+    use async_macros::{future, try_join, Cancelled};
+    use std::cell::{Cell, RefCell};
+
+    struct Guard<'a>(&'a Cell<bool>);
+
+    impl Drop for Guard<'_> {
+        fn drop(&mut self) {
+            self.0.set(true);
+        }
+    }
+
+    futures::executor::block_on(async {
+        let dropped = Cell::new(false);
+        let torn_down = RefCell::new(Vec::new());
+
+        let guard = Guard(&dropped);
+        let pending = async move {
+            let _guard = guard;
+            future::pending::<Result<u8, &str>>().await
+        };
+        let done = future::ready(Ok::<u8, &str>(1));
+        let failing = async {
+            future::yield_now().await;
+            Err::<u8, _>("oh no")
+        };
+
+        let output = try_join!(
+            on_cancel_async = |child: Cancelled| {
+                // The future was dropped before the hook runs.
+                assert!(dropped.get());
+                let torn_down = &torn_down;
+                async move { torn_down.borrow_mut().push((child.index(), child.name())) }
+            };
+            pending, done, failing
+        )
+        .await;
+        assert_eq!(output, Err("oh no"));
+        assert_eq!(*torn_down.borrow(), [(0, "pending")]);
+    });
+}
+
+#[test]
+fn hedge_only_starts_backups_after_their_delay() {
+    use async_macros::time::Timer;
+    use async_macros::{future, hedge, task};
+    use std::cell::Cell;
+    use std::future::Future;
+    use std::pin::pin;
+    use std::task::Poll;
+    use std::time::Duration;
+
+    /// A timer whose sleeps complete once `fired` is set.
+    struct Manual<'a>(&'a Cell<bool>);
+
+    impl<'a> Timer for Manual<'a> {
+        type Sleep = std::pin::Pin<Box<dyn Future<Output = ()> + 'a>>;
+
+        fn sleep(&self, _duration: Duration) -> Self::Sleep {
+            let fired = self.0;
+            Box::pin(std::future::poll_fn(move |_| {
+                if fired.get() {
+                    Poll::Ready(())
+                } else {
+                    Poll::Pending
+                }
+            }))
+        }
+    }
+
+    let fired = Cell::new(false);
+    let backup_started = Cell::new(false);
+    let primary = future::pending::<u8>();
+    let backup = async {
+        backup_started.set(true);
+        2u8
+    };
+
+    let mut hedged = pin!(hedge!(Manual(&fired); primary, backup after Duration::from_secs(1)));
+    let mut cx = task::noop_context();
+    assert!(hedged.as_mut().poll(&mut cx).is_pending());
+    assert!(!backup_started.get());
+
+    fired.set(true);
+    assert_eq!(hedged.as_mut().poll(&mut cx), Poll::Ready(2));
+}
+
+#[test]
+fn quorum_drops_stragglers() {
+    use async_macros::{future, quorum};
+
+    futures::executor::block_on(async {
+        let slow = async {
+            future::yield_now().await;
+            future::yield_now().await;
+            1u8
+        };
+        let fast = future::ready(2u8);
+        let medium = async {
+            future::yield_now().await;
+            3u8
+        };
+        assert_eq!(quorum!(2; slow, fast, medium).await, [(1, 2), (2, 3)]);
+
+        let a = future::ready(1u8);
+        let none: [(usize, u8); 0] = quorum!(0; a).await;
+        assert_eq!(none, []);
+    });
+}
+
+#[test]
+#[should_panic(expected = "`quorum!` can't wait for more futures than it was given")]
+fn quorum_panics_if_n_is_too_large() {
+    use async_macros::{future, quorum};
+
+    futures::executor::block_on(async {
+        let a = future::ready(1u8);
+        quorum!(2; a).await;
+    });
+}
+
+#[test]
+fn select_priority_is_fair_among_equal_priorities() {
+    use async_macros::rng::{SeedableRng, SmallRng};
+    use async_macros::{future, select_priority};
+
+    futures::executor::block_on(async {
+        let mut rng = SmallRng::seed_from_u64(1);
+        let mut outputs = Vec::new();
+        for _ in 0..32 {
+            let low = future::ready(0u8);
+            let a = future::ready(1u8);
+            let b = future::ready(2u8);
+            outputs.push(select_priority!(rng = &mut rng; low => 0, a => 5, b => 5).await);
+        }
+        assert!(!outputs.contains(&0));
+        assert!(outputs.contains(&1) && outputs.contains(&2));
+
+        // The winner doesn't depend on the order the futures are listed in.
+        let a = future::ready(1u8);
+        let b = future::ready(2u8);
+        let c = future::ready(3u8);
+        assert_eq!(select_priority!(a => 1, b => 3, c => 2).await, 2);
+        let pending = future::pending::<u8>();
+        let c = future::ready(3u8);
+        assert_eq!(select_priority!(pending => 9, c => 2).await, 3);
+    });
+}
+
+#[test]
+fn macros_scale_to_many_futures() {
+    use async_macros::{future, join, join_stream, select, try_join};
+    use futures::stream::{self, StreamExt};
+
+    macro_rules! many {
+        ($($fut:ident)*) => { {
+            $( let $fut = future::ready(1usize); )*
+            let joined = join!($($fut),*).await;
+            assert_eq!(joined.63, 1);
+
+            $( let $fut = future::ready(Ok::<usize, ()>(1)); )*
+            assert!(try_join!($($fut),*).await.is_ok());
+
+            $( let $fut = future::ready(1usize); )*
+            assert_eq!(select!($($fut),*).await, 1);
+
+            $( let $fut = stream::once(future::ready(1usize)); )*
+            assert_eq!(join_stream!($($fut),*).collect::<Vec<_>>().await.len(), 64);
+        } };
+    }
+
+    futures::executor::block_on(async {
+        many!(f0 f1 f2 f3 f4 f5 f6 f7 f8 f9 f10 f11 f12 f13 f14 f15 f16 f17 f18 f19 f20 f21 f22 f23 f24 f25 f26 f27 f28 f29 f30 f31 f32 f33 f34 f35 f36 f37 f38 f39 f40 f41 f42 f43 f44 f45 f46 f47 f48 f49 f50 f51 f52 f53 f54 f55 f56 f57 f58 f59 f60 f61 f62 f63);
+    });
+}
+
+#[test]
+fn macros_accept_expressions() {
+    use async_macros::{
+        future, join, join_array, join_catch, join_limited, join_stream, quorum, select, try_join,
+        try_select,
+    };
+    use futures::stream::{self, StreamExt};
+
+    futures::executor::block_on(async {
+        let a = future::ready(1u8);
+        assert_eq!(join!(a, async { 2u8 }, future::ready(3u8)).await, (1, 2, 3));
+        assert_eq!(
+            join!(drop_on_panic; async { 1u8 }, async { 2u8 }).await,
+            (1, 2)
+        );
+        assert_eq!(join_array!(async { 1u8 }, async { 2u8 }).await, [1, 2]);
+        assert_eq!(join_limited!(1; async { 1u8 }, async { 2u8 }).await, (1, 2));
+        let (a, b) = join_catch!(async { 1u8 }, async { 2u8 }).await;
+        assert_eq!((a.unwrap(), b.unwrap()), (1, 2));
+
+        let fetch = |n: u8| async move { Ok::<u8, ()>(n) };
+        assert_eq!(try_join!(fetch(1), fetch(2)).await, Ok((1, 2)));
+        assert_eq!(
+            try_join!(on_cancel = |_| {}; fetch(1), async { Err::<u8, ()>(()) }).await,
+            Err(())
+        );
+
+        assert_eq!(select!(future::pending::<u8>(), async { 2u8 }).await, 2);
+        assert_eq!(select!(fair; async { 1u8 }, async { 1u8 }).await, 1);
+        assert_eq!(try_select!(fetch(1), future::pending()).await, Ok(1));
+        assert_eq!(
+            quorum!(1; future::pending::<u8>(), async { 2u8 }).await,
+            [(1, 2)]
+        );
+
+        let s = join_stream!(stream::iter(vec![1u8]), stream::iter(vec![2u8]));
+        assert_eq!(s.collect::<Vec<_>>().await, [1, 2]);
+    });
+}
+
+#[test]
+fn join_and_try_join_accept_mutable_references() {
+    use async_macros::{future, join, task, try_join};
+    use std::future::Future;
+    use std::pin::pin;
+
+    futures::executor::block_on(async {
+        let mut a = future::ready(1u8);
+        let b = future::ready(2u8);
+        assert_eq!(join!(&mut a, b).await, (1, 2));
+
+        let mut c = Box::pin(async { Ok::<u8, ()>(3) });
+        assert_eq!(try_join!(&mut c, async { Ok(4) }).await, Ok((3, 4)));
+    });
+
+    // A join which is dropped halfway leaves the future usable.
+    let mut cx = task::noop_context();
+    let mut slow = pin!(async {
+        future::yield_now().await;
+        5u8
+    });
+    {
+        let mut join = pin!(join!(slow.as_mut(), future::pending::<()>()));
+        assert!(join.as_mut().poll(&mut cx).is_pending());
+    }
+    assert!(slow.as_mut().poll(&mut cx).is_ready());
+}
+
+#[test]
+fn macros_accept_into_future() {
+    use async_macros::{future, join, join_catch, select, try_join};
+    use std::future::IntoFuture;
+
+    struct Request(u8);
+
+    impl IntoFuture for Request {
+        type Output = u8;
+        type IntoFuture = future::Ready<u8>;
+
+        fn into_future(self) -> Self::IntoFuture {
+            future::ready(self.0)
+        }
+    }
+
+    struct TryRequest(u8);
+
+    impl IntoFuture for TryRequest {
+        type Output = Result<u8, ()>;
+        type IntoFuture = future::Ready<Result<u8, ()>>;
+
+        fn into_future(self) -> Self::IntoFuture {
+            future::ready(Ok(self.0))
+        }
+    }
+
+    futures::executor::block_on(async {
+        let a = Request(1);
+        assert_eq!(join!(a, Request(2)).await, (1, 2));
+        assert_eq!(select!(Request(3), future::pending()).await, 3);
+        let b = TryRequest(4);
+        assert_eq!(try_join!(b, TryRequest(5)).await, Ok((4, 5)));
+        let c = Request(6);
+        let (c, d) = join_catch!(c, Request(7)).await;
+        assert_eq!((c.unwrap(), d.unwrap()), (6, 7));
+    });
+}
+
+#[test]
+fn select_as_converts_the_first_output() {
+    use async_macros::{future, select_as};
+
+    futures::executor::block_on(async {
+        let a = future::pending::<u8>();
+        let b = future::ready(2u16);
+        assert_eq!(select_as!(u64; a, b).await, 2u64);
+        assert_eq!(
+            select_as!(i64; future::ready(3i32), future::ready(4u8)).await,
+            3i64
+        );
+    });
+}
+
+#[test]
+fn timeout_errors_can_be_customized() {
+    use async_macros::future;
+    use async_macros::time::{self, Elapsed, Timer};
+    use std::time::Duration;
+
+    /// A timer whose sleeps complete right away.
+    struct Instant;
+
+    impl Timer for Instant {
+        type Sleep = future::Ready<()>;
+
+        fn sleep(&self, _duration: Duration) -> future::Ready<()> {
+            future::ready(())
+        }
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum Error {
+        Slow(&'static str),
+    }
+
+    futures::executor::block_on(async {
+        let duration = Duration::from_secs(1);
+
+        // A future which is ready wins against a deadline which has passed.
+        let ready = time::timeout(Instant, duration, future::ready(1u8));
+        assert_eq!(ready.await, Ok(1));
+
+        let pending = time::timeout(Instant, duration, future::pending::<u8>());
+        assert_eq!(pending.await, Err(Elapsed::new()));
+
+        let pending = time::timeout(&Instant, duration, future::pending::<u8>())
+            .with_error(|| Error::Slow("backend"));
+        assert_eq!(pending.await, Err(Error::Slow("backend")));
+    });
+}
+
+#[test]
+fn with_timeout_races_against_any_future() {
+    use async_macros::time::{self, Elapsed};
+    use async_macros::{future, task};
+    use std::future::Future;
+    use std::pin::pin;
+
+    let mut cx = task::noop_context();
+    let (sender, receiver) = async_macros::oneshot::channel::<()>();
+    let mut work = pin!(time::with_timeout(future::pending::<u8>(), receiver));
+    assert!(work.as_mut().poll(&mut cx).is_pending());
+    sender.send(()).unwrap();
+    assert_eq!(
+        work.as_mut().poll(&mut cx),
+        std::task::Poll::Ready(Err(Elapsed::new()))
+    );
+}
+
+#[test]
+fn join_gives_deadlines_to_individual_futures() {
+    use async_macros::time::{Elapsed, Timer};
+    use async_macros::{future, join};
+    use std::cell::RefCell;
+    use std::time::Duration;
+
+    /// A timer whose sleeps complete right away, recording their durations.
+    struct Instant(RefCell<Vec<Duration>>);
+
+    impl Timer for Instant {
+        type Sleep = future::Ready<()>;
+
+        fn sleep(&self, duration: Duration) -> future::Ready<()> {
+            self.0.borrow_mut().push(duration);
+            future::ready(())
+        }
+    }
+
+    let timer = Instant(RefCell::new(Vec::new()));
+    futures::executor::block_on(async {
+        let a = future::pending::<u8>();
+        let b = future::ready(2u8);
+        let output = join!(
+            timer = &timer;
+            a @ Duration::from_secs(1),
+            b,
+            async { 3u8 },
+            future::ready(4u8),
+        )
+        .await;
+        assert_eq!(output, (Err(Elapsed::new()), 2, 3, 4));
+    });
+    assert_eq!(*timer.0.borrow(), [Duration::from_secs(1)]);
+}
+
+#[test]
+fn boxed_join_stream_is_fused_until_pushed_into() {
+    use async_macros::{join_stream, task, BoxedJoinStream};
+    use futures::stream::{self, FusedStream, Stream};
+    use std::pin::Pin;
+    use std::task::Poll;
+
+    let mut cx = task::noop_context();
+    let mut streams = BoxedJoinStream::new();
+    assert_eq!(Pin::new(&mut streams).poll_next(&mut cx), Poll::Ready(None));
+    assert!(streams.is_terminated());
+
+    streams.push(stream::pending::<u8>());
+    streams.push(stream::iter(vec![1u8]));
+    assert!(!streams.is_terminated());
+    assert_eq!(
+        Pin::new(&mut streams).poll_next(&mut cx),
+        Poll::Ready(Some(1))
+    );
+    assert_eq!(Pin::new(&mut streams).poll_next(&mut cx), Poll::Pending);
+    // The stream which ended was dropped.
+    assert_eq!(streams.len(), 1);
+
+    let mut joined = join_stream!(boxed; stream::iter(vec![2u8]));
+    assert_eq!(
+        Pin::new(&mut joined).poll_next(&mut cx),
+        Poll::Ready(Some(2))
+    );
+    assert_eq!(Pin::new(&mut joined).poll_next(&mut cx), Poll::Ready(None));
+}
+
+#[test]
+fn throttle_delays_or_drops_early_items() {
+    use async_macros::time::{self, ThrottlePolicy, Timer};
+    use async_macros::{future, task};
+    use futures::stream::{self, Stream};
+    use std::pin::pin;
+    use std::task::Poll;
+    use std::time::Duration;
+
+    /// A timer whose sleeps never complete.
+    struct Never;
+
+    impl Timer for Never {
+        type Sleep = future::Pending<()>;
+
+        fn sleep(&self, _duration: Duration) -> future::Pending<()> {
+            future::pending()
+        }
+    }
+
+    let mut cx = task::noop_context();
+    let interval = Duration::from_secs(1);
+
+    let mut delayed = pin!(time::throttle(Never, stream::iter(vec![1u8, 2]), interval));
+    assert_eq!(delayed.as_mut().poll_next(&mut cx), Poll::Ready(Some(1)));
+    assert_eq!(delayed.as_mut().poll_next(&mut cx), Poll::Pending);
+
+    let dropped = time::throttle(Never, stream::iter(vec![1u8, 2, 3]), interval)
+        .with_policy(ThrottlePolicy::Drop);
+    let mut dropped = pin!(dropped);
+    assert_eq!(dropped.as_mut().poll_next(&mut cx), Poll::Ready(Some(1)));
+    assert_eq!(dropped.as_mut().poll_next(&mut cx), Poll::Ready(None));
+
+    // A stream which is always ready doesn't keep the task busy forever.
+    let repeated =
+        time::throttle(Never, stream::repeat(1u8), interval).with_policy(ThrottlePolicy::Drop);
+    let mut repeated = pin!(repeated);
+    assert_eq!(repeated.as_mut().poll_next(&mut cx), Poll::Ready(Some(1)));
+    assert_eq!(repeated.as_mut().poll_next(&mut cx), Poll::Pending);
+}
+
+#[test]
+fn debounce_yields_the_latest_item_once_quiet() {
+    use async_macros::task;
+    use async_macros::time::{self, Timer};
+    use futures::channel::mpsc;
+    use futures::stream::{FusedStream, Stream};
+    use std::cell::RefCell;
+    use std::future::Future;
+    use std::pin::{pin, Pin};
+    use std::rc::Rc;
+    use std::task::{Context, Poll};
+    use std::time::Duration;
+
+    /// A timer whose sleeps complete once `elapse` is called.
+    #[derive(Clone, Default)]
+    struct Manual(Rc<RefCell<Vec<Rc<RefCell<bool>>>>>);
+
+    impl Manual {
+        fn elapse(&self) {
+            for fired in self.0.borrow().iter() {
+                *fired.borrow_mut() = true;
+            }
+        }
+    }
+
+    struct ManualSleep(Rc<RefCell<bool>>);
+
+    impl Future for ManualSleep {
+        type Output = ();
+
+        fn poll(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<()> {
+            if *self.0.borrow() {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        }
+    }
+
+    impl Timer for Manual {
+        type Sleep = ManualSleep;
+
+        fn sleep(&self, _duration: Duration) -> ManualSleep {
+            let fired = Rc::new(RefCell::new(false));
+            self.0.borrow_mut().push(fired.clone());
+            ManualSleep(fired)
+        }
+    }
+
+    let mut cx = task::noop_context();
+    let timer = Manual::default();
+    let (sender, receiver) = mpsc::unbounded();
+    let mut debounced = pin!(time::debounce(&timer, receiver, Duration::from_secs(1)));
+
+    sender.unbounded_send(1u8).unwrap();
+    sender.unbounded_send(2u8).unwrap();
+    assert_eq!(debounced.as_mut().poll_next(&mut cx), Poll::Pending);
+    timer.elapse();
+    assert_eq!(debounced.as_mut().poll_next(&mut cx), Poll::Ready(Some(2)));
+    assert_eq!(debounced.as_mut().poll_next(&mut cx), Poll::Pending);
+
+    // The last item is yielded as soon as the stream ends.
+    sender.unbounded_send(3u8).unwrap();
+    drop(sender);
+    assert_eq!(debounced.as_mut().poll_next(&mut cx), Poll::Ready(Some(3)));
+    assert_eq!(debounced.as_mut().poll_next(&mut cx), Poll::Ready(None));
+    assert!(debounced.is_terminated());
+}
+
+#[test]
+fn idle_timeout_keeps_going_after_an_error() {
+    use async_macros::time::{self, Timer};
+    use async_macros::{future, task};
+    use futures::stream::{self, Stream};
+    use std::cell::Cell;
+    use std::pin::pin;
+    use std::task::Poll;
+    use std::time::Duration;
+
+    /// A timer whose sleeps complete right away, counting them.
+    struct Instant(Cell<usize>);
+
+    impl Timer for Instant {
+        type Sleep = future::Ready<()>;
+
+        fn sleep(&self, _duration: Duration) -> future::Ready<()> {
+            self.0.set(self.0.get() + 1);
+            future::ready(())
+        }
+    }
+
+    let mut cx = task::noop_context();
+    let timer = Instant(Cell::new(0));
+    let stream = futures::StreamExt::chain(stream::iter(vec![1u8]), stream::pending());
+    let stream =
+        time::idle_timeout(&timer, stream, Duration::from_secs(1)).with_error(|| "stalled");
+    let mut stream = pin!(stream);
+
+    // A stream which is ready never starts a wait.
+    assert_eq!(stream.as_mut().poll_next(&mut cx), Poll::Ready(Some(Ok(1))));
+    assert_eq!(timer.0.get(), 0);
+    assert_eq!(
+        stream.as_mut().poll_next(&mut cx),
+        Poll::Ready(Some(Err("stalled")))
+    );
+    assert_eq!(
+        stream.as_mut().poll_next(&mut cx),
+        Poll::Ready(Some(Err("stalled")))
+    );
+    assert_eq!(timer.0.get(), 2);
+}
+
+#[test]
+fn take_until_stops_before_ready_items() {
+    use async_macros::{future, take_until, task};
+    use futures::stream::{self, FusedStream, Stream};
+    use std::pin::pin;
+    use std::task::Poll;
+
+    let mut cx = task::noop_context();
+    let mut stream = pin!(take_until(stream::iter(vec![1u8, 2]), future::ready(())));
+    assert_eq!(stream.as_mut().poll_next(&mut cx), Poll::Ready(None));
+    assert!(stream.is_stopped());
+    assert!(stream.is_terminated());
+
+    let mut stream = pin!(take_until(stream::iter(vec![1u8]), future::pending::<()>()));
+    assert_eq!(stream.as_mut().poll_next(&mut cx), Poll::Ready(Some(1)));
+    assert_eq!(stream.as_mut().poll_next(&mut cx), Poll::Ready(None));
+    assert!(!stream.is_stopped());
+    assert!(stream.is_terminated());
+}
+
+#[test]
+fn skip_until_yields_to_the_executor_while_skipping() {
+    use async_macros::{future, skip_until, task};
+    use futures::stream::{self, Stream};
+    use std::pin::pin;
+    use std::task::{Context, Poll};
+
+    let mock = task::MockWaker::new();
+    let waker = mock.waker();
+    let mut cx = Context::from_waker(&waker);
+    let mut stream = pin!(skip_until(stream::repeat(1u8), future::pending::<()>()));
+    assert_eq!(stream.as_mut().poll_next(&mut cx), Poll::Pending);
+    assert_eq!(mock.wake_count(), 1);
+    assert!(!stream.is_started());
+
+    let mut stream = pin!(skip_until(stream::iter(vec![1u8, 2]), future::ready(())));
+    assert_eq!(stream.as_mut().poll_next(&mut cx), Poll::Ready(Some(1)));
+    assert!(stream.is_started());
+}
+
+#[test]
+fn next_or_leaves_the_stream_usable() {
+    use async_macros::{future, next_or, task, Either};
+    use futures::stream::{self, StreamExt};
+    use std::future::Future;
+    use std::pin::pin;
+    use std::task::Poll;
+
+    let mut cx = task::noop_context();
+    let mut numbers = stream::iter(vec![1u8, 2]);
+    assert_eq!(
+        pin!(next_or(&mut numbers, future::ready("shutdown"))).poll(&mut cx),
+        Poll::Ready(Either::Right("shutdown"))
+    );
+    assert_eq!(
+        pin!(next_or(&mut numbers, future::pending::<()>())).poll(&mut cx),
+        Poll::Ready(Either::Left(Some(1)))
+    );
+    assert_eq!(futures::executor::block_on(numbers.next()), Some(2));
+}
+
+#[cfg(feature = "futures-channel")]
+#[test]
+fn stream_select_disables_finished_channel_receivers() {
+    use async_macros::stream_select;
+    use futures::channel::{mpsc, oneshot};
+
+    futures::executor::block_on(async {
+        let (sender, mut messages) = mpsc::unbounded::<u8>();
+        let (stop, mut stopped) = oneshot::channel::<&str>();
+        sender.unbounded_send(1).unwrap();
+        stop.send("stop").unwrap();
+        drop(sender);
+
+        let mut log = vec![];
+        loop {
+            stream_select! {
+                Ok(reason) in stopped => log.push(reason.to_string()),
+                message in messages => log.push(message.to_string()),
+                complete => break,
+            }
+        }
+        assert_eq!(log, ["stop", "1"]);
+
+        // A receiver whose sender was dropped disables its branch without
+        // matching.
+        let (stop, mut stopped) = oneshot::channel::<()>();
+        drop(stop);
+        let mut matched = false;
+        loop {
+            stream_select! {
+                _ in stopped => matched = true,
+                complete => break,
+            }
+        }
+        assert!(!matched);
+    });
+}
+
+#[test]
+fn try_select_reports_the_index_of_the_result() {
+    use async_macros::rng::{SeedableRng, SmallRng};
+    use async_macros::{future, try_select};
+
+    futures::executor::block_on(async {
+        let a = future::pending::<Result<u8, &str>>();
+        let b = future::ready(Ok(2u8));
+        assert_eq!(try_select!(indexed; a, b).await, Ok((1, 2)));
+
+        let output =
+            try_select!(indexed; fair; future::ready(Err::<u8, _>("a")), future::ready(Err("b")))
+                .await;
+        assert_eq!(output, Err((0, "a")));
+
+        let mut rng = SmallRng::seed_from_u64(7);
+        let output = try_select!(indexed; rng = &mut rng; future::pending(), future::ready(Ok::<_, ()>(3u8))).await;
+        assert_eq!(output, Ok((1, 3)));
+    });
+}
+
+#[test]
+fn try_select_error_policies_follow_the_argument_order() {
+    use async_macros::{future, task, try_select};
+    use std::future::Future;
+    use std::pin::pin;
+    use std::task::Poll;
+
+    futures::executor::block_on(async {
+        let a = future::ready(Err::<u8, _>("a"));
+        let b = future::ready(Err::<u8, _>("b"));
+        assert_eq!(try_select!(errors = first; a, b).await, Err("a"));
+
+        let a = future::ready(Err::<u8, _>("a"));
+        let b = future::ready(Err::<u8, _>("b"));
+        assert_eq!(try_select!(errors = last; fair; a, b).await, Err("b"));
+
+        let a = future::ready(Err::<u8, _>("a"));
+        let b = future::ready(Err::<u8, _>("b"));
+        let output = try_select!(fair; errors = all; indexed; a, b).await;
+        assert_eq!(output, Err(["a", "b"]));
+
+        let output = try_select!(errors = last; indexed; future::ready(Err::<u8, _>("a")), async {
+            Err("b")
+        });
+        assert_eq!(output.await, Err((1, "b")));
+    });
+
+    // The first error in the list wins even if it comes in last.
+    let mut cx = task::noop_context();
+    let slow = async {
+        future::yield_now().await;
+        Err::<u8, _>("slow")
+    };
+    let mut select = pin!(try_select!(errors = first; slow, future::ready(Err("fast"))));
+    assert_eq!(select.as_mut().poll(&mut cx), Poll::Pending);
+    assert_eq!(select.as_mut().poll(&mut cx), Poll::Ready(Err("slow")));
+}
+
+#[test]
+fn try_join_tags_the_error_with_the_failing_future() {
+    use async_macros::{future, try_join, Cancelled, TaggedError};
+
+    futures::executor::block_on(async {
+        let a = future::ready(Ok::<u8, &str>(1));
+        let b = future::ready(Ok::<u8, &str>(2));
+        assert_eq!(try_join!(tagged; a, b).await, Ok((1, 2)));
+
+        let output =
+            try_join!(tagged; future::ready(Ok::<u8, _>(1)), async { Err::<u8, _>("b") }).await;
+        assert_eq!(
+            output,
+            Err(TaggedError::new(1, "async { Err::<u8, _>(\"b\") }", "b"))
+        );
+
+        let mut cancelled = Vec::new();
+        let slow = future::pending::<Result<u8, &str>>();
+        let broken = future::ready(Err::<u8, _>("broken"));
+        let output = try_join!(
+            tagged;
+            on_cancel = |child: Cancelled| cancelled.push(child.index());
+            slow, broken
+        )
+        .await;
+        let err = output.unwrap_err();
+        assert_eq!(
+            (err.index(), err.name(), *err.error()),
+            (1, "broken", "broken")
+        );
+        assert_eq!(cancelled, [0]);
+
+        let hooks = std::cell::Cell::new(0);
+        let slow = future::pending::<Result<u8, &str>>();
+        let broken = future::ready(Err::<u8, _>("broken"));
+        let output = try_join!(
+            tagged;
+            on_cancel_async = |_: Cancelled| async { hooks.set(hooks.get() + 1) };
+            slow, broken
+        )
+        .await;
+        assert_eq!(
+            output.unwrap_err().to_string(),
+            "future `broken` at index 1 failed"
+        );
+        assert_eq!(hooks.get(), 1);
+    });
+}
+
+#[test]
+fn thread_timer_never_fires_for_unrepresentable_deadlines() {
+    use async_macros::task;
+    use async_macros::time::{ThreadTimer, Timer};
+    use std::future::Future;
+    use std::pin::pin;
+    use std::time::Duration;
+
+    let mut sleep = pin!(ThreadTimer.sleep(Duration::MAX));
+    let mut cx = task::noop_context();
+    assert!(sleep.as_mut().poll(&mut cx).is_pending());
+    assert!(sleep.as_mut().poll(&mut cx).is_pending());
+}
+
+#[test]
+fn futures_unordered_wakes_its_task_outside_of_the_lock() {
+    use async_macros::FuturesUnordered;
+    use futures::stream::Stream;
+    use std::pin::Pin;
+    use std::sync::{Arc, Mutex};
+    use std::task::{Context, Poll, Wake, Waker};
+
+    /// A waker which wakes another waker in turn, once.
+    struct Relay(Mutex<Option<Waker>>);
+
+    impl Wake for Relay {
+        fn wake(self: Arc<Self>) {
+            self.wake_by_ref();
+        }
+
+        fn wake_by_ref(self: &Arc<Self>) {
+            let next = self.0.lock().unwrap().take();
+            if let Some(next) = next {
+                next.wake();
+            }
+        }
+    }
+
+    let stashed = Arc::new(Mutex::new(Vec::<Waker>::new()));
+    let mut set = FuturesUnordered::new();
+    for _ in 0..2 {
+        let slot = stashed.clone();
+        set.push(std::future::poll_fn(move |cx| {
+            slot.lock().unwrap().push(cx.waker().clone());
+            Poll::<u8>::Pending
+        }));
+    }
+
+    let relay = Arc::new(Relay(Mutex::new(None)));
+    let waker = Waker::from(relay.clone());
+    let mut cx = Context::from_waker(&waker);
+    assert!(Pin::new(&mut set).poll_next(&mut cx).is_pending());
+
+    // Waking the first future wakes the task, which wakes the second one.
+    let mut children = stashed.lock().unwrap().split_off(0);
+    *relay.0.lock().unwrap() = children.pop();
+    children.pop().unwrap().wake();
+    assert!(Pin::new(&mut set).poll_next(&mut cx).is_pending());
+}
+
+#[test]
+#[cfg(feature = "waker-routing")]
+fn routed_wakers_wake_the_task_outside_of_the_lock() {
+    use async_macros::join;
+    use std::future::Future;
+    use std::pin::pin;
+    use std::sync::{Arc, Mutex};
+    use std::task::{Context, Poll, Wake, Waker};
+
+    /// A waker which wakes another waker in turn, once.
+    struct Relay(Mutex<Option<Waker>>);
+
+    impl Wake for Relay {
+        fn wake(self: Arc<Self>) {
+            self.wake_by_ref();
+        }
+
+        fn wake_by_ref(self: &Arc<Self>) {
+            let next = self.0.lock().unwrap().take();
+            if let Some(next) = next {
+                next.wake();
+            }
+        }
+    }
+
+    let stashed = Mutex::new(Vec::<Waker>::new());
+    let stash = |cx: &mut Context<'_>| {
+        stashed.lock().unwrap().push(cx.waker().clone());
+        Poll::<u8>::Pending
+    };
+    let a = std::future::poll_fn(stash);
+    let b = std::future::poll_fn(stash);
+    let mut joined = pin!(join!(a, b));
+
+    let relay = Arc::new(Relay(Mutex::new(None)));
+    let waker = Waker::from(relay.clone());
+    let mut cx = Context::from_waker(&waker);
+    assert!(joined.as_mut().poll(&mut cx).is_pending());
+
+    // Waking the first future wakes the task, which wakes the second one.
+    let mut children = stashed.lock().unwrap().split_off(0);
+    *relay.0.lock().unwrap() = children.pop();
+    children.pop().unwrap().wake();
+    assert!(joined.as_mut().poll(&mut cx).is_pending());
+}
+
+#[test]
+fn futures_passed_as_expressions_are_named_after_them() {
+    use async_macros::{future, try_join, Cancelled};
+
+    futures::executor::block_on(async {
+        let mut cancelled = Vec::new();
+        let slow = future::pending::<Result<u8, &str>>();
+        let output = try_join!(
+            on_cancel = |child: Cancelled| cancelled.push(child.name());
+            slow,
+            future::pending::<Result<u8, &str>>(),
+            future::ready(Err::<u8, _>("broken"))
+        )
+        .await;
+        assert_eq!(output, Err("broken"));
+        assert_eq!(cancelled, ["slow", "future::pending::<Result<u8, &str>>()"]);
+    });
+}
+
+#[test]
+fn task_local_providers_can_follow_doc_comments() {
+    use async_macros::task::{TaskLocalProvider, TaskLocals};
+    use async_macros::task_local;
+    use std::cell::Cell;
+
+    thread_local! {
+        static LOCALS: TaskLocals = TaskLocals::new();
+    }
+
+    struct PerThread;
+
+    impl TaskLocalProvider for PerThread {
+        fn with_current<F, R>(f: F) -> R
+        where
+            F: FnOnce(Option<&TaskLocals>) -> R,
+        {
+            LOCALS.with(|locals| f(Some(locals)))
+        }
+    }
+
+    task_local! {
+        /// Documented before the provider.
+        #[provider(PerThread)]
+        #[allow(dead_code)]
+        static FIRST: Cell<u8> = Cell::new(1);
+
+        /// Documented without a provider.
+        static SECOND: Cell<u8> = Cell::new(2);
+    }
+
+    assert_eq!(FIRST.with(Cell::get), 1);
+    assert!(SECOND.try_with(Cell::get).is_err());
+}
+
+#[test]
+fn atomic_waker_can_be_woken_while_registering() {
+    use async_macros::task::AtomicWaker;
+    use std::ptr;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::task::{RawWaker, RawWakerVTable, Waker};
+
+    // Waking the slot from within `clone` stands in for an interrupt handler
+    // waking it in the middle of `register`.
+    static SLOT: AtomicWaker = AtomicWaker::new();
+    static WAKES: AtomicUsize = AtomicUsize::new(0);
+    static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, wake, wake, noop);
+
+    fn clone(_: *const ()) -> RawWaker {
+        SLOT.wake();
+        RawWaker::new(ptr::null(), &VTABLE)
+    }
+
+    fn wake(_: *const ()) {
+        WAKES.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn noop(_: *const ()) {}
+
+    // SAFETY: the vtable does nothing with the data pointer.
+    let waker = unsafe { Waker::from_raw(RawWaker::new(ptr::null(), &VTABLE)) };
+    SLOT.register(&waker);
+    assert_eq!(WAKES.load(Ordering::SeqCst), 1);
+    assert!(SLOT.take().is_none());
+
+    SLOT.register(&waker);
+    assert_eq!(WAKES.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn task_locals_are_not_visible_from_other_threads() {
+    use async_macros::{task, task_local};
+
+    task_local! {
+        static ID: u32 = 7;
+    }
+
+    futures::executor::block_on(task::with_locals(async {
+        assert_eq!(ID.with(|id| *id), 7);
+        // The task is being polled, but only on this thread.
+        let seen = std::thread::scope(|s| s.spawn(|| ID.try_with(|id| *id)).join().unwrap());
+        assert!(seen.is_err());
+    }));
+}
+
+#[test]
+fn waker_sets_survive_removing_a_key_twice() {
+    use async_macros::task::{MockWaker, WakerSet};
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    let set = WakerSet::new();
+    let waker = MockWaker::new();
+    let key = set.insert(&waker.waker());
+    assert!(!set.remove(key));
+    assert!(catch_unwind(AssertUnwindSafe(|| set.remove(key))).is_err());
+
+    // The key went on the free list once, so two new entries get two keys.
+    let a = set.insert(&waker.waker());
+    let b = set.insert(&waker.waker());
+    assert_ne!(a, b);
+}